@@ -0,0 +1,72 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use department::alloc::GlobalAlloc;
+use department::base::Storage;
+use department::collections::{LinkedList, Vec};
+use department::heap::VirtHeap;
+use department::inline::SingleInline;
+
+const PUSHES: usize = 256;
+
+fn push_all<S>(storage: S) -> Vec<u64, S>
+where
+    S: Storage,
+{
+    let mut v = Vec::new_in(storage);
+    for i in 0..PUSHES as u64 {
+        v.push(black_box(i));
+    }
+    v
+}
+
+/// Push onto an empty vector, so the cost of each storage growing is included
+fn vec_push(c: &mut Criterion) {
+    let mut group = c.benchmark_group("vec_push");
+    group.throughput(Throughput::Elements(PUSHES as u64));
+
+    group.bench_function(BenchmarkId::from_parameter("global"), |b| {
+        b.iter(|| push_all(GlobalAlloc::default()));
+    });
+    group.bench_function(BenchmarkId::from_parameter("inline"), |b| {
+        b.iter(|| push_all(SingleInline::<[u64; PUSHES]>::new()));
+    });
+    group.bench_function(BenchmarkId::from_parameter("heap"), |b| {
+        let heap = VirtHeap::<u64, { PUSHES * 2 }>::new();
+        b.iter(|| push_all(&heap));
+    });
+
+    group.finish();
+}
+
+/// Build a list node by node, then drop it, allocating and freeing one node per element
+fn linked_list_churn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("linked_list_churn");
+    for len in [16usize, 256] {
+        group.throughput(Throughput::Elements(len as u64));
+
+        group.bench_with_input(BenchmarkId::new("global", len), &len, |b, &len| {
+            b.iter(|| {
+                let mut list = LinkedList::<u64, GlobalAlloc>::new();
+                for i in 0..len as u64 {
+                    list.push(black_box(i));
+                }
+                list
+            });
+        });
+        group.bench_with_input(BenchmarkId::new("heap", len), &len, |b, &len| {
+            let heap = VirtHeap::<u64, 2048>::new();
+            b.iter(|| {
+                let mut list = LinkedList::new_in(&heap);
+                for i in 0..len as u64 {
+                    list.push(black_box(i));
+                }
+                list
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, vec_push, linked_list_churn);
+criterion_main!(benches);
@@ -0,0 +1,96 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use department::base::MultiItemStorage;
+use department::heap::VirtHeap;
+
+const BLOCKS: usize = 4096;
+
+type Heap = VirtHeap<u64, BLOCKS>;
+
+/// Allocate and immediately free a single allocation, the best case for reusing freed blocks
+fn heap_alloc_free(c: &mut Criterion) {
+    let mut group = c.benchmark_group("heap_alloc_free");
+    for len in [1usize, 8, 64] {
+        group.throughput(Throughput::Elements(1));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &len, |b, &len| {
+            let heap = Heap::new();
+            let mut storage = &heap;
+            b.iter(|| {
+                let handle = storage.allocate::<[u64]>(black_box(len)).unwrap();
+                // SAFETY: Handle was just allocated from this storage
+                unsafe { storage.deallocate(handle) };
+            });
+        });
+    }
+    group.finish();
+}
+
+/// Fill the heap with small allocations, then free all of them
+fn heap_fill_drain(c: &mut Criterion) {
+    let mut group = c.benchmark_group("heap_fill_drain");
+    let count = BLOCKS / 4;
+    group.throughput(Throughput::Elements(count as u64));
+    group.bench_function("fill_drain", |b| {
+        let heap = Heap::new();
+        let mut storage = &heap;
+        let mut handles = Vec::with_capacity(count);
+        b.iter(|| {
+            for _ in 0..count {
+                handles.push(storage.allocate::<[u64]>(4).unwrap());
+            }
+            for handle in handles.drain(..) {
+                // SAFETY: Handle was allocated from this storage and not yet freed
+                unsafe { storage.deallocate(handle) };
+            }
+        });
+    });
+    group.finish();
+}
+
+/// Free every other allocation of a full heap, then allocate into the gaps with mixed sizes. This
+/// is the case that the fit strategy of the heap matters most for.
+fn heap_fragmented(c: &mut Criterion) {
+    let mut group = c.benchmark_group("heap_fragmented");
+    let count = BLOCKS / 8;
+    group.throughput(Throughput::Elements((count / 2) as u64));
+    group.bench_function("refill_gaps", |b| {
+        let heap = Heap::new();
+        b.iter_batched_ref(
+            || {
+                let mut storage = &heap;
+                let mut handles = Vec::with_capacity(count);
+                for i in 0..count {
+                    let len = if i % 3 == 0 { 2 } else { 6 };
+                    handles.push(storage.allocate::<[u64]>(len).unwrap());
+                }
+                let mut kept = Vec::with_capacity(count);
+                for (i, handle) in handles.into_iter().enumerate() {
+                    if i % 2 == 0 {
+                        // SAFETY: Handle was allocated from this storage and not yet freed
+                        unsafe { storage.deallocate(handle) };
+                    } else {
+                        kept.push(handle);
+                    }
+                }
+                kept
+            },
+            |kept| {
+                let mut storage = &heap;
+                for i in 0..count / 2 {
+                    let len = if i % 2 == 0 { 2 } else { 4 };
+                    kept.push(storage.allocate::<[u64]>(len).unwrap());
+                }
+                for handle in kept.drain(..) {
+                    // SAFETY: Handle was allocated from this storage and not yet freed
+                    unsafe { storage.deallocate(handle) };
+                }
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    group.finish();
+}
+
+criterion_group!(benches, heap_alloc_free, heap_fill_drain, heap_fragmented);
+criterion_main!(benches);
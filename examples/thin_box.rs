@@ -94,7 +94,7 @@ impl<T: ?Sized, S: Storage> Deref for ThinBox<T, S> {
         let ptr = unsafe { self.storage.get(handle) };
         let meta = unsafe { *ptr.as_ptr() };
         let data_ptr = unsafe { NonNull::new_unchecked(ptr.as_ptr().add(1)) };
-        unsafe { NonNull::from_raw_parts(data_ptr.cast(), meta).as_ref() }
+        unsafe { NonNull::from_raw_parts(data_ptr.cast::<()>(), meta).as_ref() }
     }
 }
 
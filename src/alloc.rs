@@ -12,6 +12,7 @@ use core::alloc::{Allocator, Layout};
 #[cfg(feature = "unsize")]
 use core::marker::Unsize;
 use core::ptr::{NonNull, Pointee};
+use core::sync::atomic::{AtomicUsize, Ordering};
 use rs_alloc::alloc::Global;
 
 use crate::base::{
@@ -87,6 +88,13 @@ unsafe impl<A: Allocator> Storage for Alloc<A> {
         <Self as MultiItemStorage>::allocate(self, meta)
     }
 
+    fn allocate_single_zeroed<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> error::Result<Self::Handle<T>> {
+        <Self as MultiItemStorage>::allocate_zeroed(self, meta)
+    }
+
     unsafe fn deallocate_single<T: ?Sized + Pointee>(&mut self, handle: Self::Handle<T>) {
         // SAFETY: Shares our safety requirements
         unsafe { <Self as MultiItemStorage>::deallocate(self, handle) }
@@ -114,7 +122,7 @@ unsafe impl<A: Allocator> Storage for Alloc<A> {
                 })?
         };
 
-        Ok(NonNull::from_raw_parts(new_ptr.cast(), capacity))
+        Ok(NonNull::from_raw_parts(new_ptr.cast::<()>(), capacity))
     }
 
     unsafe fn try_shrink<T>(
@@ -136,7 +144,7 @@ unsafe impl<A: Allocator> Storage for Alloc<A> {
                 .map_err(|_| StorageError::Unimplemented)?
         };
 
-        Ok(NonNull::from_raw_parts(new_ptr.cast(), capacity))
+        Ok(NonNull::from_raw_parts(new_ptr.cast::<()>(), capacity))
     }
 }
 
@@ -146,7 +154,7 @@ unsafe impl<A: Allocator> MultiItemStorage for Alloc<A> {
         &mut self,
         meta: T::Metadata,
     ) -> error::Result<Self::Handle<T>> {
-        let layout = utils::layout_of::<T>(meta);
+        let layout = utils::layout_of::<T>(meta)?;
 
         let allocated: NonNull<()> = self
             .0
@@ -160,9 +168,27 @@ unsafe impl<A: Allocator> MultiItemStorage for Alloc<A> {
         Ok(NonNull::from_raw_parts(allocated, meta))
     }
 
+    fn allocate_zeroed<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> error::Result<Self::Handle<T>> {
+        let layout = utils::layout_of::<T>(meta)?;
+
+        let allocated: NonNull<()> = self
+            .0
+            .allocate_zeroed(layout)
+            .map_err(|_| StorageError::InsufficientSpace {
+                expected: layout.size(),
+                available: None,
+            })?
+            .cast();
+
+        Ok(NonNull::from_raw_parts(allocated, meta))
+    }
+
     unsafe fn deallocate<T: ?Sized + Pointee>(&mut self, handle: Self::Handle<T>) {
         // SAFETY: By deallocation's safety requirements, the handle is valid at this point
-        let layout = unsafe { Layout::for_value_raw(handle.as_ptr()) };
+        let layout = unsafe { self.layout_of_handle(handle) };
         // SAFETY: Our requirements are at least as strict as `Allocator::deallocate`
         unsafe { self.0.deallocate(handle.cast(), layout) };
     }
@@ -181,6 +207,225 @@ unsafe impl<A: Allocator + Clone> FromLeakedStorage for Alloc<A> {
     }
 }
 
+/// An [`Allocator`] backed storage with a limit on the total number of bytes allocated through it
+/// at once. Allocations that would go over the limit fail with
+/// [`StorageError::InsufficientSpace`].
+///
+/// The storage is implemented on references to this type, so many collections can share one
+/// budget, in the same way as `VirtHeap`.
+pub struct BudgetedAlloc<A: Allocator> {
+    alloc: A,
+    limit: usize,
+    used: AtomicUsize,
+}
+
+impl<A: Allocator> BudgetedAlloc<A> {
+    /// Create a new [`BudgetedAlloc`] which allows at most `limit` bytes to be allocated from
+    /// the provided allocator at once.
+    pub const fn new(alloc: A, limit: usize) -> BudgetedAlloc<A> {
+        BudgetedAlloc {
+            alloc,
+            limit,
+            used: AtomicUsize::new(0),
+        }
+    }
+
+    /// The maximum number of bytes that can be allocated at once
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// The number of bytes currently allocated
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    fn reserve(&self, size: usize) -> error::Result<()> {
+        self.used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(size).filter(|&new| new <= self.limit)
+            })
+            .map(|_| ())
+            .map_err(|used| StorageError::InsufficientSpace {
+                expected: size,
+                available: Some(self.limit.saturating_sub(used)),
+            })
+    }
+
+    fn release(&self, size: usize) {
+        self.used.fetch_sub(size, Ordering::Relaxed);
+    }
+
+    fn allocate_with<T: ?Sized + Pointee>(
+        &self,
+        meta: T::Metadata,
+        allocate: impl FnOnce(&A, Layout) -> Result<NonNull<[u8]>, core::alloc::AllocError>,
+    ) -> error::Result<NonNull<T>> {
+        let layout = utils::layout_of::<T>(meta)?;
+        self.reserve(layout.size())?;
+
+        match allocate(&self.alloc, layout) {
+            Ok(allocated) => Ok(NonNull::from_raw_parts(allocated.cast::<()>(), meta)),
+            Err(_) => {
+                self.release(layout.size());
+                Err(StorageError::InsufficientSpace {
+                    expected: layout.size(),
+                    available: None,
+                })
+            }
+        }
+    }
+}
+
+impl<A: Allocator + Default> Default for BudgetedAlloc<A> {
+    /// Create a new [`BudgetedAlloc`] with no limit
+    fn default() -> Self {
+        BudgetedAlloc::new(A::default(), usize::MAX)
+    }
+}
+
+// SAFETY: `Allocator` safety requirements are a superset of `Storage` currently, and the budget
+//         only ever causes extra failures
+unsafe impl<A: Allocator> Storage for &BudgetedAlloc<A> {
+    type Handle<T: ?Sized + Pointee> = NonNull<T>;
+
+    unsafe fn get<T: ?Sized + Pointee>(&self, handle: Self::Handle<T>) -> NonNull<T> {
+        handle
+    }
+
+    fn from_raw_parts<T: ?Sized + Pointee>(
+        handle: Self::Handle<()>,
+        meta: T::Metadata,
+    ) -> Self::Handle<T> {
+        <Self::Handle<T>>::from_raw_parts(handle, meta)
+    }
+
+    fn cast<T: ?Sized + Pointee, U>(handle: Self::Handle<T>) -> Self::Handle<U> {
+        handle.cast::<U>()
+    }
+
+    fn cast_unsized<T: ?Sized + Pointee, U: ?Sized + Pointee<Metadata = T::Metadata>>(
+        handle: Self::Handle<T>,
+    ) -> Self::Handle<U> {
+        let (ptr, meta) = handle.to_raw_parts();
+        NonNull::from_raw_parts(ptr, meta)
+    }
+
+    #[cfg(feature = "unsize")]
+    fn coerce<T: ?Sized + Pointee + Unsize<U>, U: ?Sized + Pointee>(
+        handle: Self::Handle<T>,
+    ) -> Self::Handle<U> {
+        handle
+    }
+
+    fn allocate_single<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> error::Result<Self::Handle<T>> {
+        <Self as MultiItemStorage>::allocate(self, meta)
+    }
+
+    fn allocate_single_zeroed<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> error::Result<Self::Handle<T>> {
+        <Self as MultiItemStorage>::allocate_zeroed(self, meta)
+    }
+
+    unsafe fn deallocate_single<T: ?Sized + Pointee>(&mut self, handle: Self::Handle<T>) {
+        // SAFETY: Shares our safety requirements
+        unsafe { <Self as MultiItemStorage>::deallocate(self, handle) }
+    }
+
+    unsafe fn try_grow<T>(
+        &mut self,
+        handle: Self::Handle<[T]>,
+        capacity: usize,
+    ) -> error::Result<Self::Handle<[T]>> {
+        let old_len = handle.to_raw_parts().1;
+
+        let old_layout = Layout::array::<T>(old_len).expect("Valid handle");
+        let new_layout = Layout::array::<T>(capacity).map_err(|_| StorageError::exceeds_max())?;
+        let extra = new_layout.size() - old_layout.size();
+        self.reserve(extra)?;
+
+        // SAFETY: Our safety requirements are at least as specific as `grow`, and layouts are
+        //         generated to match
+        let new_ptr = unsafe { self.alloc.grow(handle.cast(), old_layout, new_layout) };
+        match new_ptr {
+            Ok(new_ptr) => Ok(NonNull::from_raw_parts(new_ptr.cast::<()>(), capacity)),
+            Err(_) => {
+                self.release(extra);
+                Err(StorageError::InsufficientSpace {
+                    expected: new_layout.size(),
+                    available: None,
+                })
+            }
+        }
+    }
+
+    unsafe fn try_shrink<T>(
+        &mut self,
+        handle: Self::Handle<[T]>,
+        capacity: usize,
+    ) -> error::Result<Self::Handle<[T]>> {
+        let old_len = handle.to_raw_parts().1;
+
+        let old_layout = Layout::array::<T>(old_len).expect("Valid handle");
+        let new_layout = Layout::array::<T>(capacity).map_err(|_| StorageError::exceeds_max())?;
+
+        // SAFETY: Our safety requirements are at least as specific as `grow`, and layouts are
+        //         generated to match
+        let new_ptr = unsafe {
+            self.alloc
+                .shrink(handle.cast(), old_layout, new_layout)
+                // Should probably only fail if shrinking isn't supported
+                .map_err(|_| StorageError::Unimplemented)?
+        };
+        self.release(old_layout.size() - new_layout.size());
+
+        Ok(NonNull::from_raw_parts(new_ptr.cast::<()>(), capacity))
+    }
+}
+
+// SAFETY: Rust requires that implementors of `Allocator` are multi-item currently
+unsafe impl<A: Allocator> MultiItemStorage for &BudgetedAlloc<A> {
+    fn allocate<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> error::Result<Self::Handle<T>> {
+        self.allocate_with::<T>(meta, A::allocate)
+    }
+
+    fn allocate_zeroed<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> error::Result<Self::Handle<T>> {
+        self.allocate_with::<T>(meta, A::allocate_zeroed)
+    }
+
+    unsafe fn deallocate<T: ?Sized + Pointee>(&mut self, handle: Self::Handle<T>) {
+        // SAFETY: By deallocation's safety requirements, the handle is valid at this point
+        let layout = unsafe { self.layout_of_handle(handle) };
+        // SAFETY: Our requirements are at least as strict as `Allocator::deallocate`
+        unsafe { self.alloc.deallocate(handle.cast(), layout) };
+        self.release(layout.size());
+    }
+}
+
+// SAFETY: All references share the same allocator and budget
+unsafe impl<A: Allocator> ClonesafeStorage for &BudgetedAlloc<A> {}
+
+// SAFETY: Rust requires that implementors of `Allocator` are leak-safe currently
+unsafe impl<A: Allocator> LeaksafeStorage for &BudgetedAlloc<A> {}
+
+// SAFETY: Rust `Allocator` uses a `NonNull` as its handle type, this works trivially
+unsafe impl<A: Allocator> FromLeakedStorage for &BudgetedAlloc<A> {
+    unsafe fn unleak_ptr<T: ?Sized>(&self, leaked: *mut T) -> Self::Handle<T> {
+        NonNull::new(leaked).unwrap()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::boxed::Box;
@@ -206,4 +451,46 @@ mod tests {
 
         assert_eq!(&*v, &[1, 2, 3, 4]);
     }
+
+    #[test]
+    fn test_budget() {
+        let budget = BudgetedAlloc::new(Global, 32);
+
+        let b = Box::new_in([0u64; 2], &budget);
+        assert_eq!(budget.used(), 16);
+        assert!(Box::try_new_in([0u64; 3], &budget).is_err());
+
+        let mut v = Vec::<u32, _>::with_capacity_in(2, &budget);
+        v.extend([1, 2]);
+        assert_eq!(budget.used(), 24);
+        // Growing past 4 elements needs more space than is left
+        let (_, item, mut rest) = v.try_extend([3, 4, 5]).unwrap_err();
+        assert_eq!(item, 5);
+        assert_eq!(rest.next(), None);
+        assert_eq!(budget.used(), 32);
+
+        drop(b);
+        assert_eq!(budget.used(), 16);
+        v.push(5);
+        assert_eq!(&*v, &[1, 2, 3, 4, 5]);
+        assert_eq!(budget.used(), 32);
+
+        v.shrink_to_fit();
+        assert_eq!(budget.used(), 20);
+        drop(v);
+        assert_eq!(budget.used(), 0);
+    }
+
+    #[test]
+    fn test_oversized_meta() {
+        let mut alloc = Alloc(Global);
+        let res = alloc.allocate_single::<[u64]>(usize::MAX / 2);
+        assert!(matches!(
+            res,
+            Err(StorageError::InsufficientSpace {
+                available: Some(usize::MAX),
+                ..
+            })
+        ));
+    }
 }
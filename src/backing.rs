@@ -58,6 +58,18 @@ impl<const N: usize, A: Align> Backing<N, A> {
     pub const fn new() -> Backing<N, A> {
         Backing([0; N], [])
     }
+
+    /// Whether a value of type `T` fits in this backing, both in size and alignment. This is
+    /// usable in const contexts, allowing compile-time checks that a storage is large enough:
+    ///
+    /// ```
+    /// # use department::backing::{Align8, Backing};
+    /// const _: () = assert!(Backing::<16, Align8>::fits::<[u64; 2]>());
+    /// ```
+    pub const fn fits<T>() -> bool {
+        mem::size_of::<T>() <= mem::size_of::<Self>()
+            && mem::align_of::<T>() <= mem::align_of::<Self>()
+    }
 }
 
 impl<const N: usize, A: Align> Default for Backing<N, A> {
@@ -105,4 +117,15 @@ mod tests {
         assert_eq!(mem::size_of::<Backing16>(), 16);
         assert_eq!(mem::align_of::<Backing16>(), 16);
     }
+
+    #[test]
+    fn test_fits() {
+        const { assert!(Backing::<8, Align4>::fits::<[u32; 2]>()) };
+
+        assert!(Backing::<8, Align4>::fits::<u8>());
+        assert!(!Backing::<8, Align4>::fits::<[u32; 3]>());
+        assert!(!Backing::<8, Align4>::fits::<u64>());
+        // Size is rounded up to the alignment
+        assert!(Backing::<3, Align4>::fits::<u32>());
+    }
 }
@@ -4,6 +4,7 @@
 //! They are separated to allow implementations to be as specific or general as they wish in
 //! what they support.
 
+use core::alloc::Layout;
 #[cfg(feature = "unsize")]
 use core::marker::Unsize;
 #[cfg(feature = "unsize")]
@@ -18,7 +19,7 @@ use crate::handles::Handle;
 macro_rules! create_drop {
     ($create:ident, $create_range:ident, $create_dyn:ident, $drop:ident; $allocate:ident, $deallocate:ident) => {
         /// Attempt to allocate an item into this storage, and initialize it with the provided `T`.
-        fn $create<T: Pointee>(
+        fn $create<T>(
             &mut self,
             value: T,
         ) -> core::result::Result<Self::Handle<T>, (StorageError, T)> {
@@ -92,6 +93,57 @@ macro_rules! create_drop {
     };
 }
 
+/// Copy the bytes of an item from an allocation in one storage to an allocation in another. The
+/// number of bytes copied is the size of the source item, as given by
+/// [`Storage::layout_of_handle`].
+///
+/// This is a bitwise copy - if `T` isn't [`Copy`], only one of the two items may be used or
+/// dropped afterwards.
+///
+/// # Safety
+///
+/// - Both handles must be valid for their storages. See [`Storage::Handle`]
+/// - The destination allocation must be at least as large as the source item
+/// - The two allocations must not overlap
+pub unsafe fn copy_between<S1, S2, T>(
+    src: &S1,
+    src_handle: S1::Handle<T>,
+    dst: &S2,
+    dst_handle: S2::Handle<T>,
+) where
+    S1: ?Sized + Storage,
+    S2: ?Sized + Storage,
+    T: ?Sized,
+{
+    // SAFETY: `src_handle` is valid by safety requirements
+    let size = unsafe { src.layout_of_handle(src_handle) }.size();
+    // SAFETY: Both handles are valid by safety requirements
+    let (src_ptr, dst_ptr) = unsafe { (src.get(src_handle), dst.get(dst_handle)) };
+    // SAFETY: The destination fits `size` bytes and doesn't overlap the source, by safety
+    //         requirements
+    unsafe {
+        ptr::copy_nonoverlapping(
+            src_ptr.as_ptr().cast::<u8>(),
+            dst_ptr.as_ptr().cast::<u8>(),
+            size,
+        )
+    };
+}
+
+/// Set all the bytes of an allocation to zero
+///
+/// # Safety
+///
+/// The provided handle must be valid. See [`Storage::Handle`].
+unsafe fn zero_handle<S: ?Sized + Storage, T: ?Sized>(storage: &S, handle: S::Handle<T>) {
+    // SAFETY: `handle` is valid by safety requirements
+    let pointer = unsafe { storage.get(handle) };
+    // SAFETY: `handle` is valid by safety requirements
+    let size = unsafe { storage.layout_of_handle(handle) }.size();
+    // SAFETY: `pointer` points to an allocation of at least `size` bytes
+    unsafe { ptr::write_bytes(pointer.as_ptr().cast::<u8>(), 0, size) };
+}
+
 /// A collection of types safe to be used with inline or static storages.
 ///
 /// # Safety
@@ -163,6 +215,19 @@ pub unsafe trait Storage {
     /// Specific implementations *may* loosen these requirements.
     unsafe fn get<T: ?Sized>(&self, handle: Self::Handle<T>) -> NonNull<T>;
 
+    /// Get the layout of a live allocation. This is the same as calling
+    /// [`Layout::for_value_raw`] on the pointer returned by [`Self::get`].
+    ///
+    /// # Safety
+    ///
+    /// The provided handle must be valid. See [`Self::Handle`].
+    unsafe fn layout_of_handle<T: ?Sized>(&self, handle: Self::Handle<T>) -> Layout {
+        // SAFETY: `handle` is valid by safety requirements
+        let pointer = unsafe { self.get(handle) };
+        // SAFETY: `pointer` is valid, as it was just returned by `get`
+        unsafe { Layout::for_value_raw(pointer.as_ptr()) }
+    }
+
     // TODO: The four below should really be implemented on the handles, however,
     //       there's currently no clean to express the correct bounds to allow this to work
     //       in generic contexts - `Handle::This<U>` can't be related to `Storage::Handle<U>`
@@ -189,6 +254,40 @@ pub unsafe trait Storage {
         handle: Self::Handle<T>,
     ) -> Self::Handle<U>;
 
+    /// Check whether two handles point to the same allocation, ignoring their types and
+    /// metadata. Handles which compare unequal may still alias the same memory, for example a
+    /// slice handle and a handle to its first element, while this only compares their address.
+    ///
+    /// Handles from different instances of a storage may compare as the same allocation, so this
+    /// is only meaningful for handles provided by the same storage.
+    fn same_allocation<T: ?Sized + Pointee, U: ?Sized + Pointee>(
+        a: Self::Handle<T>,
+        b: Self::Handle<U>,
+    ) -> bool {
+        Self::cast::<T, ()>(a) == Self::cast::<U, ()>(b)
+    }
+
+    /// Check whether an item with the provided metadata could ever fit in this storage, if the
+    /// storage knows. Returns `None` if it doesn't, which is the default.
+    ///
+    /// Storages implementing [`ExactSizeStorage`] should return
+    /// [`ExactSizeStorage::will_fit`] here, so it can be consulted without knowing whether the
+    /// trait is implemented. As with it, `Some(true)` doesn't guarantee an allocation will
+    /// succeed.
+    #[allow(unused_variables)]
+    fn will_fit_hint<T: ?Sized + Pointee>(&self, meta: T::Metadata) -> Option<bool> {
+        None
+    }
+
+    /// Return the largest range of a sized type that could fit in this storage, or `usize::MAX`
+    /// if the storage doesn't know, which is the default.
+    ///
+    /// Storages implementing [`ExactSizeStorage`] should return [`ExactSizeStorage::max_range`]
+    /// here, so it can be consulted without knowing whether the trait is implemented.
+    fn max_range_hint<T>(&self) -> usize {
+        usize::MAX
+    }
+
     /// Attempt to allocate an element into this storage, returning a [`StorageError`] on failure.
     ///
     /// If an element has already been allocated, this *may* overwrite the existing item, allocate
@@ -199,6 +298,21 @@ pub unsafe trait Storage {
         meta: T::Metadata,
     ) -> error::Result<Self::Handle<T>>;
 
+    /// Attempt to allocate an element into this storage with all of its bytes set to zero,
+    /// returning a [`StorageError`] on failure.
+    ///
+    /// The default implementation allocates, then zeroes the memory. Storages which can
+    /// provide zeroed memory more efficiently should override this.
+    fn allocate_single_zeroed<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> error::Result<Self::Handle<T>> {
+        let handle = self.allocate_single::<T>(meta)?;
+        // SAFETY: `handle` is valid, as allocate just succeeded
+        unsafe { zero_handle(self, handle) };
+        Ok(handle)
+    }
+
     /// Deallocate a previously allocated element
     ///
     /// # Safety
@@ -222,6 +336,30 @@ pub unsafe trait Storage {
         Err(StorageError::Unimplemented)
     }
 
+    /// Attempt to grow a previously allocated range up to the size of `capacity`, also returning
+    /// whether the range was moved. If it wasn't, pointers previously returned by [`Self::get`]
+    /// still point to the same place, so there's no need to retrieve them again.
+    ///
+    /// The default implementation compares the address of the range before and after calling
+    /// [`Self::try_grow`]. Storages which know whether they grew in-place should override this.
+    ///
+    /// # Safety
+    ///
+    /// The same requirements as [`Self::try_grow`]
+    unsafe fn try_grow_moved<T>(
+        &mut self,
+        handle: Self::Handle<[T]>,
+        capacity: usize,
+    ) -> error::Result<(Self::Handle<[T]>, bool)> {
+        // SAFETY: `handle` is valid by safety requirements
+        let old_ptr = unsafe { self.get(handle) };
+        // SAFETY: Same safety requirements
+        let new_handle = unsafe { self.try_grow(handle, capacity) }?;
+        // SAFETY: `new_handle` is valid, as grow just succeeded
+        let new_ptr = unsafe { self.get(new_handle) };
+        Ok((new_handle, old_ptr.cast::<()>() != new_ptr.cast::<()>()))
+    }
+
     /// Attempt to shrink a previously allocated range down to the size of `capacity`
     ///
     /// # Safety
@@ -237,6 +375,45 @@ pub unsafe trait Storage {
         Err(StorageError::Unimplemented)
     }
 
+    /// Attempt to grow a previously allocated range by `additional` items. This is equivalent to
+    /// calling [`Self::try_grow`] with the current length plus `additional`.
+    ///
+    /// # Safety
+    ///
+    /// - The provided handle must be valid. See [`Self::Handle`]
+    unsafe fn try_grow_by<T>(
+        &mut self,
+        handle: Self::Handle<[T]>,
+        additional: usize,
+    ) -> error::Result<Self::Handle<[T]>> {
+        let capacity = handle
+            .metadata()
+            .checked_add(additional)
+            .ok_or(StorageError::exceeds_max())?;
+        // SAFETY: Handle is valid by our safety requirements, and the new capacity can't be less
+        //         than the current length
+        unsafe { self.try_grow(handle, capacity) }
+    }
+
+    /// Attempt to shrink a previously allocated range by `reduce` items. This is equivalent to
+    /// calling [`Self::try_shrink`] with the current length minus `reduce`.
+    ///
+    /// # Safety
+    ///
+    /// - The provided handle must be valid. See [`Self::Handle`]
+    /// - `reduce` must be less than or equal to the allocation's current length
+    unsafe fn try_shrink_by<T>(
+        &mut self,
+        handle: Self::Handle<[T]>,
+        reduce: usize,
+    ) -> error::Result<Self::Handle<[T]>> {
+        debug_assert!(reduce <= handle.metadata());
+        let capacity = handle.metadata() - reduce;
+        // SAFETY: Handle is valid by our safety requirements, and the new capacity can't be
+        //         greater than the current length
+        unsafe { self.try_shrink(handle, capacity) }
+    }
+
     create_drop!(
         create_single, create_single_range, create_single_dyn, drop_single;
         allocate_single, deallocate_single
@@ -252,6 +429,11 @@ unsafe impl<S: Storage> Storage for &mut S {
         unsafe { S::get(self, handle) }
     }
 
+    unsafe fn layout_of_handle<T: ?Sized>(&self, handle: Self::Handle<T>) -> Layout {
+        // SAFETY: Same safety requirements
+        unsafe { S::layout_of_handle(self, handle) }
+    }
+
     fn from_raw_parts<T: ?Sized + Pointee>(
         handle: Self::Handle<()>,
         meta: T::Metadata,
@@ -276,6 +458,21 @@ unsafe impl<S: Storage> Storage for &mut S {
         S::coerce(handle)
     }
 
+    fn same_allocation<T: ?Sized + Pointee, U: ?Sized + Pointee>(
+        a: Self::Handle<T>,
+        b: Self::Handle<U>,
+    ) -> bool {
+        S::same_allocation(a, b)
+    }
+
+    fn will_fit_hint<T: ?Sized + Pointee>(&self, meta: T::Metadata) -> Option<bool> {
+        S::will_fit_hint::<T>(self, meta)
+    }
+
+    fn max_range_hint<T>(&self) -> usize {
+        S::max_range_hint::<T>(self)
+    }
+
     fn allocate_single<T: ?Sized + Pointee>(
         &mut self,
         meta: T::Metadata,
@@ -283,6 +480,13 @@ unsafe impl<S: Storage> Storage for &mut S {
         S::allocate_single(self, meta)
     }
 
+    fn allocate_single_zeroed<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> error::Result<Self::Handle<T>> {
+        S::allocate_single_zeroed(self, meta)
+    }
+
     unsafe fn deallocate_single<T: ?Sized>(&mut self, handle: Self::Handle<T>) {
         // SAFETY: Same safety requirements
         unsafe { S::deallocate_single(self, handle) }
@@ -297,6 +501,15 @@ unsafe impl<S: Storage> Storage for &mut S {
         unsafe { S::try_grow(self, handle, capacity) }
     }
 
+    unsafe fn try_grow_moved<T>(
+        &mut self,
+        handle: Self::Handle<[T]>,
+        capacity: usize,
+    ) -> error::Result<(Self::Handle<[T]>, bool)> {
+        // SAFETY: Same safety requirements
+        unsafe { S::try_grow_moved(self, handle, capacity) }
+    }
+
     unsafe fn try_shrink<T>(
         &mut self,
         handle: Self::Handle<[T]>,
@@ -319,10 +532,21 @@ where
         S::allocate(self, meta)
     }
 
+    fn allocate_zeroed<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> error::Result<Self::Handle<T>> {
+        S::allocate_zeroed(self, meta)
+    }
+
     unsafe fn deallocate<T: ?Sized + Pointee>(&mut self, handle: Self::Handle<T>) {
         // SAFETY: Same safety requirements
         unsafe { S::deallocate(self, handle) }
     }
+
+    fn is_allocated<T: ?Sized + Pointee>(&self, handle: Self::Handle<T>) -> bool {
+        S::is_allocated(self, handle)
+    }
 }
 
 impl<S> ExactSizeStorage for &mut S
@@ -355,6 +579,21 @@ pub unsafe trait MultiItemStorage: Storage {
         meta: T::Metadata,
     ) -> error::Result<Self::Handle<T>>;
 
+    /// Attempt to allocate an item into this storage with all of its bytes set to zero,
+    /// returning [`StorageError`] on failure.
+    ///
+    /// The default implementation allocates, then zeroes the memory. Storages which can
+    /// provide zeroed memory more efficiently should override this.
+    fn allocate_zeroed<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> error::Result<Self::Handle<T>> {
+        let handle = self.allocate::<T>(meta)?;
+        // SAFETY: `handle` is valid, as allocate just succeeded
+        unsafe { zero_handle(self, handle) };
+        Ok(handle)
+    }
+
     /// Deallocate a previously allocated item
     ///
     /// # Safety
@@ -362,6 +601,17 @@ pub unsafe trait MultiItemStorage: Storage {
     /// The provided handle must be valid. See [`Self::Handle`](`Storage::Handle`).
     unsafe fn deallocate<T: ?Sized + Pointee>(&mut self, handle: Self::Handle<T>);
 
+    /// Check whether a handle currently refers to a live allocation in this storage. This is
+    /// safe to call with any handle, valid or not.
+    ///
+    /// The default implementation always returns `true`, as not every storage can cheaply tell.
+    /// A `false` result means the handle is definitely invalid, while `true` only means it may
+    /// be valid.
+    #[allow(unused_variables)]
+    fn is_allocated<T: ?Sized + Pointee>(&self, handle: Self::Handle<T>) -> bool {
+        true
+    }
+
     create_drop!(
         create, create_range, create_dyn, drop;
         allocate, deallocate
@@ -370,6 +620,9 @@ pub unsafe trait MultiItemStorage: Storage {
 
 /// An extension to [`Storage`] for storages that know the exact maximum size that can be stored
 /// within them.
+///
+/// Implementors should also override [`Storage::will_fit_hint`] and [`Storage::max_range_hint`]
+/// to return the same results, so generic code can consult them.
 pub trait ExactSizeStorage: Storage {
     /// Given a type and metadata, return whether the item would fit in this storage.
     ///
@@ -447,6 +700,37 @@ mod tests {
 
     type Store = SingleInline<[usize; 4]>;
 
+    #[test]
+    fn layout_of_handle() {
+        let mut storage = Store::default();
+
+        let handle = storage.allocate_single::<[u16]>(5).unwrap();
+        assert_eq!(
+            unsafe { storage.layout_of_handle(handle) },
+            Layout::array::<u16>(5).unwrap()
+        );
+        unsafe { storage.deallocate_single(handle) };
+
+        let handle = storage.allocate_single::<u64>(()).unwrap();
+        let handle = Store::coerce::<u64, dyn core::fmt::Debug>(handle);
+        assert_eq!(
+            unsafe { storage.layout_of_handle(handle) },
+            Layout::new::<u64>()
+        );
+    }
+
+    #[test]
+    fn copy_between() {
+        let mut s1 = Store::default();
+        let mut s2 = SingleInline::<[u16; 3]>::default();
+
+        let h1 = s1.create_single([1u16, 2, 3]).unwrap();
+        let h2 = s2.allocate_single::<[u16]>(3).unwrap();
+        unsafe { super::copy_between(&s1, Store::coerce(h1), &s2, h2) };
+
+        assert_eq!(unsafe { s2.get(h2).as_ref() }, &[1, 2, 3]);
+    }
+
     #[test]
     fn create_single() {
         let mut storage = Store::default();
@@ -472,4 +756,29 @@ mod tests {
             .unwrap();
         unsafe { storage.drop_single(handle) };
     }
+
+    #[test]
+    fn try_grow_by() {
+        let mut storage = Store::default();
+
+        let handle = storage.allocate_single::<[u8]>(4).unwrap();
+        let handle = unsafe { storage.try_grow_by(handle, 4) }.unwrap();
+        assert_eq!(handle.metadata(), 8);
+        let handle = unsafe { storage.try_shrink_by(handle, 6) }.unwrap();
+        assert_eq!(handle.metadata(), 2);
+        unsafe { storage.try_grow_by(handle, usize::MAX) }.unwrap_err();
+        unsafe { storage.deallocate_single(handle) };
+    }
+
+    #[test]
+    fn try_grow_moved() {
+        let mut storage = Store::default();
+
+        let handle = storage.allocate_single::<[u8]>(4).unwrap();
+        // Inline storage always grows in-place
+        let (handle, moved) = unsafe { storage.try_grow_moved(handle, 16) }.unwrap();
+        assert_eq!(handle.metadata(), 16);
+        assert!(!moved);
+        unsafe { storage.deallocate_single(handle) };
+    }
 }
@@ -0,0 +1,172 @@
+//! A storage-based implementation of [`std::borrow`]
+
+use core::fmt;
+use core::ops::Deref;
+use core::ptr::Pointee;
+
+use crate::base::Storage;
+use crate::boxed::Box;
+
+/// Storage-based implementation of [`Cow`](std::borrow::Cow).
+///
+/// Owned values are stored in a [`Box`] in the chosen storage, cloning a borrowed value into it
+/// the first time mutable access is required.
+pub enum Cow<'a, T, S>
+where
+    T: Pointee,
+    S: Storage,
+{
+    /// Borrowed data
+    Borrowed(&'a T),
+    /// Owned data
+    Owned(Box<T, S>),
+}
+
+impl<T, S> Cow<'_, T, S>
+where
+    T: Pointee,
+    S: Storage,
+{
+    /// Whether this holds borrowed data
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self, Cow::Borrowed(_))
+    }
+
+    /// Whether this holds owned data
+    pub fn is_owned(&self) -> bool {
+        matches!(self, Cow::Owned(_))
+    }
+}
+
+impl<T, S> Cow<'_, T, S>
+where
+    T: Pointee + Clone,
+    S: Storage + Default,
+{
+    /// Get a mutable reference to the owned data, cloning it into a new box if it's currently
+    /// borrowed
+    ///
+    /// # Panics
+    ///
+    /// If the data is borrowed, and the backing storage fails to allocate the new box
+    pub fn to_mut(&mut self) -> &mut T {
+        if let Cow::Borrowed(val) = *self {
+            *self = Cow::Owned(Box::new(val.clone()));
+        }
+
+        match self {
+            Cow::Borrowed(_) => unreachable!(),
+            Cow::Owned(b) => b,
+        }
+    }
+
+    /// Extract the owned data, cloning it into a new box if it's currently borrowed
+    ///
+    /// # Panics
+    ///
+    /// If the data is borrowed, and the backing storage fails to allocate the new box
+    pub fn into_owned(self) -> Box<T, S> {
+        match self {
+            Cow::Borrowed(val) => Box::new(val.clone()),
+            Cow::Owned(b) => b,
+        }
+    }
+}
+
+impl<T, S> Deref for Cow<'_, T, S>
+where
+    T: Pointee,
+    S: Storage,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self {
+            Cow::Borrowed(val) => val,
+            Cow::Owned(b) => b,
+        }
+    }
+}
+
+impl<T, S> AsRef<T> for Cow<'_, T, S>
+where
+    T: Pointee,
+    S: Storage,
+{
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T, S> Clone for Cow<'_, T, S>
+where
+    T: Pointee + Clone,
+    S: Storage + Default,
+{
+    fn clone(&self) -> Self {
+        match self {
+            Cow::Borrowed(val) => Cow::Borrowed(val),
+            Cow::Owned(b) => Cow::Owned(b.clone()),
+        }
+    }
+}
+
+impl<T, S> fmt::Debug for Cow<'_, T, S>
+where
+    T: Pointee + fmt::Debug,
+    S: Storage,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_ref())
+    }
+}
+
+impl<T, S> fmt::Display for Cow<'_, T, S>
+where
+    T: Pointee + fmt::Display,
+    S: Storage,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::inline::SingleInline;
+
+    type Cow<'a, T> = super::Cow<'a, T, SingleInline<[usize; 4]>>;
+
+    #[test]
+    fn deref() {
+        let val = [1, 2];
+        let c = Cow::Borrowed(&val);
+        assert!(c.is_borrowed());
+        assert_eq!(*c, [1, 2]);
+    }
+
+    #[test]
+    fn to_mut() {
+        let val = [1, 2];
+        let mut c = Cow::Borrowed(&val);
+
+        c.to_mut()[0] = 3;
+        assert!(c.is_owned());
+        assert_eq!(*c, [3, 2]);
+        assert_eq!(val, [1, 2]);
+
+        c.to_mut()[1] = 4;
+        assert_eq!(*c, [3, 4]);
+    }
+
+    #[test]
+    fn into_owned() {
+        let val = [1, 2];
+        let c = Cow::Borrowed(&val);
+        let b = c.into_owned();
+        assert_eq!(*b, [1, 2]);
+
+        let c = Cow::Owned(b);
+        assert_eq!(*c.into_owned(), [1, 2]);
+    }
+}
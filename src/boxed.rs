@@ -5,14 +5,19 @@ use core::borrow::{Borrow, BorrowMut};
 use core::cmp::Ordering;
 #[cfg(feature = "unsize")]
 use core::marker::Unsize;
-use core::mem::ManuallyDrop;
+use core::mem::{ManuallyDrop, MaybeUninit};
 #[cfg(feature = "unsize")]
 use core::ops::CoerceUnsized;
 use core::ops::{Deref, DerefMut};
+use core::pin::Pin;
 use core::ptr::{NonNull, Pointee};
 use core::{fmt, mem, ptr};
 
-use crate::base::{FromLeakedStorage, LeaksafeStorage, Storage};
+use crate::base::{
+    self, ExactSizeStorage, FromLeakedStorage, LeaksafeStorage, MultiItemStorage, Storage,
+};
+use crate::error;
+use crate::handles::Handle;
 
 /// Storage-based implementation of [`Box`](std::boxed::Box).
 ///
@@ -54,6 +59,39 @@ where
             storage: ManuallyDrop::new(storage),
         })
     }
+
+    /// Create a new [`Box`] with uninitialized contents, with the memory filled with `0` bytes.
+    /// Creates a default instance of the desired storage.
+    ///
+    /// # Panics
+    ///
+    /// If the storage fails to allocate for any reason
+    pub fn new_zeroed() -> Box<MaybeUninit<T>, S> {
+        Box::new_zeroed_in(S::default())
+    }
+
+    /// Create a new boxed slice with uninitialized contents, with the memory filled with `0`
+    /// bytes. Creates a default instance of the desired storage.
+    ///
+    /// # Panics
+    ///
+    /// If the storage fails to allocate for any reason
+    pub fn new_zeroed_slice(len: usize) -> Box<[MaybeUninit<T>], S> {
+        Box::new_zeroed_slice_in(len, S::default())
+    }
+
+    /// Create a new pinned [`Box`] containing the provided value, creating a default instance of
+    /// the desired storage. See [`Box::into_pin`].
+    ///
+    /// # Panics
+    ///
+    /// If the storage fails to allocate for any reason
+    pub fn pin(val: T) -> Pin<Box<T, S>>
+    where
+        S: LeaksafeStorage + 'static,
+    {
+        Box::into_pin(Box::new(val))
+    }
 }
 
 impl<T, S> Box<T, S>
@@ -87,6 +125,109 @@ where
             storage: ManuallyDrop::new(storage),
         })
     }
+
+    /// Create a new pinned [`Box`] containing the provided value, in the provided storage. See
+    /// [`Box::into_pin`].
+    ///
+    /// # Panics
+    ///
+    /// If the storage fails to allocate for any reason
+    pub fn pin_in(val: T, storage: S) -> Pin<Box<T, S>>
+    where
+        S: LeaksafeStorage + 'static,
+    {
+        Box::into_pin(Box::new_in(val, storage))
+    }
+
+    /// Create a new [`Box`] with uninitialized contents, with the memory filled with `0` bytes,
+    /// in the provided storage.
+    ///
+    /// # Panics
+    ///
+    /// If the storage fails to allocate for any reason
+    pub fn new_zeroed_in(mut storage: S) -> Box<MaybeUninit<T>, S> {
+        Box {
+            handle: storage
+                .allocate_single_zeroed(())
+                .unwrap_or_else(|e| panic!("{}", e)),
+            storage: ManuallyDrop::new(storage),
+        }
+    }
+
+    /// Create a new boxed slice with uninitialized contents, with the memory filled with `0`
+    /// bytes, in the provided storage.
+    ///
+    /// # Panics
+    ///
+    /// If the storage fails to allocate for any reason
+    pub fn new_zeroed_slice_in(len: usize, mut storage: S) -> Box<[MaybeUninit<T>], S> {
+        Box {
+            handle: storage
+                .allocate_single_zeroed(len)
+                .unwrap_or_else(|e| panic!("{}", e)),
+            storage: ManuallyDrop::new(storage),
+        }
+    }
+}
+
+impl<T, S> Box<MaybeUninit<T>, S>
+where
+    S: Storage,
+{
+    /// Convert into a `Box<T, S>`, assuming the contents have been initialized.
+    ///
+    /// # Safety
+    ///
+    /// The contained value must be a valid, initialized instance of `T`. See
+    /// [`MaybeUninit::assume_init`].
+    pub unsafe fn assume_init(self) -> Box<T, S> {
+        let (storage, handle) = self.into_parts();
+        // SAFETY: The handle is from this storage, and the caller guarantees it's initialized
+        unsafe { Box::from_parts(storage, S::cast(handle)) }
+    }
+}
+
+impl<T, S> Box<[MaybeUninit<T>], S>
+where
+    S: Storage,
+{
+    /// Convert into a `Box<[T], S>`, assuming the contents have been initialized.
+    ///
+    /// # Safety
+    ///
+    /// Every element of the slice must be a valid, initialized instance of `T`. See
+    /// [`MaybeUninit::assume_init`].
+    pub unsafe fn assume_init(self) -> Box<[T], S> {
+        let (storage, handle) = self.into_parts();
+        // SAFETY: The handle is from this storage, and the caller guarantees it's initialized
+        unsafe { Box::from_parts(storage, S::cast_unsized(handle)) }
+    }
+}
+
+impl<S> Box<str, S>
+where
+    S: Storage,
+{
+    /// Create a new boxed string slice in the provided storage, copying the contents of `s`
+    ///
+    /// # Panics
+    ///
+    /// If the storage fails to allocate for any reason
+    pub fn from_str_in(s: &str, mut storage: S) -> Box<str, S> {
+        let handle = storage
+            .allocate_single::<[u8]>(s.len())
+            .unwrap_or_else(|e| panic!("{}", e));
+        // SAFETY: Handle was just allocated, so is valid
+        let ptr = unsafe { storage.get(handle) };
+        // SAFETY: The allocation has space for exactly `s.len()` bytes, and can't overlap `s`
+        unsafe { ptr::copy_nonoverlapping(s.as_ptr(), ptr.as_ptr().cast::<u8>(), s.len()) };
+
+        Box {
+            // The bytes were copied from a `str`, so are valid UTF-8
+            handle: S::cast_unsized(handle),
+            storage: ManuallyDrop::new(storage),
+        }
+    }
 }
 
 impl<T, S> Box<T, S>
@@ -100,26 +241,15 @@ where
     where
         Ns: Storage,
     {
-        let layout = Layout::for_value(&*self);
-
-        // SAFETY: Our handle is guaranteed valid by internal invariant
-        let (old_ptr, meta) = unsafe { self.storage.get(self.handle).to_raw_parts() };
+        let meta = self.handle.metadata();
         let new_handle = match new_storage.allocate_single::<T>(meta) {
             Ok(handle) => handle,
             Err(_) => return Err((self, new_storage)),
         };
 
-        // SAFETY: New handle is valid because allocate just succeeded
-        let new_ptr = unsafe { new_storage.get(new_handle).to_raw_parts().0 };
-
-        // SAFETY: Handles are from different allocations
-        unsafe {
-            ptr::copy_nonoverlapping(
-                old_ptr.as_ptr().cast::<u8>(),
-                new_ptr.as_ptr().cast::<u8>(),
-                layout.size(),
-            );
-        }
+        // SAFETY: Our handle is valid by internal invariant, the new one because allocate just
+        //         succeeded. They're from different allocations of the same item.
+        unsafe { base::copy_between(&*self.storage, self.handle, &new_storage, new_handle) };
 
         // SAFETY: Our handle is guaranteed valid by internal invariant
         unsafe { self.storage.deallocate_single(self.handle) };
@@ -132,6 +262,23 @@ where
         Ok(unsafe { Box::from_parts(new_storage, new_handle) })
     }
 
+    /// Swap the contents of two boxes.
+    ///
+    /// Rather than swapping the values, this swaps the storages and handles of the boxes, which is
+    /// always valid as each handle moves along with the storage it came from. For storages which
+    /// keep values out-of-line, such as [`GlobalAlloc`](crate::alloc::GlobalAlloc) or a shared
+    /// `&VirtHeap`, this only costs as much as swapping the handles, however large `T` is. Inline
+    /// storages contain the value themselves, so for them this is equivalent to a value swap.
+    ///
+    /// To swap the values while keeping each box's allocation in place, use [`mem::swap`] on the
+    /// dereferenced boxes instead.
+    ///
+    /// This is an associated function so it doesn't shadow a `swap` method on `T`, such as
+    /// [`slice::swap`].
+    pub fn swap(this: &mut Self, other: &mut Self) {
+        mem::swap(this, other)
+    }
+
     /// Consumes and leaks this box, returning a mutable reference.
     ///
     /// The returned data lives for the rest of the program's life, dropping the reference will
@@ -146,6 +293,33 @@ where
         unsafe { Box::into_raw(this).as_mut() }
     }
 
+    /// Convert this box into a [`Pin`]. Mutable access to the value is then only available if
+    /// it's [`Unpin`].
+    ///
+    /// This requires a [`LeaksafeStorage`], as other storages may keep the value inside
+    /// themselves, moving it whenever the box is moved. The storage must also be `'static`, as
+    /// otherwise the box could be forgotten and its memory reused once the storage goes out of
+    /// scope, without the value ever being dropped:
+    ///
+    /// ```compile_fail
+    /// # use department::backing::Backing;
+    /// # use department::boxed::Box;
+    /// # use department::heap::VirtHeap;
+    /// let heap = VirtHeap::<Backing<16>, 1>::new();
+    /// let pinned = Box::pin_in(1, &heap);
+    /// core::mem::forget(pinned);
+    /// ```
+    pub fn into_pin(this: Self) -> Pin<Self>
+    where
+        S: LeaksafeStorage + 'static,
+    {
+        // SAFETY: The storage is leaksafe, so the value doesn't move with the box. The storage
+        //         is also `'static`, so even if the box is leaked the memory stays valid, and
+        //         otherwise the value isn't moved out of the box or deallocated until after it's
+        //         dropped
+        unsafe { Pin::new_unchecked(this) }
+    }
+
     /// Consumes and leaks this box, returning a raw pointer.
     ///
     /// The returned data lives for the rest of the program's life, dropping the pointer will
@@ -210,6 +384,12 @@ where
         (storage, handle)
     }
 
+    /// Get a reference to this box's storage and a copy of its handle, without consuming it.
+    /// The handle remains owned by the box, and must not be deallocated through the storage.
+    pub fn parts(&self) -> (&S, S::Handle<T>) {
+        (&self.storage, self.handle)
+    }
+
     /// Create a box from a component storage and handle
     ///
     /// # Safety
@@ -238,6 +418,22 @@ where
     }
 }
 
+// SAFETY: A box uniquely owns its value, so can be sent if the value and its storage can be
+unsafe impl<T, S> Send for Box<T, S>
+where
+    T: ?Sized + Pointee + Send,
+    S: Storage + Send,
+{
+}
+
+// SAFETY: A shared box only provides shared access to its value and storage
+unsafe impl<T, S> Sync for Box<T, S>
+where
+    T: ?Sized + Pointee + Sync,
+    S: Storage + Sync,
+{
+}
+
 impl<T, S> fmt::Debug for Box<T, S>
 where
     T: ?Sized + fmt::Debug,
@@ -258,6 +454,18 @@ where
     }
 }
 
+impl<T, S> fmt::Pointer for Box<T, S>
+where
+    T: ?Sized,
+    S: Storage,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Print the resolved address, even if the handle is an offset
+        let ptr: *const T = &**self;
+        fmt::Pointer::fmt(&ptr, f)
+    }
+}
+
 #[cfg(feature = "unsize")]
 impl<T, U, S> CoerceUnsized<Box<U, S>> for Box<T, S>
 where
@@ -345,6 +553,16 @@ where
     }
 }
 
+impl<T, S> From<Box<T, S>> for Pin<Box<T, S>>
+where
+    T: ?Sized + Pointee,
+    S: LeaksafeStorage + 'static,
+{
+    fn from(b: Box<T, S>) -> Pin<Box<T, S>> {
+        Box::into_pin(b)
+    }
+}
+
 impl<T, S> Clone for Box<T, S>
 where
     T: Pointee + Clone,
@@ -366,16 +584,114 @@ where
     }
 }
 
-impl<T, S> PartialEq for Box<T, S>
+impl<T, S, const N: usize> From<[T; N]> for Box<[T], S>
+where
+    S: Storage + Default,
+{
+    fn from(arr: [T; N]) -> Box<[T], S> {
+        let mut storage = S::default();
+        let handle = storage
+            .allocate_single::<[T]>(N)
+            .unwrap_or_else(|e| panic!("{}", e));
+        // SAFETY: Handle was just allocated, so is valid
+        let ptr = unsafe { storage.get(handle) };
+        // SAFETY: The allocation has space for exactly `N` elements of `T`, and ownership of the
+        //         array moves into it
+        unsafe { ptr.cast::<[T; N]>().write(arr) };
+
+        Box {
+            handle,
+            storage: ManuallyDrop::new(storage),
+        }
+    }
+}
+
+impl<S> From<&str> for Box<str, S>
+where
+    S: Storage + Default,
+{
+    fn from(s: &str) -> Box<str, S> {
+        Box::from_str_in(s, S::default())
+    }
+}
+
+impl<T, S1, S2> PartialEq<Box<T, S2>> for Box<T, S1>
 where
     T: ?Sized + Pointee + PartialEq,
-    S: Storage,
+    S1: Storage,
+    S2: Storage,
 {
-    fn eq(&self, other: &Self) -> bool {
+    fn eq(&self, other: &Box<T, S2>) -> bool {
         T::eq(&**self, &**other)
     }
 }
 
+impl<T, S> PartialEq<[T]> for Box<[T], S>
+where
+    T: PartialEq,
+    S: Storage,
+{
+    fn eq(&self, other: &[T]) -> bool {
+        **self == *other
+    }
+}
+
+impl<T, S> PartialEq<&[T]> for Box<[T], S>
+where
+    T: PartialEq,
+    S: Storage,
+{
+    fn eq(&self, other: &&[T]) -> bool {
+        **self == **other
+    }
+}
+
+impl<T, S, const N: usize> PartialEq<[T; N]> for Box<[T], S>
+where
+    T: PartialEq,
+    S: Storage,
+{
+    fn eq(&self, other: &[T; N]) -> bool {
+        **self == *other
+    }
+}
+
+impl<S> PartialEq<str> for Box<str, S>
+where
+    S: Storage,
+{
+    fn eq(&self, other: &str) -> bool {
+        **self == *other
+    }
+}
+
+impl<S> PartialEq<&str> for Box<str, S>
+where
+    S: Storage,
+{
+    fn eq(&self, other: &&str) -> bool {
+        **self == **other
+    }
+}
+
+impl<S> PartialEq<Box<str, S>> for str
+where
+    S: Storage,
+{
+    fn eq(&self, other: &Box<str, S>) -> bool {
+        *self == **other
+    }
+}
+
+impl<S> PartialEq<Box<str, S>> for &str
+where
+    S: Storage,
+{
+    fn eq(&self, other: &Box<str, S>) -> bool {
+        **self == **other
+    }
+}
+
 impl<T, S> Eq for Box<T, S>
 where
     T: ?Sized + Pointee + Eq,
@@ -383,12 +699,13 @@ where
 {
 }
 
-impl<T, S> PartialOrd for Box<T, S>
+impl<T, S1, S2> PartialOrd<Box<T, S2>> for Box<T, S1>
 where
     T: ?Sized + Pointee + PartialOrd,
-    S: Storage,
+    S1: Storage,
+    S2: Storage,
 {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    fn partial_cmp(&self, other: &Box<T, S2>) -> Option<Ordering> {
         T::partial_cmp(&**self, &**other)
     }
 }
@@ -403,8 +720,165 @@ where
     }
 }
 
+// SAFETY: Boxed item promises to fulfill safety guarantees
+unsafe impl<S, A> Storage for Box<S, A>
+where
+    S: Storage,
+    A: Storage,
+{
+    type Handle<T: ?Sized> = S::Handle<T>;
+
+    unsafe fn get<T: ?Sized>(&self, handle: Self::Handle<T>) -> NonNull<T> {
+        // SAFETY: Same safety requirements
+        unsafe { S::get(self, handle) }
+    }
+
+    unsafe fn layout_of_handle<T: ?Sized>(&self, handle: Self::Handle<T>) -> Layout {
+        // SAFETY: Same safety requirements
+        unsafe { S::layout_of_handle(self, handle) }
+    }
+
+    fn from_raw_parts<T: ?Sized + Pointee>(
+        handle: Self::Handle<()>,
+        meta: T::Metadata,
+    ) -> Self::Handle<T> {
+        S::from_raw_parts(handle, meta)
+    }
+
+    fn cast<T: ?Sized + Pointee, U>(handle: Self::Handle<T>) -> Self::Handle<U> {
+        S::cast(handle)
+    }
+
+    fn cast_unsized<T: ?Sized + Pointee, U: ?Sized + Pointee<Metadata = T::Metadata>>(
+        handle: Self::Handle<T>,
+    ) -> Self::Handle<U> {
+        S::cast_unsized(handle)
+    }
+
+    #[cfg(feature = "unsize")]
+    fn coerce<T: ?Sized + Pointee + Unsize<U>, U: ?Sized + Pointee>(
+        handle: Self::Handle<T>,
+    ) -> Self::Handle<U> {
+        S::coerce(handle)
+    }
+
+    fn same_allocation<T: ?Sized + Pointee, U: ?Sized + Pointee>(
+        a: Self::Handle<T>,
+        b: Self::Handle<U>,
+    ) -> bool {
+        S::same_allocation(a, b)
+    }
+
+    fn will_fit_hint<T: ?Sized + Pointee>(&self, meta: T::Metadata) -> Option<bool> {
+        S::will_fit_hint::<T>(self, meta)
+    }
+
+    fn max_range_hint<T>(&self) -> usize {
+        S::max_range_hint::<T>(self)
+    }
+
+    fn allocate_single<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> error::Result<Self::Handle<T>> {
+        S::allocate_single(self, meta)
+    }
+
+    fn allocate_single_zeroed<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> error::Result<Self::Handle<T>> {
+        S::allocate_single_zeroed(self, meta)
+    }
+
+    unsafe fn deallocate_single<T: ?Sized>(&mut self, handle: Self::Handle<T>) {
+        // SAFETY: Same safety requirements
+        unsafe { S::deallocate_single(self, handle) }
+    }
+
+    unsafe fn try_grow<T>(
+        &mut self,
+        handle: Self::Handle<[T]>,
+        capacity: usize,
+    ) -> error::Result<Self::Handle<[T]>> {
+        // SAFETY: Same safety requirements
+        unsafe { S::try_grow(self, handle, capacity) }
+    }
+
+    unsafe fn try_grow_moved<T>(
+        &mut self,
+        handle: Self::Handle<[T]>,
+        capacity: usize,
+    ) -> error::Result<(Self::Handle<[T]>, bool)> {
+        // SAFETY: Same safety requirements
+        unsafe { S::try_grow_moved(self, handle, capacity) }
+    }
+
+    unsafe fn try_shrink<T>(
+        &mut self,
+        handle: Self::Handle<[T]>,
+        capacity: usize,
+    ) -> error::Result<Self::Handle<[T]>> {
+        // SAFETY: Same safety requirements
+        unsafe { S::try_shrink(self, handle, capacity) }
+    }
+}
+
+// SAFETY: Boxed item promises to fulfill safety guarantees
+unsafe impl<S, A> MultiItemStorage for Box<S, A>
+where
+    S: MultiItemStorage,
+    A: Storage,
+{
+    fn allocate<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> error::Result<Self::Handle<T>> {
+        S::allocate(self, meta)
+    }
+
+    fn allocate_zeroed<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> error::Result<Self::Handle<T>> {
+        S::allocate_zeroed(self, meta)
+    }
+
+    unsafe fn deallocate<T: ?Sized + Pointee>(&mut self, handle: Self::Handle<T>) {
+        // SAFETY: Same safety requirements
+        unsafe { S::deallocate(self, handle) }
+    }
+
+    fn is_allocated<T: ?Sized + Pointee>(&self, handle: Self::Handle<T>) -> bool {
+        S::is_allocated(self, handle)
+    }
+}
+
+impl<S, A> ExactSizeStorage for Box<S, A>
+where
+    S: ExactSizeStorage,
+    A: Storage,
+{
+    fn will_fit<T: ?Sized + Pointee>(&self, meta: T::Metadata) -> bool {
+        S::will_fit::<T>(self, meta)
+    }
+
+    fn max_range<T>(&self) -> usize {
+        S::max_range::<T>(self)
+    }
+}
+
+// SAFETY: Boxed item promises to fulfill safety guarantees
+unsafe impl<S, A> LeaksafeStorage for Box<S, A>
+where
+    S: LeaksafeStorage,
+    A: Storage,
+{
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::alloc::GlobalAlloc;
     use crate::inline::SingleInline;
 
     type Box<T> = super::Box<T, SingleInline<[usize; 4]>>;
@@ -436,4 +910,130 @@ mod tests {
 
         assert_eq!(*b3.0, [1, 2]);
     }
+
+    #[test]
+    fn cmp_storages() {
+        let b1 = Box::new(1);
+        let b2 = super::Box::<_, GlobalAlloc>::new(1);
+        let b3 = super::Box::<_, GlobalAlloc>::new(2);
+
+        assert_eq!(b1, b2);
+        assert_ne!(b1, b3);
+        assert!(b1 < b3);
+        assert_eq!(format!("{:?}", b1), format!("{:?}", b2));
+    }
+
+    #[test]
+    fn new_zeroed() {
+        let b = Box::<u64>::new_zeroed();
+        let b = unsafe { b.assume_init() };
+        assert_eq!(*b, 0);
+
+        let b = super::Box::<u64, GlobalAlloc>::new_zeroed();
+        let b = unsafe { b.assume_init() };
+        assert_eq!(*b, 0);
+    }
+
+    #[test]
+    fn new_zeroed_slice() {
+        let b = Box::<u16>::new_zeroed_slice(8);
+        let b = unsafe { b.assume_init() };
+        assert_eq!(*b, [0; 8]);
+
+        let b = super::Box::<u16, GlobalAlloc>::new_zeroed_slice(64);
+        let b = unsafe { b.assume_init() };
+        assert_eq!(*b, [0; 64]);
+    }
+
+    #[test]
+    fn swap() {
+        let mut a = super::Box::<_, GlobalAlloc>::new([1u64; 64]);
+        let mut b = super::Box::<_, GlobalAlloc>::new([2u64; 64]);
+        let (a_ptr, b_ptr): (*const _, *const _) = (&*a, &*b);
+
+        super::Box::swap(&mut a, &mut b);
+        assert_eq!(*a, [2; 64]);
+        assert_eq!(*b, [1; 64]);
+        // Out-of-line storages swap handles, leaving the values where they were
+        assert_eq!(&*a as *const _, b_ptr);
+        assert_eq!(&*b as *const _, a_ptr);
+
+        let mut a = Box::<[u32; 2]>::new([1, 2]).coerce::<[u32]>();
+        let mut b = Box::<[u32; 3]>::new([3, 4, 5]).coerce::<[u32]>();
+        Box::swap(&mut a, &mut b);
+        assert_eq!(*a, [3, 4, 5]);
+        assert_eq!(*b, [1, 2]);
+        // Slice methods aren't shadowed
+        a.swap(0, 2);
+        assert_eq!(*a, [5, 4, 3]);
+    }
+
+    #[test]
+    fn fmt_pointer() {
+        let b = Box::<u32>::new(1);
+        assert_eq!(format!("{:p}", b), format!("{:p}", &*b));
+
+        let b = super::Box::<[u8; 4], GlobalAlloc>::new([1, 2, 3, 4]);
+        assert_eq!(format!("{:p}", b), format!("{:p}", &*b));
+    }
+
+    #[test]
+    fn cmp_slice() {
+        let b = Box::<[u32; 3]>::new([1, 2, 3]).coerce::<[u32]>();
+
+        assert_eq!(b, [1, 2, 3]);
+        assert_eq!(b, &[1, 2, 3][..]);
+        assert_eq!(b, *[1, 2, 3].as_slice());
+        assert_ne!(b, [1, 2]);
+        assert_ne!(b, &[3, 2, 1][..]);
+    }
+
+    #[test]
+    fn pin() {
+        let mut b = super::Box::<u32, GlobalAlloc>::pin(1);
+        *b = 2;
+        assert_eq!(*b, 2);
+
+        let b = super::Box::<_, GlobalAlloc>::pin(core::marker::PhantomPinned);
+        let ptr: *const _ = &*b;
+        let moved = b;
+        assert_eq!(&*moved as *const _, ptr);
+    }
+
+    #[test]
+    fn from_array() {
+        let b: Box<[u32]> = [1, 2, 3].into();
+        assert_eq!(b, [1, 2, 3]);
+
+        let b: super::Box<[u32], GlobalAlloc> = [].into();
+        assert_eq!(b, []);
+    }
+
+    #[test]
+    fn from_str() {
+        let b = Box::<str>::from_str_in("Hello!", SingleInline::new());
+        assert_eq!(&*b, "Hello!");
+        assert_eq!(b.len(), 6);
+
+        let b: super::Box<str, GlobalAlloc> = "".into();
+        assert_eq!(&*b, "");
+    }
+
+    #[test]
+    fn boxed_storage() {
+        use crate::collections::Vec;
+        use crate::heap::VirtHeap;
+
+        let storage = super::Box::<_, GlobalAlloc>::new(SingleInline::<[u32; 4]>::new());
+        let mut v = Vec::new_in(storage);
+        v.extend([1, 2, 3]);
+        assert_eq!(&*v, &[1, 2, 3]);
+
+        // Nested references forward through the existing `&mut S` impl
+        let heap = VirtHeap::<u32, 8>::new();
+        let mut storage = &heap;
+        let mut storage = &mut storage;
+        let b = super::Box::new_in(1u32, &mut storage);
+        assert_eq!(*b, 1);
+    }
 }
@@ -0,0 +1,282 @@
+//! Storage wrapper which allows allocating through a shared reference, using a [`RefCell`]
+//!
+//! # Advantages
+//! - Lets multiple collections share a multi-item storage, such as a
+//!   [`Debug`](crate::debug::Debug) storage, in a single thread
+//! - No synchronization overhead
+//!
+//! # Disadvantages
+//! - Not [`Sync`], so can't be shared between threads
+//! - Borrows are checked at runtime. See [`RefCellStorage`] for when this may panic.
+
+use core::alloc::Layout;
+use core::cell::RefCell;
+use core::fmt;
+#[cfg(feature = "unsize")]
+use core::marker::Unsize;
+use core::ptr::{NonNull, Pointee};
+
+use crate::base::{ClonesafeStorage, ExactSizeStorage, LeaksafeStorage, MultiItemStorage, Storage};
+use crate::error::Result;
+
+/// A storage which wraps another in a [`RefCell`], so it can be used through a shared reference.
+/// [`Storage`] is implemented for `&RefCellStorage<S>`, in the same way as for a
+/// [`VirtHeap`](crate::heap::VirtHeap), so multiple collections can share the storage.
+///
+/// As every copy of the reference allocates from the same inner storage, the inner storage must
+/// be a [`MultiItemStorage`]. Single-item allocations through the reference are made with the
+/// inner storage's multi-item methods.
+///
+/// The inner storage must also be a [`LeaksafeStorage`], so its items live outside of the
+/// storage itself. Otherwise, allocating through one copy of the reference would mutably borrow
+/// memory which other collections still hold references into.
+///
+/// Each operation borrows the inner storage only for its own duration, and no borrow is ever
+/// held by a collection between calls. Borrowing only panics if the inner storage re-enters the
+/// same [`RefCellStorage`] from inside one of its own methods, such as if it were itself backed
+/// by an allocation from this storage.
+pub struct RefCellStorage<S> {
+    inner: RefCell<S>,
+}
+
+impl<S> RefCellStorage<S> {
+    /// Create a new [`RefCellStorage`] wrapping the provided storage
+    pub const fn new(storage: S) -> RefCellStorage<S> {
+        RefCellStorage {
+            inner: RefCell::new(storage),
+        }
+    }
+
+    /// Unwrap the inner storage
+    pub fn into_inner(self) -> S {
+        self.inner.into_inner()
+    }
+}
+
+impl<S> Default for RefCellStorage<S>
+where
+    S: Default,
+{
+    fn default() -> RefCellStorage<S> {
+        RefCellStorage::new(S::default())
+    }
+}
+
+impl<S> fmt::Debug for RefCellStorage<S>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RefCellStorage")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+// SAFETY: Inner storage promises to fulfill safety guarantees, and every instance of the
+//         reference shares the same inner storage
+unsafe impl<S> Storage for &RefCellStorage<S>
+where
+    S: MultiItemStorage + LeaksafeStorage,
+{
+    type Handle<T: ?Sized> = S::Handle<T>;
+
+    unsafe fn get<T: ?Sized>(&self, handle: Self::Handle<T>) -> NonNull<T> {
+        // SAFETY: Same safety requirements. The inner storage is leaksafe, so the pointer doesn't
+        //         point into it, and stays valid after the borrow ends
+        unsafe { self.inner.borrow().get(handle) }
+    }
+
+    unsafe fn layout_of_handle<T: ?Sized>(&self, handle: Self::Handle<T>) -> Layout {
+        // SAFETY: Same safety requirements
+        unsafe { self.inner.borrow().layout_of_handle(handle) }
+    }
+
+    fn from_raw_parts<T: ?Sized + Pointee>(
+        handle: Self::Handle<()>,
+        meta: T::Metadata,
+    ) -> Self::Handle<T> {
+        S::from_raw_parts(handle, meta)
+    }
+
+    fn cast<T: ?Sized + Pointee, U>(handle: Self::Handle<T>) -> Self::Handle<U> {
+        S::cast(handle)
+    }
+
+    fn cast_unsized<T: ?Sized + Pointee, U: ?Sized + Pointee<Metadata = T::Metadata>>(
+        handle: Self::Handle<T>,
+    ) -> Self::Handle<U> {
+        S::cast_unsized(handle)
+    }
+
+    #[cfg(feature = "unsize")]
+    fn coerce<T: ?Sized + Pointee + Unsize<U>, U: ?Sized + Pointee>(
+        handle: Self::Handle<T>,
+    ) -> Self::Handle<U> {
+        S::coerce(handle)
+    }
+
+    fn same_allocation<T: ?Sized + Pointee, U: ?Sized + Pointee>(
+        a: Self::Handle<T>,
+        b: Self::Handle<U>,
+    ) -> bool {
+        S::same_allocation(a, b)
+    }
+
+    fn will_fit_hint<T: ?Sized + Pointee>(&self, meta: T::Metadata) -> Option<bool> {
+        self.inner.borrow().will_fit_hint::<T>(meta)
+    }
+
+    fn max_range_hint<T>(&self) -> usize {
+        self.inner.borrow().max_range_hint::<T>()
+    }
+
+    fn allocate_single<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> Result<Self::Handle<T>> {
+        self.inner.borrow_mut().allocate(meta)
+    }
+
+    fn allocate_single_zeroed<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> Result<Self::Handle<T>> {
+        self.inner.borrow_mut().allocate_zeroed(meta)
+    }
+
+    unsafe fn deallocate_single<T: ?Sized>(&mut self, handle: Self::Handle<T>) {
+        // SAFETY: Same safety requirements
+        unsafe { self.inner.borrow_mut().deallocate(handle) }
+    }
+
+    unsafe fn try_grow<T>(
+        &mut self,
+        handle: Self::Handle<[T]>,
+        capacity: usize,
+    ) -> Result<Self::Handle<[T]>> {
+        // SAFETY: Same safety requirements
+        unsafe { self.inner.borrow_mut().try_grow(handle, capacity) }
+    }
+
+    unsafe fn try_grow_moved<T>(
+        &mut self,
+        handle: Self::Handle<[T]>,
+        capacity: usize,
+    ) -> Result<(Self::Handle<[T]>, bool)> {
+        // SAFETY: Same safety requirements
+        unsafe { self.inner.borrow_mut().try_grow_moved(handle, capacity) }
+    }
+
+    unsafe fn try_shrink<T>(
+        &mut self,
+        handle: Self::Handle<[T]>,
+        capacity: usize,
+    ) -> Result<Self::Handle<[T]>> {
+        // SAFETY: Same safety requirements
+        unsafe { self.inner.borrow_mut().try_shrink(handle, capacity) }
+    }
+}
+
+// SAFETY: Inner storage promises to fulfill safety guarantees
+unsafe impl<S> MultiItemStorage for &RefCellStorage<S>
+where
+    S: MultiItemStorage + LeaksafeStorage,
+{
+    fn allocate<T: ?Sized + Pointee>(&mut self, meta: T::Metadata) -> Result<Self::Handle<T>> {
+        self.inner.borrow_mut().allocate(meta)
+    }
+
+    fn allocate_zeroed<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> Result<Self::Handle<T>> {
+        self.inner.borrow_mut().allocate_zeroed(meta)
+    }
+
+    unsafe fn deallocate<T: ?Sized + Pointee>(&mut self, handle: Self::Handle<T>) {
+        // SAFETY: Same safety requirements
+        unsafe { self.inner.borrow_mut().deallocate(handle) }
+    }
+
+    fn is_allocated<T: ?Sized + Pointee>(&self, handle: Self::Handle<T>) -> bool {
+        self.inner.borrow().is_allocated(handle)
+    }
+}
+
+impl<S> ExactSizeStorage for &RefCellStorage<S>
+where
+    S: ExactSizeStorage + MultiItemStorage + LeaksafeStorage,
+{
+    fn will_fit<T: ?Sized + Pointee>(&self, meta: T::Metadata) -> bool {
+        self.inner.borrow().will_fit::<T>(meta)
+    }
+
+    fn max_range<T>(&self) -> usize {
+        self.inner.borrow().max_range::<T>()
+    }
+}
+
+// SAFETY: All copies of the reference share the same inner storage, so can handle each-other's
+//         allocations
+unsafe impl<S> ClonesafeStorage for &RefCellStorage<S> where S: MultiItemStorage + LeaksafeStorage {}
+
+// SAFETY: The inner storage is leaksafe, and is borrowed, so can't be dropped while any handle
+//         from it is in use, even if the reference the handle came from is dropped
+unsafe impl<S> LeaksafeStorage for &RefCellStorage<S> where S: MultiItemStorage + LeaksafeStorage {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc::GlobalAlloc;
+    use crate::backing::{Align8, Backing};
+    use crate::boxed::Box;
+    use crate::collections::Vec;
+    use crate::debug::Debug;
+    use crate::heap::VirtHeap;
+    use crate::rc::Rc;
+
+    type Inner = Debug<GlobalAlloc>;
+
+    #[test]
+    fn test_shared() {
+        let storage = RefCellStorage::new(Inner::new(GlobalAlloc::default()));
+
+        let mut v1 = Vec::<u32, _>::new_in(&storage);
+        let mut v2 = Vec::<u32, _>::new_in(&storage);
+        let b = Box::new_in(5u64, &storage);
+        v1.extend([1, 2, 3]);
+        let first = &v1[0];
+        // Growing another collection doesn't disturb existing references
+        v2.extend([4, 5]);
+        assert_eq!(*first, 1);
+
+        assert_eq!(&*v1, &[1, 2, 3]);
+        assert_eq!(&*v2, &[4, 5]);
+        assert_eq!(*b, 5);
+        let b2 = Box::new_in(6u8, &storage);
+        // Every allocation has its own slot
+        assert_eq!((*b, *b2), (5, 6));
+
+        drop((v1, v2, b, b2));
+        let _ = storage.into_inner();
+    }
+
+    #[test]
+    fn test_rc() {
+        let storage = RefCellStorage::new(Inner::new(GlobalAlloc::default()));
+
+        let rc1 = Rc::new_in([1u8, 2], &storage);
+        let rc2 = Rc::clone(&rc1);
+        drop(rc1);
+        assert_eq!(*rc2, [1, 2]);
+    }
+
+    #[test]
+    fn test_exact_size() {
+        let heap = VirtHeap::<Backing<32, Align8>, 1>::new();
+        let storage = RefCellStorage::new(&heap);
+        assert!((&storage).will_fit::<[u64; 4]>(()));
+        assert!(!(&storage).will_fit::<[u64; 5]>(()));
+    }
+}
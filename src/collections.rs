@@ -1,11 +1,27 @@
 //! Implementations of some common collection types, using storages for memory.
 
+#[cfg(feature = "vec")]
+pub mod array_vec;
+#[cfg(feature = "hash")]
+pub mod hash_map;
 #[cfg(feature = "linked")]
-mod linked_list;
+pub mod linked_list;
+#[cfg(feature = "vec")]
+pub mod sorted_set;
 #[cfg(feature = "vec")]
-mod vec;
+pub mod vec;
+#[cfg(feature = "deque")]
+pub mod vec_deque;
 
+#[cfg(feature = "vec")]
+pub use array_vec::ArrayVec;
+#[cfg(feature = "hash")]
+pub use hash_map::HashMap;
 #[cfg(feature = "linked")]
 pub use linked_list::LinkedList;
 #[cfg(feature = "vec")]
+pub use sorted_set::SortedSet;
+#[cfg(feature = "vec")]
 pub use vec::Vec;
+#[cfg(feature = "deque")]
+pub use vec_deque::VecDeque;
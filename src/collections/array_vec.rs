@@ -0,0 +1,325 @@
+//! A fixed-capacity vector, stored inline with space for exactly `N` elements
+
+use core::alloc::Layout;
+use core::cell::UnsafeCell;
+#[cfg(feature = "unsize")]
+use core::marker::Unsize;
+use core::mem::MaybeUninit;
+use core::ops::{Deref, DerefMut};
+use core::ptr::{NonNull, Pointee};
+use core::{fmt, mem};
+
+use crate::base::{ExactSizeStorage, Storage};
+use crate::collections::Vec;
+use crate::error::{Result, StorageError};
+use crate::handles::{Handle, MetaHandle};
+use crate::utils;
+
+/// Inline single-element storage sized and aligned for exactly `N` items of `T`.
+///
+/// Unlike [`SingleInline`](crate::inline::SingleInline), the backing doesn't need to be a
+/// [`StorageSafe`](crate::base::StorageSafe) type, so no space is lost rounding `[T; N]` up to
+/// some number of integers.
+pub struct ArrayStorage<T, const N: usize> {
+    storage: UnsafeCell<MaybeUninit<[T; N]>>,
+}
+
+impl<T, const N: usize> ArrayStorage<T, N> {
+    /// Create a new, empty [`ArrayStorage`]
+    pub const fn new() -> ArrayStorage<T, N> {
+        ArrayStorage {
+            storage: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+}
+
+// SAFETY: Internal checks ensure memory safety
+unsafe impl<T, const N: usize> Storage for ArrayStorage<T, N> {
+    type Handle<U: ?Sized> = MetaHandle<U>;
+
+    unsafe fn get<U: ?Sized>(&self, handle: Self::Handle<U>) -> NonNull<U> {
+        let ptr: NonNull<()> = NonNull::new(self.storage.get()).unwrap().cast();
+        NonNull::from_raw_parts(ptr, handle.metadata())
+    }
+
+    fn from_raw_parts<U: ?Sized + Pointee>(
+        handle: Self::Handle<()>,
+        meta: U::Metadata,
+    ) -> Self::Handle<U> {
+        <Self::Handle<U>>::from_raw_parts(handle, meta)
+    }
+
+    fn cast<U: ?Sized + Pointee, V>(handle: Self::Handle<U>) -> Self::Handle<V> {
+        handle.cast()
+    }
+
+    fn cast_unsized<U: ?Sized + Pointee, V: ?Sized + Pointee<Metadata = U::Metadata>>(
+        handle: Self::Handle<U>,
+    ) -> Self::Handle<V> {
+        handle.cast_unsized()
+    }
+
+    #[cfg(feature = "unsize")]
+    fn coerce<U: ?Sized + Pointee + Unsize<V>, V: ?Sized + Pointee>(
+        handle: Self::Handle<U>,
+    ) -> Self::Handle<V> {
+        handle.coerce()
+    }
+
+    fn will_fit_hint<U: ?Sized + Pointee>(&self, meta: U::Metadata) -> Option<bool> {
+        Some(self.will_fit::<U>(meta))
+    }
+
+    fn max_range_hint<U>(&self) -> usize {
+        self.max_range::<U>()
+    }
+
+    fn allocate_single<U: ?Sized + Pointee>(
+        &mut self,
+        meta: U::Metadata,
+    ) -> Result<Self::Handle<U>> {
+        utils::validate_layout::<U, [T; N]>(meta)?;
+        Ok(MetaHandle::from_metadata(meta))
+    }
+
+    unsafe fn deallocate_single<U: ?Sized>(&mut self, _handle: Self::Handle<U>) {}
+
+    unsafe fn try_grow<U>(
+        &mut self,
+        handle: Self::Handle<[U]>,
+        capacity: usize,
+    ) -> Result<Self::Handle<[U]>> {
+        debug_assert!(capacity >= handle.metadata());
+        let new_layout = Layout::array::<U>(capacity).map_err(|_| StorageError::exceeds_max())?;
+        utils::validate_layout_for::<[T; N]>(new_layout)?;
+        Ok(MetaHandle::from_metadata(capacity))
+    }
+
+    unsafe fn try_shrink<U>(
+        &mut self,
+        handle: Self::Handle<[U]>,
+        capacity: usize,
+    ) -> Result<Self::Handle<[U]>> {
+        debug_assert!(capacity <= handle.metadata());
+        Ok(MetaHandle::from_metadata(capacity))
+    }
+}
+
+impl<T, const N: usize> ExactSizeStorage for ArrayStorage<T, N> {
+    fn will_fit<U: ?Sized + Pointee>(&self, meta: U::Metadata) -> bool {
+        utils::validate_layout::<U, [T; N]>(meta).is_ok()
+    }
+
+    fn max_range<U>(&self) -> usize {
+        match mem::size_of::<U>() {
+            0 => usize::MAX,
+            size => mem::size_of::<[T; N]>() / size,
+        }
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for ArrayStorage<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArrayStorage").finish_non_exhaustive()
+    }
+}
+
+impl<T, const N: usize> Default for ArrayStorage<T, N> {
+    fn default() -> ArrayStorage<T, N> {
+        ArrayStorage::new()
+    }
+}
+
+/// A vector with a fixed capacity of `N` elements, stored inline.
+///
+/// No operation on this type panics due to running out of space - pushing to a full vector hands
+/// the value back instead.
+pub struct ArrayVec<T, const N: usize> {
+    inner: Vec<T, ArrayStorage<T, N>>,
+}
+
+impl<T, const N: usize> ArrayVec<T, N> {
+    /// Create a new, empty [`ArrayVec`]
+    pub fn new() -> ArrayVec<T, N> {
+        let inner = Vec::try_with_capacity_in(N, ArrayStorage::new())
+            .unwrap_or_else(|_| unreachable!("ArrayStorage always fits N items"));
+        ArrayVec { inner }
+    }
+
+    /// Get the number of elements in the vector
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Check if the vector contains no elements
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Get the capacity of the vector, which is always `N`
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Check if the vector is at capacity, so no more elements can be pushed
+    pub fn is_full(&self) -> bool {
+        self.len() == N
+    }
+
+    /// Get how many more elements can be pushed before the vector is full
+    pub fn remaining_capacity(&self) -> usize {
+        N - self.len()
+    }
+
+    /// Push an element onto the end of the vector, or return it if the vector is full
+    pub fn try_push(&mut self, val: T) -> core::result::Result<(), T> {
+        if self.is_full() {
+            return Err(val);
+        }
+        // Never grows, as there's space left
+        self.inner.push(val);
+        Ok(())
+    }
+
+    /// Remove the element at the end of the vector and return it, if there is one
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.inner.pop())
+        }
+    }
+
+    /// Remove the element at `pos` and return it, shifting all elements after it down. Returns
+    /// `None` if `pos` is out of bounds.
+    pub fn remove(&mut self, pos: usize) -> Option<T> {
+        if pos < self.len() {
+            Some(self.inner.remove(pos))
+        } else {
+            None
+        }
+    }
+
+    /// Push elements from an iterator onto the end of the vector until it's exhausted or the
+    /// vector is full.
+    ///
+    /// If the vector fills up with elements remaining, the element that didn't fit is returned
+    /// alongside the rest of the iterator, as checking for a remaining element has to take it
+    /// from the original iterator. See [`Vec::try_extend`].
+    pub fn try_extend<I>(&mut self, iter: I) -> core::result::Result<(), (T, I::IntoIter)>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut iter = iter.into_iter();
+        while let Some(val) = iter.next() {
+            if self.is_full() {
+                return Err((val, iter));
+            }
+            self.inner.push(val);
+        }
+        Ok(())
+    }
+
+    /// Convert this into the underlying [`Vec`]
+    pub fn into_inner(self) -> Vec<T, ArrayStorage<T, N>> {
+        self.inner
+    }
+}
+
+impl<T, const N: usize> Default for ArrayVec<T, N> {
+    fn default() -> ArrayVec<T, N> {
+        ArrayVec::new()
+    }
+}
+
+impl<T, const N: usize> Deref for ArrayVec<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.inner
+    }
+}
+
+impl<T, const N: usize> DerefMut for ArrayVec<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.inner
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for ArrayVec<T, N>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_size() {
+        assert_eq!(mem::size_of::<ArrayStorage<u8, 3>>(), 3);
+        assert_eq!(mem::size_of::<ArrayStorage<[u16; 3], 5>>(), 30);
+        assert_eq!(
+            mem::align_of::<ArrayStorage<u64, 1>>(),
+            mem::align_of::<u64>()
+        );
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let mut v = ArrayVec::<u8, 3>::new();
+        assert_eq!(v.capacity(), 3);
+        assert_eq!(v.pop(), None);
+
+        assert_eq!(v.try_push(1), Ok(()));
+        assert_eq!(v.try_push(2), Ok(()));
+        assert_eq!(v.try_push(3), Ok(()));
+        assert!(v.is_full());
+        assert_eq!(v.try_push(4), Err(4));
+        assert_eq!(&*v, &[1, 2, 3]);
+
+        assert_eq!(v.remove(3), None);
+        assert_eq!(v.remove(0), Some(1));
+        assert_eq!(v.pop(), Some(3));
+        assert_eq!(v.remaining_capacity(), 2);
+        assert_eq!(&*v, &[2]);
+    }
+
+    #[test]
+    fn test_try_extend() {
+        let mut v = ArrayVec::<u32, 4>::new();
+        assert!(v.try_extend([1, 2]).is_ok());
+
+        let (item, mut rest) = v.try_extend(3..10).unwrap_err();
+        assert_eq!(&*v, &[1, 2, 3, 4]);
+        assert_eq!(item, 5);
+        assert_eq!(rest.next(), Some(6));
+        assert!(v.try_extend([]).is_ok());
+        assert!(v.try_extend([5]).is_err());
+    }
+
+    #[test]
+    fn test_zst() {
+        let mut v = ArrayVec::<(), 2>::new();
+        assert_eq!(v.try_push(()), Ok(()));
+        assert_eq!(v.try_push(()), Ok(()));
+        assert_eq!(v.try_push(()), Err(()));
+        assert_eq!(v.len(), 2);
+    }
+
+    #[test]
+    fn test_drop() {
+        use std::rc::Rc;
+
+        let rc = Rc::new(());
+        let mut v = ArrayVec::<_, 4>::new();
+        v.try_extend([rc.clone(), rc.clone(), rc.clone()]).unwrap();
+        assert_eq!(Rc::strong_count(&rc), 4);
+        drop(v);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+}
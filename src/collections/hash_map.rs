@@ -0,0 +1,862 @@
+//! A storage-based hash map, and the types it uses
+//!
+//! The map uses open addressing with quadratic probing over a single storage allocation, so it
+//! only requires a [`Storage`] which can grow ranges, not a global allocator.
+
+use core::borrow::Borrow;
+use core::hash::{BuildHasher, Hash, Hasher};
+use core::iter::FusedIterator;
+use core::mem::MaybeUninit;
+use core::{fmt, mem, ptr, slice};
+
+use crate::base::Storage;
+use crate::error::Result;
+
+/// Smallest non-zero number of buckets. Must be a power of two, and large enough that the maximum
+/// load leaves at least one bucket empty.
+const MIN_BUCKETS: usize = 8;
+
+/// The number of buckets which may be occupied (including deleted buckets) before the map must
+/// grow or rehash
+fn max_load(buckets: usize) -> usize {
+    buckets / 8 * 7
+}
+
+/// Iterate the bucket indices to visit for a hash. Probing by triangular numbers visits every
+/// bucket exactly once, as long as the number of buckets is a power of two.
+fn probe_seq(hash: u64, buckets: usize) -> impl Iterator<Item = usize> {
+    let mask = buckets.wrapping_sub(1);
+    // Only the low bits of the hash are used, so truncation doesn't matter
+    #[allow(clippy::cast_possible_truncation)]
+    let start = hash as usize & mask;
+    (0..buckets).scan(start, move |pos, stride| {
+        let cur = *pos;
+        *pos = (*pos + stride + 1) & mask;
+        Some(cur)
+    })
+}
+
+/// A [`Hasher`] implementing 64-bit FNV-1a. This is fast for small keys and doesn't need any
+/// randomness, so works without `std`, but it provides no protection against `HashDoS` attacks.
+#[derive(Clone, Copy, Debug)]
+pub struct FnvHasher(u64);
+
+impl Default for FnvHasher {
+    fn default() -> FnvHasher {
+        FnvHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// The default [`BuildHasher`] for [`HashMap`], creating [`FnvHasher`]s
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultHasher;
+
+impl BuildHasher for DefaultHasher {
+    type Hasher = FnvHasher;
+
+    fn build_hasher(&self) -> FnvHasher {
+        FnvHasher::default()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum State {
+    /// Never held an entry since the last rehash. Ends a probe sequence
+    Empty,
+    /// Holds an initialized entry
+    Full,
+    /// Held an entry which was removed. Probe sequences continue past it
+    Deleted,
+    /// Holds an initialized entry which hasn't been placed yet during a rehash
+    Rehash,
+}
+
+struct Bucket<K, V> {
+    state: State,
+    entry: MaybeUninit<(K, V)>,
+}
+
+impl<K, V> Bucket<K, V> {
+    fn is_init(&self) -> bool {
+        matches!(self.state, State::Full | State::Rehash)
+    }
+}
+
+/// Storage based implementation of [`HashMap`](std::collections::HashMap)
+///
+/// Unlike the standard map, the default hasher is deterministic (See [`FnvHasher`]). Use a
+/// different `H` if keys may come from an untrusted source.
+pub struct HashMap<K, V, S, H = DefaultHasher>
+where
+    S: Storage,
+{
+    handle: S::Handle<[Bucket<K, V>]>,
+    len: usize,
+    deleted: usize,
+    hasher: H,
+    storage: S,
+}
+
+impl<K, V, S, H> HashMap<K, V, S, H>
+where
+    S: Storage + Default,
+    H: Default,
+{
+    /// Create a new, empty [`HashMap`], creating a default instance of the desired storage.
+    ///
+    /// # Panics
+    ///
+    /// If the backing allocation fails for any reason
+    pub fn new() -> HashMap<K, V, S, H> {
+        HashMap::with_hasher_in(H::default(), S::default())
+    }
+
+    /// Attempt to create a new, empty [`HashMap`], creating a default instance of the desired
+    /// storage.
+    pub fn try_new() -> Result<HashMap<K, V, S, H>> {
+        HashMap::try_with_hasher_in(H::default(), S::default())
+    }
+}
+
+impl<K, V, S, H> HashMap<K, V, S, H>
+where
+    S: Storage,
+    H: Default,
+{
+    /// Create a new, empty [`HashMap`], using the provided storage instance.
+    ///
+    /// # Panics
+    ///
+    /// If the backing allocation fails for any reason
+    pub fn new_in(storage: S) -> HashMap<K, V, S, H> {
+        HashMap::with_hasher_in(H::default(), storage)
+    }
+
+    /// Attempt to create a new, empty [`HashMap`], using the provided storage instance.
+    pub fn try_new_in(storage: S) -> Result<HashMap<K, V, S, H>> {
+        HashMap::try_with_hasher_in(H::default(), storage)
+    }
+}
+
+impl<K, V, S, H> HashMap<K, V, S, H>
+where
+    S: Storage,
+{
+    /// Create a new, empty [`HashMap`], using the provided hasher and storage instance.
+    ///
+    /// # Panics
+    ///
+    /// If the backing allocation fails for any reason
+    pub fn with_hasher_in(hasher: H, mut storage: S) -> HashMap<K, V, S, H> {
+        HashMap {
+            handle: storage.allocate_single(0).unwrap(),
+            len: 0,
+            deleted: 0,
+            hasher,
+            storage,
+        }
+    }
+
+    /// Attempt to create a new, empty [`HashMap`], using the provided hasher and storage instance.
+    pub fn try_with_hasher_in(hasher: H, mut storage: S) -> Result<HashMap<K, V, S, H>> {
+        Ok(HashMap {
+            handle: storage.allocate_single(0)?,
+            len: 0,
+            deleted: 0,
+            hasher,
+            storage,
+        })
+    }
+
+    /// Get the number of entries in the map
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check whether the map contains no entries
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the number of entries the map can hold before it needs to grow
+    pub fn capacity(&self) -> usize {
+        max_load(self.buckets().len())
+    }
+
+    /// Get a reference to the map's hasher
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    /// Iterate over references to the entries of the map, in arbitrary order
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            buckets: self.buckets().iter(),
+            remaining: self.len,
+        }
+    }
+
+    /// Iterate over the entries of the map in arbitrary order, with mutable references to the
+    /// values
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        let remaining = self.len;
+        IterMut {
+            buckets: self.buckets_mut().iter_mut(),
+            remaining,
+        }
+    }
+
+    /// Remove all entries from the map, keeping the allocated capacity
+    pub fn clear(&mut self) {
+        // SAFETY: Handle is guaranteed valid by internal invariant. The lifetime isn't tied to
+        //         `self`, allowing the counts to be updated while this is live
+        let buckets = unsafe { self.storage.get(self.handle).as_mut() };
+
+        // The counts are kept in step with the buckets, so if a destructor panics, the entries
+        // not yet reached are still correctly tracked by the map
+        for bucket in buckets {
+            match bucket.state {
+                State::Empty => continue,
+                State::Deleted => self.deleted -= 1,
+                State::Full | State::Rehash => self.len -= 1,
+            }
+            let was_init = bucket.is_init();
+            bucket.state = State::Empty;
+            if was_init {
+                // SAFETY: The entry was initialized, and is no longer tracked by the state
+                unsafe { bucket.entry.assume_init_drop() };
+            }
+        }
+    }
+
+    fn buckets(&self) -> &[Bucket<K, V>] {
+        // SAFETY: Handle is guaranteed valid by internal invariant, and all bucket states are
+        //         initialized outside of `grow`
+        unsafe { self.storage.get(self.handle).as_ref() }
+    }
+
+    fn buckets_mut(&mut self) -> &mut [Bucket<K, V>] {
+        // SAFETY: Handle is guaranteed valid by internal invariant, and all bucket states are
+        //         initialized outside of `grow`
+        unsafe { self.storage.get(self.handle).as_mut() }
+    }
+}
+
+impl<K, V, S, H> HashMap<K, V, S, H>
+where
+    K: Eq + Hash,
+    S: Storage,
+    H: BuildHasher,
+{
+    /// Insert a key-value pair into the map. If the key was already present, its value is
+    /// replaced and the old value is returned.
+    ///
+    /// # Panics
+    ///
+    /// If the map needs to grow and the backing storage fails to do so
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let hash = self.hasher.hash_one(&key);
+
+        if let Some(pos) = self.find(hash, &key) {
+            // SAFETY: `find` only returns full buckets
+            let entry = unsafe { self.buckets_mut()[pos].entry.assume_init_mut() };
+            return Some(mem::replace(&mut entry.1, value));
+        }
+
+        self.reserve_one().expect("Couldn't grow HashMap buffer");
+
+        let pos = self.find_slot(hash);
+        let bucket = &mut self.buckets_mut()[pos];
+        let was_deleted = bucket.state == State::Deleted;
+        bucket.state = State::Full;
+        bucket.entry.write((key, value));
+
+        self.len += 1;
+        if was_deleted {
+            self.deleted -= 1;
+        }
+        None
+    }
+
+    /// Get a reference to the value associated with a key, if it exists
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let pos = self.find(self.hasher.hash_one(key), key)?;
+        // SAFETY: `find` only returns full buckets
+        Some(&unsafe { self.buckets()[pos].entry.assume_init_ref() }.1)
+    }
+
+    /// Get a mutable reference to the value associated with a key, if it exists
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let pos = self.find(self.hasher.hash_one(key), key)?;
+        // SAFETY: `find` only returns full buckets
+        Some(&mut unsafe { self.buckets_mut()[pos].entry.assume_init_mut() }.1)
+    }
+
+    /// Check whether the map contains a value for a key
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.find(self.hasher.hash_one(key), key).is_some()
+    }
+
+    /// Remove a key from the map, returning its value if it was present
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    /// Remove a key from the map, returning the stored key and value if it was present
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let pos = self.find(self.hasher.hash_one(key), key)?;
+        let bucket = &mut self.buckets_mut()[pos];
+        bucket.state = State::Deleted;
+        // SAFETY: `find` only returns full buckets, and the state no longer tracks the entry
+        let entry = unsafe { bucket.entry.assume_init_read() };
+
+        self.len -= 1;
+        self.deleted += 1;
+        Some(entry)
+    }
+
+    /// Find the bucket holding a key, if there is one
+    fn find<Q>(&self, hash: u64, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq,
+    {
+        let buckets = self.buckets();
+        for pos in probe_seq(hash, buckets.len()) {
+            let bucket = &buckets[pos];
+            match bucket.state {
+                State::Empty => return None,
+                // SAFETY: Full buckets hold an initialized entry
+                State::Full if unsafe { bucket.entry.assume_init_ref() }.0.borrow() == key => {
+                    return Some(pos)
+                }
+                _ => (),
+            }
+        }
+        None
+    }
+
+    /// Find the first bucket a new entry with this hash may be placed in. The load factor
+    /// guarantees there always is one.
+    fn find_slot(&self, hash: u64) -> usize {
+        let buckets = self.buckets();
+        probe_seq(hash, buckets.len())
+            .find(|&pos| buckets[pos].state != State::Full)
+            .expect("HashMap has no free buckets")
+    }
+
+    /// Make sure one more entry can be inserted, by either growing or clearing out deleted buckets
+    fn reserve_one(&mut self) -> Result<()> {
+        let buckets = self.buckets().len();
+        if self.len + self.deleted < max_load(buckets) {
+            return Ok(());
+        }
+
+        if self.len < max_load(buckets) / 2 {
+            // Mostly deleted buckets, reclaiming them is enough
+            self.rehash();
+            Ok(())
+        } else {
+            self.grow(usize::max(buckets * 2, MIN_BUCKETS))
+        }
+    }
+
+    fn grow(&mut self, new_buckets: usize) -> Result<()> {
+        let old_buckets = self.buckets().len();
+
+        // SAFETY: Handle is guaranteed valid by internal invariant
+        //         New bucket count cannot be less than old due to how it's calculated
+        self.handle = unsafe { self.storage.try_grow(self.handle, new_buckets) }?;
+
+        // SAFETY: Handle is guaranteed valid, as we just got it from the storage
+        let base = unsafe { self.storage.get(self.handle) }
+            .as_ptr()
+            .cast::<Bucket<K, V>>();
+        for pos in old_buckets..new_buckets {
+            // SAFETY: `pos` is in-bounds of the new allocation. The new buckets aren't
+            //         initialized yet, so no reference may be created before this
+            unsafe { ptr::addr_of_mut!((*base.add(pos)).state).write(State::Empty) };
+        }
+
+        self.rehash();
+        Ok(())
+    }
+
+    /// Move every entry to its correct position for the current number of buckets, and clear out
+    /// all deleted buckets.
+    ///
+    /// If the hasher panics, the entries which haven't been placed yet are dropped, so the map
+    /// stays valid.
+    fn rehash(&mut self) {
+        // SAFETY: Handle is guaranteed valid by internal invariant. The lifetime isn't tied to
+        //         `self`, allowing the hasher to be used while this is live
+        let buckets = unsafe { self.storage.get(self.handle).as_mut() };
+
+        for bucket in buckets.iter_mut() {
+            bucket.state = match bucket.state {
+                State::Full => State::Rehash,
+                _ => State::Empty,
+            };
+        }
+        self.deleted = 0;
+
+        let guard = RehashGuard {
+            buckets,
+            len: &mut self.len,
+        };
+        let buckets = &mut *guard.buckets;
+        for pos in 0..buckets.len() {
+            while buckets[pos].state == State::Rehash {
+                // SAFETY: Rehash buckets hold an initialized entry
+                let key = &unsafe { buckets[pos].entry.assume_init_ref() }.0;
+                let hash = self.hasher.hash_one(key);
+                // The first bucket in the probe sequence that isn't already placed. Any bucket
+                // before it is full, so lookups will always probe past them
+                let target = probe_seq(hash, buckets.len())
+                    .find(|&target| buckets[target].state != State::Full)
+                    .expect("HashMap has no free buckets");
+
+                if target == pos {
+                    buckets[pos].state = State::Full;
+                } else {
+                    // If the target held an unplaced entry, it's swapped into `pos` and will be
+                    // placed next. Otherwise, `pos` ends up empty.
+                    buckets.swap(pos, target);
+                    buckets[target].state = State::Full;
+                }
+            }
+        }
+    }
+}
+
+/// Drops any entries left unplaced by a [`HashMap::rehash`], so they can't be mistaken for free
+/// buckets if it unwinds. A completed rehash leaves nothing for this to do.
+struct RehashGuard<'a, K, V> {
+    buckets: &'a mut [Bucket<K, V>],
+    len: &'a mut usize,
+}
+
+impl<K, V> Drop for RehashGuard<'_, K, V> {
+    fn drop(&mut self) {
+        for bucket in self.buckets.iter_mut() {
+            if bucket.state == State::Rehash {
+                bucket.state = State::Empty;
+                *self.len -= 1;
+                // SAFETY: Rehash buckets hold an initialized entry, which is no longer tracked
+                //         by the state
+                unsafe { bucket.entry.assume_init_drop() };
+            }
+        }
+    }
+}
+
+// SAFETY: A map uniquely owns its entries, so can be sent if they, its storage, and its hasher
+//         can be
+unsafe impl<K, V, S, H> Send for HashMap<K, V, S, H>
+where
+    K: Send,
+    V: Send,
+    S: Storage + Send,
+    H: Send,
+{
+}
+
+// SAFETY: A shared map only provides shared access to its entries, storage, and hasher
+unsafe impl<K, V, S, H> Sync for HashMap<K, V, S, H>
+where
+    K: Sync,
+    V: Sync,
+    S: Storage + Sync,
+    H: Sync,
+{
+}
+
+impl<K, V, S, H> fmt::Debug for HashMap<K, V, S, H>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+    S: Storage,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, S, H> Default for HashMap<K, V, S, H>
+where
+    S: Storage + Default,
+    H: Default,
+{
+    fn default() -> Self {
+        HashMap::new()
+    }
+}
+
+impl<K, V, S, H> Drop for HashMap<K, V, S, H>
+where
+    S: Storage,
+{
+    fn drop(&mut self) {
+        for bucket in self.buckets_mut() {
+            if bucket.is_init() {
+                // SAFETY: This is `drop`, so no one else will observe these values
+                unsafe { bucket.entry.assume_init_drop() };
+            }
+        }
+        // SAFETY: Handle is guaranteed valid by internal invariant
+        unsafe { self.storage.deallocate_single(self.handle) }
+    }
+}
+
+impl<K, V, S, H> Extend<(K, V)> for HashMap<K, V, S, H>
+where
+    K: Eq + Hash,
+    S: Storage,
+    H: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<'a, K, V, S, H> IntoIterator for &'a HashMap<K, V, S, H>
+where
+    S: Storage,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V, S, H> IntoIterator for &'a mut HashMap<K, V, S, H>
+where
+    S: Storage,
+{
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Iterator over references to the entries of a [`HashMap`]
+pub struct Iter<'a, K, V> {
+    buckets: slice::Iter<'a, Bucket<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bucket = self.buckets.find(|bucket| bucket.state == State::Full)?;
+        self.remaining -= 1;
+        // SAFETY: Full buckets hold an initialized entry
+        let (key, value) = unsafe { bucket.entry.assume_init_ref() };
+        Some((key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<K, V> FusedIterator for Iter<'_, K, V> {}
+
+impl<K, V> Clone for Iter<'_, K, V> {
+    fn clone(&self) -> Self {
+        Iter {
+            buckets: self.buckets.clone(),
+            remaining: self.remaining,
+        }
+    }
+}
+
+/// Iterator over the entries of a [`HashMap`], with mutable references to the values
+pub struct IterMut<'a, K, V> {
+    buckets: slice::IterMut<'a, Bucket<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bucket = self.buckets.find(|bucket| bucket.state == State::Full)?;
+        self.remaining -= 1;
+        // SAFETY: Full buckets hold an initialized entry
+        let (key, value) = unsafe { bucket.entry.assume_init_mut() };
+        Some((&*key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {}
+
+impl<K, V> FusedIterator for IterMut<'_, K, V> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::alloc::GlobalAlloc;
+    use crate::heap::VirtHeap;
+    use crate::inline::SingleInline;
+
+    type HashMap<K, V> = super::HashMap<K, V, SingleInline<[usize; 64]>>;
+
+    #[test]
+    fn map_insert_get() {
+        let mut map = HashMap::<u32, u32>::new();
+        assert!(map.is_empty());
+
+        assert_eq!(map.insert(1, 10), None);
+        assert_eq!(map.insert(2, 20), None);
+        assert_eq!(map.insert(1, 11), Some(10));
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&1), Some(&11));
+        assert_eq!(map.get(&2), Some(&20));
+        assert_eq!(map.get(&3), None);
+
+        *map.get_mut(&2).unwrap() += 1;
+        assert_eq!(map.get(&2), Some(&21));
+    }
+
+    #[test]
+    fn map_remove() {
+        let mut map = HashMap::<u32, u32>::new();
+        map.extend([(1, 10), (2, 20), (3, 30)]);
+
+        assert_eq!(map.remove(&2), Some(20));
+        assert_eq!(map.remove(&2), None);
+        assert_eq!(map.len(), 2);
+        assert!(!map.contains_key(&2));
+        assert!(map.contains_key(&1));
+        assert!(map.contains_key(&3));
+
+        map.insert(2, 22);
+        assert_eq!(map.get(&2), Some(&22));
+    }
+
+    #[test]
+    fn map_grow() {
+        let mut map = super::HashMap::<u32, u32, GlobalAlloc>::new();
+
+        for i in 0..1000 {
+            map.insert(i, i * 2);
+        }
+        assert_eq!(map.len(), 1000);
+        assert!(map.capacity() >= 1000);
+
+        for i in 0..1000 {
+            assert_eq!(map.get(&i), Some(&(i * 2)));
+        }
+    }
+
+    #[test]
+    fn map_churn() {
+        let heap = VirtHeap::<usize, 64>::new();
+        let mut map = super::HashMap::<u32, u32, _>::new_in(&heap);
+
+        // Repeated insert and remove fills the map with deleted buckets, which must be reclaimed
+        // without growing further
+        let mut capacity = 0;
+        for i in 0..1000 {
+            map.insert(i, i);
+            if i >= 4 {
+                assert_eq!(map.remove(&(i - 4)), Some(i - 4));
+            }
+            if i == 100 {
+                capacity = map.capacity();
+            }
+        }
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.capacity(), capacity);
+        for i in 996..1000 {
+            assert_eq!(map.get(&i), Some(&i));
+        }
+    }
+
+    #[test]
+    fn map_iter() {
+        let mut map = HashMap::<u32, u32>::new();
+        map.extend([(1, 10), (2, 20), (3, 30)]);
+        map.remove(&2);
+
+        assert_eq!(map.iter().len(), 2);
+        for (_, v) in &mut map {
+            *v += 1;
+        }
+        let mut entries = map
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .collect::<std::vec::Vec<_>>();
+        entries.sort();
+        assert_eq!(entries, [(1, 11), (3, 31)]);
+    }
+
+    #[test]
+    fn map_borrow() {
+        let mut map = super::HashMap::<crate::string::String<GlobalAlloc>, u32, GlobalAlloc>::new();
+        map.insert("a".into(), 1);
+        map.insert("b".into(), 2);
+
+        assert_eq!(map.get("a"), Some(&1));
+        assert_eq!(map.remove("b"), Some(2));
+        assert_eq!(map.get("b"), None);
+    }
+
+    #[test]
+    fn map_drop() {
+        use std::rc::Rc;
+
+        let item = Rc::new(());
+        let mut map = super::HashMap::<u32, Rc<()>, GlobalAlloc>::new();
+        for i in 0..20 {
+            map.insert(i, Rc::clone(&item));
+        }
+        map.remove(&3);
+        assert_eq!(Rc::strong_count(&item), 20);
+
+        map.clear();
+        assert_eq!(Rc::strong_count(&item), 1);
+        assert!(map.is_empty());
+
+        map.insert(1, Rc::clone(&item));
+        drop(map);
+        assert_eq!(Rc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn map_clear_panic() {
+        use std::panic::{self, AssertUnwindSafe};
+        use std::rc::Rc;
+
+        struct PanicDrop {
+            panics: bool,
+            _item: Rc<()>,
+        }
+
+        impl Drop for PanicDrop {
+            fn drop(&mut self) {
+                assert!(!self.panics, "PanicDrop dropped");
+            }
+        }
+
+        let item = Rc::new(());
+        let mut map = super::HashMap::<u32, PanicDrop, GlobalAlloc>::new();
+        for i in 0..8 {
+            let value = PanicDrop {
+                panics: i == 3,
+                _item: Rc::clone(&item),
+            };
+            map.insert(i, value);
+        }
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| map.clear()));
+        assert!(res.is_err());
+        // Entries which weren't reached are still in the map, and counted by it
+        assert_eq!(map.iter().count(), map.len());
+        assert_eq!(Rc::strong_count(&item), map.len() + 1);
+
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(Rc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn map_rehash_panic() {
+        use std::cell::Cell;
+        use std::hash::{Hash, Hasher};
+        use std::panic::{self, AssertUnwindSafe};
+        use std::rc::Rc;
+
+        struct Key(u32, Rc<Cell<bool>>);
+
+        impl Hash for Key {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                assert!(!self.1.get(), "Key hashed");
+                self.0.hash(state);
+            }
+        }
+
+        impl PartialEq for Key {
+            fn eq(&self, other: &Key) -> bool {
+                self.0 == other.0
+            }
+        }
+
+        impl Eq for Key {}
+
+        let panics = Rc::new(Cell::new(false));
+        let mut map = super::HashMap::<Key, u32, GlobalAlloc>::new();
+        map.insert(Key(0, Rc::clone(&panics)), 0);
+        let mut i = 1;
+        while map.len() < map.capacity() {
+            map.insert(Key(i, Rc::new(Cell::new(false))), i);
+            i += 1;
+        }
+
+        // The next insert grows the map, which rehashes the panicking key
+        panics.set(true);
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            map.insert(Key(i, Rc::new(Cell::new(false))), i);
+        }));
+        assert!(res.is_err());
+        assert_eq!(map.iter().count(), map.len());
+        // The key which couldn't be placed was dropped along with its entry
+        assert_eq!(Rc::strong_count(&panics), 1);
+
+        // The map is still usable, and the entries which were placed can be found
+        panics.set(false);
+        for (key, &value) in map.iter() {
+            assert_eq!(key.0, value);
+        }
+        map.insert(Key(100, Rc::new(Cell::new(false))), 100);
+        assert_eq!(map.get(&Key(100, Rc::new(Cell::new(false)))), Some(&100));
+        assert_eq!(map.iter().count(), map.len());
+    }
+}
@@ -1,3 +1,9 @@
+//! A storage-based implementation of [`std::collections::LinkedList`]
+
+use core::cmp::Ordering;
+use core::fmt;
+use core::iter::FusedIterator;
+
 use crate::base::{MultiItemStorage, Storage};
 
 type NodeRef<T, S> = <S as Storage>::Handle<Node<T, S>>;
@@ -150,6 +156,15 @@ impl<T, S: Storage + MultiItemStorage> LinkedList<T, S> {
         // SAFETY: We immutable borrow self, and we got this node from our internal list
         Some(unsafe { self.node_val(cur) })
     }
+
+    /// Get an iterator over references to the items in this list, from first to last
+    pub fn iter(&self) -> Iter<'_, T, S> {
+        Iter {
+            list: self,
+            next: self.first_node(),
+            remaining: self.len,
+        }
+    }
 }
 
 impl<T, S: Storage + MultiItemStorage + Default> LinkedList<T, S> {
@@ -165,6 +180,90 @@ impl<T, S: Storage + MultiItemStorage + Default> Default for LinkedList<T, S> {
     }
 }
 
+// SAFETY: A list uniquely owns its nodes, so can be sent if the values and storage can be
+unsafe impl<T: Send, S: Storage + MultiItemStorage + Send> Send for LinkedList<T, S> {}
+
+// SAFETY: A shared list only provides shared access to its values and storage
+unsafe impl<T: Sync, S: Storage + MultiItemStorage + Sync> Sync for LinkedList<T, S> {}
+
+impl<T, S> fmt::Debug for LinkedList<T, S>
+where
+    T: fmt::Debug,
+    S: Storage + MultiItemStorage,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T, S1, S2> PartialEq<LinkedList<T, S2>> for LinkedList<T, S1>
+where
+    T: PartialEq,
+    S1: Storage + MultiItemStorage,
+    S2: Storage + MultiItemStorage,
+{
+    fn eq(&self, other: &LinkedList<T, S2>) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, S: Storage + MultiItemStorage> Eq for LinkedList<T, S> {}
+
+impl<T, S1, S2> PartialOrd<LinkedList<T, S2>> for LinkedList<T, S1>
+where
+    T: PartialOrd,
+    S1: Storage + MultiItemStorage,
+    S2: Storage + MultiItemStorage,
+{
+    fn partial_cmp(&self, other: &LinkedList<T, S2>) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T: Ord, S: Storage + MultiItemStorage> Ord for LinkedList<T, S> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<'a, T, S: Storage + MultiItemStorage> IntoIterator for &'a LinkedList<T, S> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over references to the items of a [`LinkedList`]
+pub struct Iter<'a, T, S: Storage + MultiItemStorage> {
+    list: &'a LinkedList<T, S>,
+    next: Option<NodeRef<T, S>>,
+    remaining: usize,
+}
+
+impl<'a, T, S: Storage + MultiItemStorage> Iterator for Iter<'a, T, S> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cur = self.next?;
+        // SAFETY: Nodes in our list should all have valid pointers, and the list is immutably
+        //         borrowed for as long as we live
+        let node = unsafe { self.list.storage.get(cur).as_ref() };
+        self.next = node.next;
+        self.remaining -= 1;
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T, S: Storage + MultiItemStorage> ExactSizeIterator for Iter<'_, T, S> {}
+
+impl<T, S: Storage + MultiItemStorage> FusedIterator for Iter<'_, T, S> {}
+
 impl<T, S: Storage + MultiItemStorage> Drop for LinkedList<T, S> {
     fn drop(&mut self) {
         let (first, _) = match self.nodes {
@@ -192,6 +291,8 @@ impl<T, S: Storage + MultiItemStorage> Drop for LinkedList<T, S> {
 mod tests {
     use super::LinkedList;
     use crate::alloc::GlobalAlloc;
+    use crate::heap::VirtHeap;
+    use core::cmp::Ordering;
 
     #[test]
     fn test_push() {
@@ -216,4 +317,47 @@ mod tests {
         assert_eq!(list.get(1), Some(&2));
         assert_eq!(list.get(2), None);
     }
+
+    #[test]
+    fn test_iter() {
+        let mut list = LinkedList::<i32, GlobalAlloc>::new();
+        assert_eq!(list.iter().next(), None);
+
+        list.push(1);
+        list.push(2);
+        list.push(3);
+
+        let mut iter = list.iter();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.len(), 2);
+        assert_eq!((&list).into_iter().copied().collect::<Vec<_>>(), [1, 2, 3]);
+
+        iter.by_ref().for_each(drop);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_cmp() {
+        let mut a = LinkedList::<i32, GlobalAlloc>::new();
+        let mut b = LinkedList::<i32, GlobalAlloc>::new();
+        assert_eq!(a, b);
+
+        a.push(1);
+        a.push(2);
+        b.push(1);
+        assert!(a > b);
+        b.push(3);
+        assert!(a < b);
+        assert_ne!(a, b);
+        assert_eq!(a.cmp(&b), Ordering::Less);
+
+        let heap = VirtHeap::<u64, 16>::new();
+        let mut c = LinkedList::new_in(&heap);
+        c.push(1);
+        c.push(2);
+        assert_eq!(a, c);
+        assert_eq!(a.partial_cmp(&c), Some(Ordering::Equal));
+    }
 }
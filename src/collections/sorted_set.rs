@@ -0,0 +1,248 @@
+//! An ordered set, stored as a sorted vector
+
+use core::borrow::Borrow;
+use core::fmt;
+use core::ops::Deref;
+
+use crate::base::Storage;
+use crate::collections::Vec;
+
+/// A set of unique elements kept in sorted order in a single [`Vec`].
+///
+/// Lookups are a binary search, while insertion and removal shift the elements after the
+/// affected position. This makes it a good fit for small to medium sets, or sets which are read
+/// much more often than they're changed.
+pub struct SortedSet<T, S>
+where
+    S: Storage,
+{
+    inner: Vec<T, S>,
+}
+
+impl<T, S> SortedSet<T, S>
+where
+    S: Storage + Default,
+{
+    /// Create a new, empty [`SortedSet`], creating a default instance of the desired storage.
+    pub fn new() -> SortedSet<T, S> {
+        SortedSet { inner: Vec::new() }
+    }
+}
+
+impl<T, S> SortedSet<T, S>
+where
+    S: Storage,
+{
+    /// Create a new, empty [`SortedSet`], using the provided storage instance.
+    pub fn new_in(storage: S) -> SortedSet<T, S> {
+        SortedSet {
+            inner: Vec::new_in(storage),
+        }
+    }
+
+    /// Get the number of elements in the set
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Check if the set contains no elements
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Get the elements of the set, in sorted order
+    pub fn as_slice(&self) -> &[T] {
+        &self.inner
+    }
+
+    /// Convert this into the underlying [`Vec`], which is sorted and contains no duplicates
+    pub fn into_inner(self) -> Vec<T, S> {
+        self.inner
+    }
+}
+
+impl<T, S> SortedSet<T, S>
+where
+    T: Ord,
+    S: Storage,
+{
+    /// Create a set from the elements of a vector, sorting it and removing any duplicates. See
+    /// [`Vec::sort_dedup_shrink`].
+    pub fn from_vec(mut vec: Vec<T, S>) -> SortedSet<T, S> {
+        vec.sort_dedup_shrink();
+        SortedSet { inner: vec }
+    }
+
+    /// Add a value to the set. Returns `true` if it was inserted, or `false` if an equal value
+    /// was already present, in which case the set is left unchanged.
+    ///
+    /// # Panics
+    ///
+    /// If the set needs to grow and the backing storage fails to do so
+    pub fn insert(&mut self, value: T) -> bool {
+        if self.inner.binary_search(&value).is_ok() {
+            return false;
+        }
+        self.inner.insert_sorted(value);
+        true
+    }
+
+    /// Check whether the set contains a value
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.position(value).is_ok()
+    }
+
+    /// Get a reference to the element in the set equal to `value`, if there is one
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let pos = self.position(value).ok()?;
+        Some(&self.inner[pos])
+    }
+
+    /// Remove a value from the set. Returns `true` if it was present.
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.take(value).is_some()
+    }
+
+    /// Remove the element in the set equal to `value` and return it, if there is one
+    pub fn take<Q>(&mut self, value: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let pos = self.position(value).ok()?;
+        Some(self.inner.remove(pos))
+    }
+
+    fn position<Q>(&self, value: &Q) -> Result<usize, usize>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.inner.binary_search_by(|item| item.borrow().cmp(value))
+    }
+}
+
+impl<T, S> Default for SortedSet<T, S>
+where
+    S: Storage + Default,
+{
+    fn default() -> SortedSet<T, S> {
+        SortedSet::new()
+    }
+}
+
+impl<T, S> Deref for SortedSet<T, S>
+where
+    S: Storage,
+{
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.inner
+    }
+}
+
+impl<T, S> Extend<T> for SortedSet<T, S>
+where
+    T: Ord,
+    S: Storage,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|value| {
+            self.insert(value);
+        });
+    }
+}
+
+impl<T, S> From<Vec<T, S>> for SortedSet<T, S>
+where
+    T: Ord,
+    S: Storage,
+{
+    fn from(vec: Vec<T, S>) -> Self {
+        SortedSet::from_vec(vec)
+    }
+}
+
+impl<T, S> fmt::Debug for SortedSet<T, S>
+where
+    T: fmt::Debug,
+    S: Storage,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::inline::SingleInline;
+    use crate::statics::{MultiStatic, StorageCell};
+
+    type SortedSet<T> = super::SortedSet<T, SingleInline<[usize; 16]>>;
+
+    #[test]
+    fn test_insert() {
+        let mut set = SortedSet::<u32>::new();
+        assert!(set.insert(3));
+        assert!(set.insert(1));
+        assert!(set.insert(2));
+        assert!(!set.insert(3));
+
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.as_slice(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_contains_remove() {
+        let mut set = SortedSet::<u32>::new();
+        set.extend([5, 1, 4, 1, 3]);
+        assert!(set.contains(&4));
+        assert!(!set.contains(&2));
+        assert_eq!(set.get(&5), Some(&5));
+
+        assert!(set.remove(&4));
+        assert!(!set.remove(&4));
+        assert_eq!(set.take(&1), Some(1));
+        assert_eq!(set.take(&1), None);
+        assert_eq!(&*set, &[3, 5]);
+    }
+
+    #[test]
+    fn test_borrow() {
+        let mut set = super::SortedSet::<&str, SingleInline<[usize; 8]>>::new();
+        set.insert("b");
+        set.insert("a");
+        assert!(set.contains("a"));
+        assert_eq!(format!("{:?}", set), r#"{"a", "b"}"#);
+    }
+
+    #[test]
+    fn test_from_vec() {
+        let v = crate::collections::Vec::<u8, SingleInline<[usize; 4]>>::from([3, 1, 3, 2, 1]);
+        let set = super::SortedSet::from(v);
+        assert_eq!(set.as_slice(), &[1, 2, 3]);
+        assert_eq!(set.into_inner().as_ref(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_static() {
+        static FOO: StorageCell<[u32; 8]> = StorageCell::new([0; 8]);
+
+        let mut set = super::SortedSet::new_in(FOO.claim::<MultiStatic<_, 8>>());
+        set.extend([4u32, 2, 8, 6]);
+        assert_eq!(set.as_slice(), &[2, 4, 6, 8]);
+    }
+}
@@ -1,17 +1,27 @@
+//! A storage-based implementation of [`std::vec`]
+
 use core::borrow::{Borrow, BorrowMut};
+use core::iter::{self, FusedIterator};
 use core::mem::MaybeUninit;
-use core::ops::{Deref, DerefMut, Index, IndexMut};
+use core::ops::{self, Deref, DerefMut, Index, IndexMut, RangeBounds};
+use core::ptr::NonNull;
 use core::{fmt, mem, ptr, slice};
 
-use crate::base::Storage;
-use crate::error::Result;
+use crate::base::{self, Storage};
+#[cfg(feature = "box")]
+use crate::boxed::Box;
+use crate::error::{Result, StorageError};
+#[cfg(feature = "box")]
+use crate::handles::Handle;
+use crate::utils;
 
 /// Storage based implementation of [`Vec`](`std::vec::Vec`)
 pub struct Vec<T, S>
 where
     S: Storage,
 {
-    handle: S::Handle<[MaybeUninit<T>]>,
+    // `None` until a buffer is allocated, so empty vectors don't need to touch the storage
+    handle: Option<S::Handle<[MaybeUninit<T>]>>,
     len: usize,
     storage: S,
 }
@@ -22,44 +32,35 @@ where
 {
     /// Create a new, empty [`Vec`], creating a default instance of the desired storage.
     ///
-    /// # Panics
-    ///
-    /// If the backing allocation fails for any reason
+    /// This doesn't allocate, see [`Vec::new_in`].
     pub fn new() -> Vec<T, S> {
-        let mut storage = S::default();
-
-        Vec {
-            handle: storage.allocate_single(0).unwrap(),
-            len: 0,
-            storage,
-        }
+        Vec::new_in(S::default())
     }
 
     /// Attempt to create a new, empty [`Vec`], creating a default instance of the desired storage.
+    ///
+    /// This doesn't allocate, so never fails. It only exists for symmetry with the other
+    /// fallible constructors.
     pub fn try_new() -> Result<Vec<T, S>> {
-        let mut storage = S::default();
-
-        Ok(Vec {
-            handle: storage.allocate_single(0)?,
-            len: 0,
-            storage,
-        })
+        Ok(Vec::new())
     }
 
     /// Create a new [`Vec`], with a pre-allocated capacity equal to `size`.
     /// Uses a new default instance of the desired storage.
     ///
+    /// A `size` of zero never touches the storage, see [`Vec::with_capacity_in`].
+    ///
     /// # Panics
     ///
     /// If the backing allocation fails for any reason
     pub fn with_capacity(size: usize) -> Vec<T, S> {
-        let mut storage = S::default();
+        Vec::with_capacity_in(size, S::default())
+    }
 
-        Vec {
-            handle: storage.allocate_single(size).unwrap(),
-            len: 0,
-            storage,
-        }
+    /// Attempt to create a new [`Vec`], with a pre-allocated capacity equal to `size`.
+    /// Uses a new default instance of the desired storage.
+    pub fn try_with_capacity(size: usize) -> Result<Vec<T, S>> {
+        Vec::try_with_capacity_in(size, S::default()).map_err(|(err, _)| err)
     }
 }
 
@@ -69,38 +70,75 @@ where
 {
     /// Create a new, empty [`Vec`], using the provided storage instance.
     ///
-    /// # Panics
-    ///
-    /// If the backing allocation fails for any reason
-    pub fn new_in(mut storage: S) -> Vec<T, S> {
+    /// This never touches the storage, the buffer is only allocated once the vector first grows.
+    /// This means any number of empty vectors can be created on a bounded storage without using
+    /// up its space.
+    pub const fn new_in(storage: S) -> Vec<T, S> {
         Vec {
-            handle: storage.allocate_single(0).unwrap(),
+            handle: None,
             len: 0,
             storage,
         }
     }
 
     /// Attempt to create a new, empty [`Vec`], using the provided storage instance.
-    pub fn try_new_in(mut storage: S) -> Result<Vec<T, S>> {
-        Ok(Vec {
-            handle: storage.allocate_single(0)?,
-            len: 0,
-            storage,
-        })
+    ///
+    /// This doesn't allocate, so never fails. It only exists for symmetry with the other
+    /// fallible constructors.
+    pub fn try_new_in(storage: S) -> Result<Vec<T, S>> {
+        Ok(Vec::new_in(storage))
     }
 
     /// Create a new [`Vec`], with a pre-allocated capacity equal to `size`.
     /// Uses the provided instance of the desired storage.
     ///
+    /// As with [`Vec::new_in`], a `size` of zero never touches the storage.
+    ///
     /// # Panics
     ///
     /// If the backing allocation fails for any reason
-    pub fn with_capacity_in(size: usize, mut storage: S) -> Vec<T, S> {
-        Vec {
-            handle: storage.allocate_single(size).unwrap(),
+    pub fn with_capacity_in(size: usize, storage: S) -> Vec<T, S> {
+        Vec::try_with_capacity_in(size, storage).unwrap_or_else(|(err, _)| panic!("{}", err))
+    }
+
+    /// Attempt to create a new [`Vec`], with a pre-allocated capacity equal to `size`.
+    /// Uses the provided instance of the desired storage.
+    ///
+    /// On failure, the storage is returned alongside the error. As with
+    /// [`Vec::with_capacity_in`], a `size` of zero never touches the storage, and so can't fail.
+    pub fn try_with_capacity_in(
+        size: usize,
+        mut storage: S,
+    ) -> core::result::Result<Vec<T, S>, (StorageError, S)> {
+        let handle = if size == 0 {
+            None
+        } else {
+            match storage.allocate_single(size) {
+                Ok(handle) => Some(handle),
+                Err(err) => return Err((err, storage)),
+            }
+        };
+
+        Ok(Vec {
+            handle,
             len: 0,
             storage,
-        }
+        })
+    }
+
+    /// Attempt to create a new [`Vec`] containing the items of an iterator, using the provided
+    /// instance of the desired storage.
+    ///
+    /// If the storage runs out of space before the iterator is exhausted, the error is returned,
+    /// and the items collected so far and the rest of the iterator are dropped. Use
+    /// [`Vec::try_extend`] to keep them instead.
+    pub fn try_from_iter_in<I>(iter: I, storage: S) -> Result<Vec<T, S>>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut vec = Vec::new_in(storage);
+        vec.try_extend(iter).map_err(|(err, _, _)| err)?;
+        Ok(vec)
     }
 
     /// Check if the vector contains no element
@@ -115,46 +153,122 @@ where
 
     /// Check the vector's current capacity, the maximum length it can grow to without reallocating
     pub fn capacity(&self) -> usize {
-        // SAFETY: Handle is guaranteed valid by internal invariant
-        let ptr = unsafe { self.storage.get(self.handle) };
-        // SAFETY: Valid handles are guaranteed to return a valid pointer
-        unsafe { ptr.as_ref().len() }
+        self.buf_ptr().len()
     }
 
-    /// Add a new element onto the end of the vector
-    pub fn push(&mut self, val: T) {
-        let old_capacity = self.capacity();
+    /// Get the unused part of the buffer, from the end of the vector up to its capacity, as
+    /// possibly uninitialized elements.
+    ///
+    /// Elements written here aren't part of the vector until it's extended over them with
+    /// [`Vec::set_len`]. This allows filling the vector in place, such as by reading into the
+    /// buffer, without initializing it first.
+    pub fn spare_capacity_mut(&mut self) -> &mut [MaybeUninit<T>] {
+        let len = self.len;
+        let mut ptr = self.buf_ptr();
+        // SAFETY: Valid handles are guaranteed to return valid pointers, and we uniquely borrow
+        //         self
+        unsafe { &mut ptr.as_mut()[len..] }
+    }
 
-        if self.len + 1 > old_capacity {
-            let new_capacity = if old_capacity == 0 {
-                2
-            } else {
-                old_capacity * 2
-            };
+    /// Set the length of the vector without dropping or initializing any elements.
+    ///
+    /// Shrinking the length this way leaks the elements past the new length, while growing it
+    /// takes ownership of the elements written through [`Vec::spare_capacity_mut`].
+    ///
+    /// # Safety
+    ///
+    /// `new_len` must be no greater than the [capacity](Vec::capacity), and all elements up to
+    /// `new_len` must be initialized
+    pub unsafe fn set_len(&mut self, new_len: usize) {
+        debug_assert!(new_len <= self.capacity());
+        self.len = new_len;
+    }
 
-            // SAFETY: Handle is guaranteed valid by internal invariant
-            //         New capacity cannot be less than old due to how it's calculated
-            unsafe {
-                self.handle = self
-                    .storage
-                    .try_grow(self.handle, new_capacity)
-                    .expect("Couldn't grow Vec buffer");
-            }
+    /// Make sure there's space for at least `additional` more elements, growing the buffer if
+    /// it's too small. Like pushing, this may grow to more than the required capacity, so that
+    /// later growth needs fewer reallocations. See [`Vec::reserve_exact`] to avoid this.
+    ///
+    /// # Panics
+    ///
+    /// If the vector needs to grow and the backing storage fails to do so
+    pub fn reserve(&mut self, additional: usize) {
+        self.grow_for(additional);
+    }
+
+    /// Make sure there's space for at least `additional` more elements, growing the buffer if
+    /// it's too small. Returns an error if the backing storage fails to grow. See
+    /// [`Vec::reserve`].
+    pub fn try_reserve(&mut self, additional: usize) -> Result<()> {
+        self.try_grow_for(additional)
+    }
+
+    /// Make sure there's space for at least `additional` more elements, growing the buffer to
+    /// exactly the required capacity if it's too small
+    ///
+    /// # Panics
+    ///
+    /// If the vector needs to grow and the backing storage fails to do so
+    pub fn reserve_exact(&mut self, additional: usize) {
+        self.try_reserve_exact(additional)
+            .expect("Couldn't grow Vec buffer");
+    }
+
+    /// Make sure there's space for at least `additional` more elements, growing the buffer to
+    /// exactly the required capacity if it's too small. Returns an error if the backing storage
+    /// fails to grow.
+    pub fn try_reserve_exact(&mut self, additional: usize) -> Result<()> {
+        let required = self
+            .len
+            .checked_add(additional)
+            .ok_or(StorageError::exceeds_max())?;
+
+        if required > self.capacity() {
+            self.try_grow_to(required)?;
         }
+        Ok(())
+    }
 
-        // SAFETY: Handle is guaranteed valid by internal invariant
-        let mut ptr = unsafe { self.storage.get(self.handle) };
+    /// Add a new element onto the end of the vector
+    ///
+    /// # Panics
+    ///
+    /// If the vector needs to grow and the backing storage fails to do so
+    pub fn push(&mut self, val: T) {
+        self.grow_for(1);
+
+        let mut ptr = self.buf_ptr();
         // SAFETY: Valid handles are guaranteed to return valid pointers
         unsafe { ptr.as_mut()[self.len] = MaybeUninit::new(val) };
         self.len += 1;
     }
 
+    /// Push elements from an iterator onto the end of the vector until it's exhausted, or the
+    /// vector fails to grow.
+    ///
+    /// On failure, the error is returned alongside the element which didn't fit, as it has already
+    /// been taken from the iterator, and the unconsumed remainder of the iterator.
+    pub fn try_extend<I>(
+        &mut self,
+        iter: I,
+    ) -> core::result::Result<(), (StorageError, T, I::IntoIter)>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut iter = iter.into_iter();
+        while let Some(val) = iter.next() {
+            if let Err(err) = self.try_grow_for(1) {
+                return Err((err, val, iter));
+            }
+            self.push(val);
+        }
+        Ok(())
+    }
+
     /// Remove the element at the end of the vector and return it
     pub fn pop(&mut self) -> T {
         self.len -= 1;
 
-        // SAFETY: Handle is guaranteed valid by internal invariant
-        let mut ptr = unsafe { self.storage.get(self.handle) };
+        let mut ptr = self.buf_ptr();
         // SAFETY: Valid handles are guaranteed to return valid pointers
         let item = unsafe { &mut ptr.as_mut()[self.len] };
         let out = mem::replace(item, MaybeUninit::uninit());
@@ -162,12 +276,49 @@ where
         unsafe { out.assume_init() }
     }
 
-    /// Remove the element at a specific position in the vector and return it
+    /// Insert an element at position `pos` in the vector, shifting all elements after it up one
+    ///
+    /// # Panics
+    ///
+    /// If `pos` is greater than the vector's length, or if the vector needs to grow and the
+    /// backing storage fails to do so
+    pub fn insert(&mut self, pos: usize, val: T) {
+        let len = self.len;
+        assert!(
+            pos <= len,
+            "insertion index (is {pos}) should be <= len (is {len})"
+        );
+        self.grow_for(1);
+
+        let mut ptr = self.buf_ptr();
+        // SAFETY: Valid handles are guaranteed to return valid pointers
+        let slice = unsafe { ptr.as_mut() };
+
+        // Move all items from `pos` up one, leaving a gap to write the new element into
+        // SAFETY: There's space for one more element, so `pos + 1..=len` is within the capacity
+        unsafe {
+            let base = slice.as_mut_ptr();
+            ptr::copy(base.add(pos), base.add(pos + 1), len - pos);
+        }
+        slice[pos] = MaybeUninit::new(val);
+        self.len += 1;
+    }
+
+    /// Remove the element at a specific position in the vector and return it, shifting all
+    /// elements after it down one
+    ///
+    /// # Panics
+    ///
+    /// If `pos` is out of bounds
     pub fn remove(&mut self, pos: usize) -> T {
+        let len = self.len;
+        assert!(
+            pos < len,
+            "removal index (is {pos}) should be < len (is {len})"
+        );
         self.len -= 1;
 
-        // SAFETY: Handle is valid by internal invariant
-        let mut ptr = unsafe { self.storage.get::<[MaybeUninit<T>]>(self.handle) };
+        let mut ptr = self.buf_ptr();
 
         // SAFETY: Valid handles are guaranteed to return valid pointers
         let slice = unsafe { ptr.as_mut() };
@@ -175,268 +326,2489 @@ where
         let out = mem::replace(item, MaybeUninit::uninit());
 
         // Move all items after it back one
-        // SAFETY: New range is shorter than old, so this can't overrun
+        // SAFETY: `pos + 1..=len` is within the old length, so this can't overrun
         unsafe {
-            ptr::copy(
-                slice[pos + 1].as_ptr(),
-                slice[pos].as_mut_ptr(),
-                self.len - pos + 1,
-            );
+            let base = slice.as_mut_ptr();
+            ptr::copy(base.add(pos + 1), base.add(pos), self.len - pos);
         }
 
         // SAFETY: Popped element must be initialized, as length counts initialized items
         unsafe { out.assume_init() }
     }
-}
 
-impl<T, S> fmt::Debug for Vec<T, S>
-where
-    T: fmt::Debug,
-    S: Storage,
-{
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self.as_ref())
-    }
-}
+    /// Remove the element at a specific position in the vector and return it, replacing it with
+    /// the last element. This doesn't preserve ordering, but is O(1).
+    ///
+    /// # Panics
+    ///
+    /// If `pos` is out of bounds
+    pub fn swap_remove(&mut self, pos: usize) -> T {
+        let len = self.len;
+        assert!(
+            pos < len,
+            "swap_remove index (is {pos}) should be < len (is {len})"
+        );
+        self.len -= 1;
 
-impl<T, S> Default for Vec<T, S>
-where
-    S: Storage + Default,
-{
-    fn default() -> Vec<T, S> {
-        Vec::new()
-    }
-}
+        let mut ptr = self.buf_ptr();
+        // SAFETY: Valid handles are guaranteed to return valid pointers
+        let slice = unsafe { ptr.as_mut() };
+        // Swap the removed element to the end, then take it out, leaving its slot uninitialized
+        slice.swap(pos, self.len);
+        let out = mem::replace(&mut slice[self.len], MaybeUninit::uninit());
 
-impl<T, S> AsRef<[T]> for Vec<T, S>
-where
-    S: Storage,
-{
-    fn as_ref(&self) -> &[T] {
-        self
+        // SAFETY: Removed element must be initialized, as length counts initialized items
+        unsafe { out.assume_init() }
     }
-}
 
-impl<T, S> AsMut<[T]> for Vec<T, S>
-where
-    S: Storage,
-{
-    fn as_mut(&mut self) -> &mut [T] {
-        self
+    /// Shorten the vector to `len` elements, dropping the rest. Does nothing if the vector is
+    /// already no longer than `len`. The capacity is unchanged.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        let old_len = self.len;
+        // Lower the length first, so a panicking destructor leaks the rest instead of them
+        // being dropped again
+        self.len = len;
+        // SAFETY: Elements `len..old_len` are initialized and no longer part of the vector.
+        //         Dropping a slice continues dropping the remaining elements if one panics
+        unsafe {
+            let tail = ptr::slice_from_raw_parts_mut(self.as_mut_ptr().add(len), old_len - len);
+            ptr::drop_in_place(tail);
+        }
     }
-}
 
-impl<T, S> Borrow<[T]> for Vec<T, S>
-where
-    S: Storage,
-{
-    fn borrow(&self) -> &[T] {
-        self
+    /// Remove all elements from the vector, dropping them. The capacity is unchanged.
+    pub fn clear(&mut self) {
+        self.truncate(0);
     }
-}
 
-impl<T, S> BorrowMut<[T]> for Vec<T, S>
-where
-    S: Storage,
-{
-    fn borrow_mut(&mut self) -> &mut [T] {
-        self
-    }
-}
+    /// Attempt to move the elements of this vector into a new vector using a different backing
+    /// storage. The new vector's capacity is equal to the current length. In case of failure, the
+    /// original vector is returned unchanged.
+    pub fn map_storage<Ns>(
+        self,
+        mut new_storage: Ns,
+    ) -> core::result::Result<Vec<T, Ns>, (Vec<T, S>, Ns)>
+    where
+        Ns: Storage,
+    {
+        let new_handle = match new_storage.allocate_single::<[MaybeUninit<T>]>(self.len) {
+            Ok(handle) => handle,
+            Err(_) => return Err((self, new_storage)),
+        };
 
-impl<T, S> Deref for Vec<T, S>
-where
-    S: Storage,
-{
-    type Target = [T];
+        let (handle, len) = (self.handle, self.len);
+        if let Some(handle) = handle {
+            // Only the initialized prefix of the buffer is moved
+            let src = S::from_raw_parts(S::cast(handle), len);
+            // SAFETY: Our handle is valid by internal invariant, so the prefix is too. The new
+            //         handle is valid because allocate just succeeded, and fits `len` elements
+            unsafe { base::copy_between(&self.storage, src, &new_storage, new_handle) };
+        }
 
-    fn deref(&self) -> &Self::Target {
-        // SAFETY: Handle is guaranteed valid by internal invariant
-        let ptr = unsafe { self.storage.get(self.handle) };
-        // SAFETY: Valid handles are guaranteed to return valid pointers
-        //         Length counts initialized items, safe to interpret as `T`
-        unsafe { slice::from_raw_parts(ptr.cast().as_ptr(), self.len) }
-    }
-}
+        let mut this = mem::ManuallyDrop::new(self);
+        if let Some(handle) = handle {
+            // SAFETY: Our handle is guaranteed valid by internal invariant. The elements were
+            //         moved out, so only the backing is deallocated
+            unsafe { this.storage.deallocate_single(handle) };
+        }
+        // SAFETY: `this` is never used or dropped again
+        unsafe { ptr::drop_in_place(&mut this.storage) };
 
-impl<T, S> DerefMut for Vec<T, S>
-where
-    S: Storage,
-{
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        // SAFETY: Handle is guaranteed valid by internal invariant
-        let ptr = unsafe { self.storage.get(self.handle) };
-        // SAFETY: Valid handles are guaranteed to return valid pointers
-        //         Length counts initialized items, safe to interpret as `T`
-        unsafe { slice::from_raw_parts_mut(ptr.cast().as_ptr(), self.len) }
+        Ok(Vec {
+            handle: Some(new_handle),
+            len,
+            storage: new_storage,
+        })
     }
-}
 
-impl<T, S> Drop for Vec<T, S>
-where
-    S: Storage,
-{
-    fn drop(&mut self) {
-        for i in self.as_mut() {
-            // SAFETY: This is `drop`, so no one else will observe these values
-            unsafe { ptr::drop_in_place(i) }
-        }
-        // SAFETY: Handle is guaranteed valid by internal invariant
-        unsafe { self.storage.deallocate_single(self.handle) }
+    /// Convert this vector into a boxed slice, shrinking the backing to exactly its length.
+    ///
+    /// # Panics
+    ///
+    /// If the storage fails to shrink the allocation to the vector's length. See
+    /// [`Vec::try_into_boxed_slice`].
+    #[cfg(feature = "box")]
+    pub fn into_boxed_slice(self) -> Box<[T], S> {
+        self.try_into_boxed_slice()
+            .unwrap_or_else(|(err, _)| panic!("{}", err))
     }
-}
 
-impl<T, S> Index<usize> for Vec<T, S>
-where
-    S: Storage,
-{
-    type Output = T;
+    /// Attempt to convert this vector into a boxed slice, shrinking the backing to exactly its
+    /// length. On failure, the error is returned alongside the unchanged vector.
+    ///
+    /// If the storage can't shrink the allocation, a new one isn't attempted instead, as in a
+    /// single-item storage it would overlap the current one.
+    #[cfg(feature = "box")]
+    pub fn try_into_boxed_slice(
+        mut self,
+    ) -> core::result::Result<Box<[T], S>, (StorageError, Self)> {
+        let res = match self.handle {
+            None => self.storage.allocate_single::<[MaybeUninit<T>]>(0),
+            Some(handle) if self.capacity() == self.len => Ok(handle),
+            // SAFETY: Handle is guaranteed valid by internal invariant, length is never greater
+            //         than capacity
+            Some(handle) => unsafe { self.storage.try_shrink(handle, self.len) },
+        };
+        let handle = match res {
+            Ok(handle) => handle,
+            Err(err) => return Err((err, self)),
+        };
 
-    fn index(&self, index: usize) -> &Self::Output {
-        &self.as_ref()[index]
+        let this = mem::ManuallyDrop::new(self);
+        // SAFETY: `this` is never used or dropped again, so the storage is moved out of it
+        let storage = unsafe { ptr::read(&this.storage) };
+        let handle = S::cast_unsized::<_, [T]>(handle);
+        // SAFETY: The handle is valid for the storage, and has exactly `len` initialized elements
+        Ok(unsafe { Box::from_parts(storage, handle) })
     }
-}
 
-impl<T, S> IndexMut<usize> for Vec<T, S>
-where
-    S: Storage,
-{
-    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        &mut self.as_mut()[index]
+    /// Convert a boxed slice into a vector, with a capacity equal to its length. This reuses the
+    /// box's allocation, so no elements are moved or copied.
+    #[cfg(feature = "box")]
+    pub fn from_boxed_slice(boxed: Box<[T], S>) -> Vec<T, S> {
+        let (storage, handle) = boxed.into_parts();
+        let len = handle.metadata();
+        let handle = S::cast_unsized::<[T], [MaybeUninit<T>]>(handle);
+        // SAFETY: The handle came from a box in the same storage, so is valid and all of its
+        //         elements are initialized. The box was consumed, so nothing else uses it.
+        unsafe { Vec::from_raw_parts(handle, len, storage) }
     }
-}
 
-impl<T, S> Clone for Vec<T, S>
-where
-    T: Clone,
-    S: Storage + Clone,
-{
-    fn clone(&self) -> Self {
-        let mut new_storage = self.storage.clone();
-        let new_handle = new_storage
-            .allocate_single::<[MaybeUninit<T>]>(self.len())
-            .expect("Couldn't allocate new array");
+    /// Decompose this vector into its handle, length, and storage, without dropping any elements.
+    /// The capacity is the length of the handle's slice. Use [`Vec::from_raw_parts`] to rebuild
+    /// the vector.
+    ///
+    /// # Panics
+    ///
+    /// If no buffer has been allocated yet, and the storage fails to allocate an empty one
+    pub fn into_raw_parts(mut self) -> (S::Handle<[MaybeUninit<T>]>, usize, S) {
+        let handle = match self.handle {
+            Some(handle) => handle,
+            None => self
+                .storage
+                .allocate_single(0)
+                .expect("Couldn't allocate Vec buffer"),
+        };
 
-        // SAFETY: New handle is guaranteed valid as allocate succeeded
-        let mut ptr = unsafe { new_storage.get(new_handle) };
-        // SAFETY: Valid handles are guaranteed to return valid pointers
-        let new_iter = unsafe { ptr.as_mut().iter_mut() };
-        for (old, new) in self.as_ref().iter().zip(new_iter) {
-            new.write(old.clone());
-        }
+        let this = mem::ManuallyDrop::new(self);
+        // SAFETY: `this` is never used or dropped again, so the storage is moved out of it
+        let storage = unsafe { ptr::read(&this.storage) };
+        (handle, this.len, storage)
+    }
 
+    /// Rebuild a vector from a handle, length, and storage, such as those returned by
+    /// [`Vec::into_raw_parts`]. The vector takes ownership of the handle and its elements.
+    ///
+    /// # Safety
+    ///
+    /// The handle must be valid for the provided storage, and its first `len` elements must be
+    /// initialized. Nothing else may use the handle after this call.
+    pub unsafe fn from_raw_parts(
+        handle: S::Handle<[MaybeUninit<T>]>,
+        len: usize,
+        storage: S,
+    ) -> Vec<T, S> {
         Vec {
-            handle: new_handle,
-            len: self.len(),
-            storage: new_storage,
+            handle: Some(handle),
+            len,
+            storage,
         }
     }
-}
 
-impl<T, S> From<&[T]> for Vec<T, S>
-where
-    T: Clone,
-    S: Storage + Default,
-{
-    fn from(val: &[T]) -> Self {
-        let mut v = Vec::with_capacity(val.len());
-        v.extend(val.iter().cloned());
-        v
+    /// Return the index of the first element matching the predicate, if there is one
+    pub fn position<F>(&self, f: F) -> Option<usize>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.iter().position(f)
     }
-}
 
-impl<T, S, const N: usize> From<[T; N]> for Vec<T, S>
-where
-    S: Storage + Default,
-{
-    fn from(val: [T; N]) -> Self {
-        let mut v = Vec::with_capacity(N);
-        v.extend(val);
-        v
+    /// Split the vector into its first element and the rest, or `None` if it's empty. See
+    /// [`slice::split_first`].
+    pub fn split_first(&self) -> Option<(&T, &[T])> {
+        <[T]>::split_first(self)
     }
-}
 
-impl<T, S> From<(&[T], S)> for Vec<T, S>
-where
-    T: Clone,
-    S: Storage,
-{
-    fn from(val: (&[T], S)) -> Self {
-        let mut v = Vec::with_capacity_in(val.0.len(), val.1);
-        v.extend(val.0.iter().cloned());
+    /// Split the vector into a mutable first element and the rest, or `None` if it's empty. See
+    /// [`slice::split_first_mut`].
+    pub fn split_first_mut(&mut self) -> Option<(&mut T, &mut [T])> {
+        <[T]>::split_first_mut(self)
+    }
+
+    /// Split the vector into its last element and the rest, or `None` if it's empty. See
+    /// [`slice::split_last`].
+    pub fn split_last(&self) -> Option<(&T, &[T])> {
+        <[T]>::split_last(self)
+    }
+
+    /// Split the vector into a mutable last element and the rest, or `None` if it's empty. See
+    /// [`slice::split_last_mut`].
+    pub fn split_last_mut(&mut self) -> Option<(&mut T, &mut [T])> {
+        <[T]>::split_last_mut(self)
+    }
+
+    /// Split the vector into two slices at an index, the first containing the elements before
+    /// `mid`. See [`slice::split_at`].
+    ///
+    /// # Panics
+    ///
+    /// If `mid` is greater than the length
+    pub fn split_at(&self, mid: usize) -> (&[T], &[T]) {
+        <[T]>::split_at(self, mid)
+    }
+
+    /// Split the vector into two mutable slices at an index, the first containing the elements
+    /// before `mid`. See [`slice::split_at_mut`].
+    ///
+    /// # Panics
+    ///
+    /// If `mid` is greater than the length
+    pub fn split_at_mut(&mut self, mid: usize) -> (&mut [T], &mut [T]) {
+        <[T]>::split_at_mut(self, mid)
+    }
+
+    /// Check whether the vector is sorted according to `compare`, which should return `true` if
+    /// its two arguments are in order. See [`slice::is_sorted_by`].
+    pub fn is_sorted_by<F>(&self, compare: F) -> bool
+    where
+        F: FnMut(&T, &T) -> bool,
+    {
+        <[T]>::is_sorted_by(self, compare)
+    }
+
+    /// Shrink the capacity of the vector as close to its length as the storage allows.
+    ///
+    /// Storages which don't support shrinking, or fail to shrink, leave the capacity unchanged.
+    pub fn shrink_to_fit(&mut self) {
+        self.shrink_to(0);
+    }
+
+    /// Shrink the capacity of the vector as close to the larger of its length and `min_capacity`
+    /// as the storage allows. If the capacity is already no greater than that, this does nothing.
+    ///
+    /// Storages which don't support shrinking, or fail to shrink, leave the capacity unchanged.
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        let Some(handle) = self.handle else {
+            return;
+        };
+        let new_capacity = usize::max(self.len, min_capacity);
+        if self.capacity() <= new_capacity {
+            return;
+        }
+
+        // SAFETY: Handle is guaranteed valid by internal invariant
+        //         New capacity is less than the current capacity, checked above
+        if let Ok(handle) = unsafe { self.storage.try_shrink(handle, new_capacity) } {
+            self.handle = Some(handle);
+        }
+    }
+
+    /// Keep only the elements for which `f` returns `true`, removing the rest in place. `f` is
+    /// called exactly once per element in order, and the kept elements keep their relative order.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.retain_indexed(|_, item| f(item));
+    }
+
+    /// Keep only the elements for which `f` returns `true`, removing the rest in place. `f` is
+    /// passed each element's index from before any were removed, along with the element, and is
+    /// called exactly once per element in order.
+    pub fn retain_indexed<F>(&mut self, mut f: F)
+    where
+        F: FnMut(usize, &T) -> bool,
+    {
+        let len = self.len;
+        let base = self.as_mut_ptr();
+        // If `f` or a destructor panics, the guard compacts whatever hasn't been visited yet so
+        // no element is dropped twice or left uninitialized inside the length
+        self.len = 0;
+        let mut guard = CompactGuard {
+            vec: self,
+            read: 0,
+            write: 0,
+            len,
+        };
+
+        while guard.read < guard.len {
+            // SAFETY: `read` is in-bounds and initialized
+            let cur = unsafe { base.add(guard.read) };
+
+            // SAFETY: Pointer is valid, see above
+            if f(guard.read, unsafe { &*cur }) {
+                // SAFETY: `write` is at most `read`, both are in-bounds
+                unsafe { ptr::copy(cur, base.add(guard.write), 1) };
+                guard.read += 1;
+                guard.write += 1;
+            } else {
+                guard.read += 1;
+                // SAFETY: The element is initialized, and is now outside the tracked range
+                unsafe { ptr::drop_in_place(cur) };
+            }
+        }
+    }
+
+    /// Remove all but the first of consecutive elements in the vector which `same_bucket`
+    /// considers equal.
+    ///
+    /// `same_bucket` is passed the element being considered for removal first, and the most
+    /// recently retained element second.
+    pub fn dedup_by<F>(&mut self, mut same_bucket: F)
+    where
+        F: FnMut(&mut T, &mut T) -> bool,
+    {
+        let len = self.len;
+        if len <= 1 {
+            return;
+        }
+
+        let base = self.as_mut_ptr();
+        // If `same_bucket` or a destructor panics, the guard compacts whatever hasn't been
+        // visited yet so no element is dropped twice or left uninitialized inside the length
+        self.len = 0;
+        let mut guard = CompactGuard {
+            vec: self,
+            read: 1,
+            write: 1,
+            len,
+        };
+
+        while guard.read < guard.len {
+            // SAFETY: `read` and `write - 1` are both in-bounds and initialized, and are never
+            //         the same element
+            let (cur, prev) = unsafe { (base.add(guard.read), base.add(guard.write - 1)) };
+
+            // SAFETY: Pointers are valid and don't alias, see above
+            if same_bucket(unsafe { &mut *cur }, unsafe { &mut *prev }) {
+                guard.read += 1;
+                // SAFETY: The element is initialized, and is now outside the tracked range
+                unsafe { ptr::drop_in_place(cur) };
+            } else {
+                // SAFETY: `write` is at most `read`, both are in-bounds
+                unsafe { ptr::copy(cur, base.add(guard.write), 1) };
+                guard.read += 1;
+                guard.write += 1;
+            }
+        }
+    }
+
+    /// Remove all but the first of consecutive elements in the vector that resolve to the
+    /// same key.
+    pub fn dedup_by_key<K, F>(&mut self, mut key: F)
+    where
+        K: PartialEq,
+        F: FnMut(&mut T) -> K,
+    {
+        self.dedup_by(|a, b| key(a) == key(b))
+    }
+
+    /// Create an iterator which removes and yields every element for which `f` returns `true`.
+    /// Elements for which it returns `false` are kept, in their original order.
+    ///
+    /// If the iterator is dropped before being exhausted, the remaining elements are kept without
+    /// being visited.
+    pub fn extract_if<F>(&mut self, f: F) -> ExtractIf<'_, T, S, F>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        let len = self.len;
+        // If the iterator is leaked, the elements are leaked instead of being dropped twice
+        self.len = 0;
+        ExtractIf {
+            guard: CompactGuard {
+                vec: self,
+                read: 0,
+                write: 0,
+                len,
+            },
+            pred: f,
+        }
+    }
+
+    /// Split the vector into two new vectors, each allocated in a clone of this vector's
+    /// storage. The first contains all elements for which `f` returned `true`, the second all
+    /// elements for which it returned `false`.
+    ///
+    /// # Panics
+    ///
+    /// If either of the new vectors needs to grow and the backing storage fails to do so
+    pub fn partition<F>(mut self, mut f: F) -> (Vec<T, S>, Vec<T, S>)
+    where
+        S: Clone,
+        F: FnMut(&T) -> bool,
+    {
+        let mut left = Vec::new_in(self.storage.clone());
+        let mut right = Vec::new_in(self.storage.clone());
+
+        let len = self.len;
+        let base = self.as_mut_ptr();
+        // If `f` or a push panics, the guard moves the unvisited elements back to the front of
+        // this vector, so they're dropped with it instead of being lost or dropped twice
+        self.len = 0;
+        let mut guard = CompactGuard {
+            vec: &mut self,
+            read: 0,
+            write: 0,
+            len,
+        };
+
+        while guard.read < guard.len {
+            // SAFETY: `read` is in-bounds and initialized
+            let cur = unsafe { base.add(guard.read) };
+            // SAFETY: Pointer is valid, see above
+            let is_left = f(unsafe { &*cur });
+
+            guard.read += 1;
+            // SAFETY: The element is initialized, and is now outside the tracked range so it
+            //         won't be read again
+            let item = unsafe { ptr::read(cur) };
+            if is_left {
+                left.push(item);
+            } else {
+                right.push(item);
+            }
+        }
+
+        (left, right)
+    }
+
+    /// Split the vector into runs of consecutive elements for which `same_run` returns `true`,
+    /// moving each run into a new vector allocated in a clone of this vector's storage. The runs
+    /// are collected into a vector allocated in `storage`.
+    ///
+    /// `same_run` is passed the previous element of the current run, then the next element.
+    ///
+    /// # Panics
+    ///
+    /// If any of the new vectors needs to grow and the backing storage fails to do so
+    pub fn group_runs_by_into<Ns, F>(mut self, storage: Ns, mut same_run: F) -> Vec<Vec<T, S>, Ns>
+    where
+        S: Clone,
+        Ns: Storage,
+        F: FnMut(&T, &T) -> bool,
+    {
+        let mut runs = Vec::<Vec<T, S>, Ns>::new_in(storage);
+
+        let len = self.len;
+        let base = self.as_mut_ptr();
+        // If `same_run` or a push panics, the guard moves the unvisited elements back to the
+        // front of this vector, so they're dropped with it instead of being lost or dropped twice
+        self.len = 0;
+        let mut guard = CompactGuard {
+            vec: &mut self,
+            read: 0,
+            write: 0,
+            len,
+        };
+
+        while guard.read < guard.len {
+            // SAFETY: `read` is in-bounds and initialized
+            let cur = unsafe { base.add(guard.read) };
+            let new_run = match runs.last().and_then(|run| run.last()) {
+                // SAFETY: Pointer is valid, see above
+                Some(prev) => !same_run(prev, unsafe { &*cur }),
+                None => true,
+            };
+
+            guard.read += 1;
+            // SAFETY: The element is initialized, and is now outside the tracked range so it
+            //         won't be read again
+            let item = unsafe { ptr::read(cur) };
+            if new_run {
+                let mut run = Vec::new_in(guard.vec.storage.clone());
+                run.push(item);
+                runs.push(run);
+            } else {
+                // Runs are never empty, so there's always a last one here
+                let last = runs.len() - 1;
+                runs[last].push(item);
+            }
+        }
+
+        runs
+    }
+
+    /// Make sure there's space for at least `additional` more elements, growing the buffer
+    /// to double its capacity or the required capacity, whichever is larger.
+    ///
+    /// The doubled capacity is capped at the most the storage could ever hold, and if growing to
+    /// it fails, growing to exactly the required capacity is attempted instead. This lets bounded
+    /// storages fill up completely, rather than failing at the last doubling step.
+    pub(crate) fn try_grow_for(&mut self, additional: usize) -> Result<()> {
+        let old_capacity = self.capacity();
+        let required = self
+            .len
+            .checked_add(additional)
+            .ok_or(StorageError::exceeds_max())?;
+
+        if required > old_capacity {
+            let doubled = if old_capacity == 0 {
+                2
+            } else {
+                old_capacity.saturating_mul(2)
+            };
+            // The most items the storage could ever hold, so growth never asks for more than that
+            let max_capacity = if mem::size_of::<T>() == 0 {
+                usize::MAX
+            } else {
+                self.storage.max_range_hint::<T>()
+            };
+            let new_capacity = usize::min(doubled, max_capacity);
+            if new_capacity > required && self.try_grow_to(new_capacity).is_ok() {
+                return Ok(());
+            }
+            self.try_grow_to(required)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn grow_for(&mut self, additional: usize) {
+        self.try_grow_for(additional)
+            .expect("Couldn't grow Vec buffer");
+    }
+
+    /// Grow the buffer to exactly `new_capacity`, which must be greater than the current capacity
+    fn try_grow_to(&mut self, new_capacity: usize) -> Result<()> {
+        debug_assert!(new_capacity > self.capacity());
+        let handle = match self.handle {
+            // SAFETY: Handle is guaranteed valid by internal invariant
+            //         New capacity is greater than the old one, as required by the caller
+            Some(handle) => unsafe { self.storage.try_grow(handle, new_capacity) }?,
+            None => self.storage.allocate_single(new_capacity)?,
+        };
+        self.handle = Some(handle);
+        Ok(())
+    }
+
+    /// Pointer to the backing buffer, which is dangling and empty if it hasn't been allocated
+    fn buf_ptr(&self) -> NonNull<[MaybeUninit<T>]> {
+        match self.handle {
+            // SAFETY: Handle is guaranteed valid by internal invariant
+            Some(handle) => unsafe { self.storage.get(handle) },
+            None => NonNull::slice_from_raw_parts(NonNull::dangling(), 0),
+        }
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut T {
+        self.buf_ptr().as_ptr().cast()
+    }
+}
+
+impl<T, S> Vec<T, S>
+where
+    T: PartialEq,
+    S: Storage,
+{
+    /// Remove consecutive repeated elements from the vector. If the vector is sorted, this
+    /// removes all duplicates.
+    pub fn dedup(&mut self) {
+        self.dedup_by(|a, b| a == b)
+    }
+
+    /// Remove the first element equal to `x` and return it, if there is one
+    pub fn remove_first(&mut self, x: &T) -> Option<T> {
+        let pos = self.position(|item| item == x)?;
+        Some(self.remove(pos))
+    }
+
+    /// Split the vector into runs of consecutive equal elements. See
+    /// [`Vec::group_runs_by_into`].
+    ///
+    /// # Panics
+    ///
+    /// If any of the new vectors needs to grow and the backing storage fails to do so
+    pub fn group_runs_into<Ns>(self, storage: Ns) -> Vec<Vec<T, S>, Ns>
+    where
+        S: Clone,
+        Ns: Storage,
+    {
+        self.group_runs_by_into(storage, |a, b| a == b)
+    }
+}
+
+impl<T, S> Vec<T, S>
+where
+    T: PartialOrd,
+    S: Storage,
+{
+    /// Check whether the vector's elements are in ascending order. See [`slice::is_sorted`].
+    pub fn is_sorted(&self) -> bool {
+        <[T]>::is_sorted(self)
+    }
+}
+
+impl<T, S> Vec<T, S>
+where
+    T: Ord,
+    S: Storage,
+{
+    /// Insert a value into a sorted vector, keeping it sorted, and return the index it was
+    /// placed at. The position is found by binary search, so the vector must already be sorted
+    /// for the result to be meaningful.
+    ///
+    /// If there are elements equal to `value`, it's inserted after all of them, so equal elements
+    /// stay in insertion order.
+    ///
+    /// # Panics
+    ///
+    /// If the vector needs to grow and the backing storage fails to do so
+    pub fn insert_sorted(&mut self, value: T) -> usize {
+        let pos = self.partition_point(|item| *item <= value);
+        self.insert(pos, value);
+        pos
+    }
+
+    /// Normalize this vector into a sorted set - sort it, remove all duplicates, then shrink the
+    /// backing to the remaining length.
+    ///
+    /// The sort is unstable, as equal elements are removed anyway. Storages which don't support
+    /// shrinking keep their old capacity.
+    pub fn sort_dedup_shrink(&mut self) {
+        self.sort_unstable();
+        self.dedup();
+        self.shrink_to_fit();
+    }
+}
+
+impl<T, S> Vec<T, S>
+where
+    T: Clone,
+    S: Storage,
+{
+    /// Create a new [`Vec`] containing `n` clones of `value`, creating a default instance of the
+    /// desired storage. This is the equivalent of `vec![value; n]`.
+    ///
+    /// # Panics
+    ///
+    /// If the backing allocation fails for any reason
+    pub fn from_elem(value: T, n: usize) -> Vec<T, S>
+    where
+        S: Default,
+    {
+        Vec::from_elem_in(value, n, S::default())
+    }
+
+    /// Create a new [`Vec`] containing `n` clones of `value`, using the provided storage instance.
+    /// The vector's capacity is exactly `n`.
+    ///
+    /// If cloning panics, the clones made so far are dropped along with the vector.
+    ///
+    /// # Panics
+    ///
+    /// If the backing allocation fails for any reason
+    pub fn from_elem_in(value: T, n: usize, storage: S) -> Vec<T, S> {
+        let mut out = Vec::with_capacity_in(n, storage);
+        // Each element is pushed as it's created, so the length always covers every clone
+        out.extend(iter::repeat_n(value, n));
+        out
+    }
+
+    /// Clone this vector into a clone of its storage, returning an error instead of panicking if
+    /// the new buffer can't be allocated. For shared storages such as `&VirtHeap`, the clone is
+    /// allocated from the same backing as the original.
+    ///
+    /// If cloning an element panics, the clones made so far are dropped along with the new vector.
+    pub fn try_clone(&self) -> Result<Vec<T, S>>
+    where
+        S: Clone,
+    {
+        let mut out =
+            Vec::try_with_capacity_in(self.len(), self.storage.clone()).map_err(|(err, _)| err)?;
+        // The capacity covers every element, so this never grows
+        out.extend(self.iter().cloned());
+        Ok(out)
+    }
+
+    /// Clone all elements of a slice onto the end of the vector
+    ///
+    /// # Panics
+    ///
+    /// If the vector needs to grow and the backing storage fails to do so
+    pub fn extend_from_slice(&mut self, other: &[T]) {
+        self.extend(other.iter().cloned());
+    }
+
+    /// Clone this vector into a new one using the provided storage, which may be of a different
+    /// type. The new vector's capacity is equal to the current length.
+    ///
+    /// This can be used, for example, to move data from one allocator to another by passing a
+    /// differing `Alloc`.
+    ///
+    /// # Panics
+    ///
+    /// If the new storage fails to allocate space for the elements
+    pub fn clone_in<Ns>(&self, storage: Ns) -> Vec<T, Ns>
+    where
+        Ns: Storage,
+    {
+        let mut out = Vec::with_capacity_in(self.len, storage);
+        out.extend_from_slice(self);
+        out
+    }
+
+    /// Clone the elements in the range `src` onto the end of the vector
+    ///
+    /// # Panics
+    ///
+    /// If the range is out of bounds, or the vector needs to grow and the backing storage fails
+    /// to do so
+    pub fn extend_from_within<R>(&mut self, src: R)
+    where
+        R: RangeBounds<usize>,
+    {
+        let range = utils::resolve_range(src, self.len);
+        self.grow_for(range.len());
+
+        // The pointer is retrieved after growing, as growing may move the buffer
+        let base = self.as_mut_ptr();
+        for i in range {
+            // SAFETY: `i` is less than the old length, so it's initialized and never overlaps
+            //         the uninitialized tail being written
+            let item = unsafe { (*base.add(i)).clone() };
+            // SAFETY: We grew to fit the whole range, so `len` is in-bounds
+            unsafe { base.add(self.len).write(item) };
+            self.len += 1;
+        }
+    }
+}
+
+impl<V, S> Vec<V, S>
+where
+    S: Storage,
+{
+    /// Flatten a vector of slices into a single [`Vec`], using a default instance of the desired
+    /// storage.
+    ///
+    /// # Panics
+    ///
+    /// If the backing allocation fails for any reason
+    pub fn concat<T, Ns>(&self) -> Vec<T, Ns>
+    where
+        V: Borrow<[T]>,
+        T: Clone,
+        Ns: Storage + Default,
+    {
+        self.concat_in(Ns::default())
+    }
+
+    /// Flatten a vector of slices into a single [`Vec`], using the provided storage instance.
+    ///
+    /// # Panics
+    ///
+    /// If the backing allocation fails for any reason
+    pub fn concat_in<T, Ns>(&self, storage: Ns) -> Vec<T, Ns>
+    where
+        V: Borrow<[T]>,
+        T: Clone,
+        Ns: Storage,
+    {
+        let size = self.iter().map(|v| v.borrow().len()).sum();
+        let mut out = Vec::with_capacity_in(size, storage);
+        for v in self.iter() {
+            out.extend(v.borrow().iter().cloned());
+        }
+        out
+    }
+
+    /// Flatten a vector of slices into a single [`Vec`], placing a separator between each one.
+    /// Uses a default instance of the desired storage.
+    ///
+    /// # Panics
+    ///
+    /// If the backing allocation fails for any reason
+    pub fn join<T, Ns>(&self, sep: &T) -> Vec<T, Ns>
+    where
+        V: Borrow<[T]>,
+        T: Clone,
+        Ns: Storage + Default,
+    {
+        self.join_in(sep, Ns::default())
+    }
+
+    /// Flatten a vector of slices into a single [`Vec`], placing a separator between each one.
+    /// Uses the provided storage instance.
+    ///
+    /// # Panics
+    ///
+    /// If the backing allocation fails for any reason
+    pub fn join_in<T, Ns>(&self, sep: &T, storage: Ns) -> Vec<T, Ns>
+    where
+        V: Borrow<[T]>,
+        T: Clone,
+        Ns: Storage,
+    {
+        let size =
+            self.iter().map(|v| v.borrow().len()).sum::<usize>() + self.len.saturating_sub(1);
+        let mut out = Vec::with_capacity_in(size, storage);
+        for (idx, v) in self.iter().enumerate() {
+            if idx != 0 {
+                out.push(sep.clone());
+            }
+            out.extend(v.borrow().iter().cloned());
+        }
+        out
+    }
+}
+
+/// Tracks an in-progress compaction of a [`Vec`]. Elements in `read..len` have not been visited
+/// yet, elements before `write` have been kept. On drop, the unvisited tail is moved down to
+/// `write` and the vector's length is restored.
+struct CompactGuard<'a, T, S: Storage> {
+    vec: &'a mut Vec<T, S>,
+    read: usize,
+    write: usize,
+    len: usize,
+}
+
+impl<T, S: Storage> Drop for CompactGuard<'_, T, S> {
+    fn drop(&mut self) {
+        let tail = self.len - self.read;
+        let base = self.vec.as_mut_ptr();
+        // SAFETY: Both ranges are in-bounds of the allocation, and `write <= read`
+        unsafe { ptr::copy(base.add(self.read), base.add(self.write), tail) };
+        self.vec.len = self.write + tail;
+    }
+}
+
+/// Iterator which removes and yields the elements of a [`Vec`] matching a predicate. See
+/// [`Vec::extract_if`].
+pub struct ExtractIf<'a, T, S, F>
+where
+    S: Storage,
+{
+    guard: CompactGuard<'a, T, S>,
+    pred: F,
+}
+
+impl<T, S, F> Iterator for ExtractIf<'_, T, S, F>
+where
+    S: Storage,
+    F: FnMut(&mut T) -> bool,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let guard = &mut self.guard;
+        let base = guard.vec.as_mut_ptr();
+
+        while guard.read < guard.len {
+            // SAFETY: `read` is in-bounds and initialized
+            let cur = unsafe { base.add(guard.read) };
+            // SAFETY: Pointer is valid, see above. If `pred` panics, the element is still in the
+            //         unvisited tail, so the guard keeps it
+            let extract = (self.pred)(unsafe { &mut *cur });
+            guard.read += 1;
+
+            if extract {
+                // SAFETY: The element is initialized, and is now outside the tracked range so it
+                //         won't be read again
+                return Some(unsafe { ptr::read(cur) });
+            }
+
+            if guard.read - 1 != guard.write {
+                // SAFETY: `write < read`, so both are in-bounds and don't overlap. The slot at
+                //         `write` was either extracted or already moved from
+                unsafe { ptr::copy_nonoverlapping(cur, base.add(guard.write), 1) };
+            }
+            guard.write += 1;
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.guard.len - self.guard.read))
+    }
+}
+
+impl<T, S, F> FusedIterator for ExtractIf<'_, T, S, F>
+where
+    S: Storage,
+    F: FnMut(&mut T) -> bool,
+{
+}
+
+/// Iterator which moves the elements out of a [`Vec`], from front to back. See
+/// [`Vec::into_iter`](IntoIterator::into_iter).
+pub struct IntoIter<T, S>
+where
+    S: Storage,
+{
+    // Length is always zero, so dropping it only deallocates the buffer. Elements `start..end`
+    // of the buffer are the ones not yet yielded.
+    vec: Vec<T, S>,
+    start: usize,
+    end: usize,
+}
+
+impl<T, S> IntoIter<T, S>
+where
+    S: Storage,
+{
+    /// Get the remaining elements of this iterator as a slice
+    pub fn as_slice(&self) -> &[T] {
+        let base: *const T = self.vec.buf_ptr().as_ptr().cast();
+        // SAFETY: Elements `start..end` are initialized, and not yet moved out
+        unsafe { slice::from_raw_parts(base.add(self.start), self.end - self.start) }
+    }
+
+    /// Get the remaining elements of this iterator as a mutable slice
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        let base = self.vec.as_mut_ptr();
+        // SAFETY: Elements `start..end` are initialized, and not yet moved out
+        unsafe { slice::from_raw_parts_mut(base.add(self.start), self.end - self.start) }
+    }
+}
+
+impl<T, S> Iterator for IntoIter<T, S>
+where
+    S: Storage,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.start == self.end {
+            return None;
+        }
+        // SAFETY: `start` is in-bounds and initialized, and is moved past so won't be read again
+        let out = unsafe { ptr::read(self.vec.as_mut_ptr().add(self.start)) };
+        self.start += 1;
+        Some(out)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.start;
+        (len, Some(len))
+    }
+}
+
+impl<T, S> DoubleEndedIterator for IntoIter<T, S>
+where
+    S: Storage,
+{
+    fn next_back(&mut self) -> Option<T> {
+        if self.start == self.end {
+            return None;
+        }
+        self.end -= 1;
+        // SAFETY: `end` is in-bounds and initialized, and is now outside the remaining range
+        Some(unsafe { ptr::read(self.vec.as_mut_ptr().add(self.end)) })
+    }
+}
+
+impl<T, S> ExactSizeIterator for IntoIter<T, S> where S: Storage {}
+
+impl<T, S> FusedIterator for IntoIter<T, S> where S: Storage {}
+
+impl<T, S> Drop for IntoIter<T, S>
+where
+    S: Storage,
+{
+    fn drop(&mut self) {
+        let remaining: *mut [T] = self.as_mut_slice();
+        // SAFETY: The remaining elements are initialized and won't be used again. If one of their
+        //         destructors panics, the rest are still dropped, then the buffer is deallocated
+        //         when `vec` is dropped
+        unsafe { ptr::drop_in_place(remaining) }
+    }
+}
+
+impl<T, S> fmt::Debug for IntoIter<T, S>
+where
+    T: fmt::Debug,
+    S: Storage,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("IntoIter").field(&self.as_slice()).finish()
+    }
+}
+
+// SAFETY: A vec uniquely owns its elements, so can be sent if they and its storage can be
+unsafe impl<T, S> Send for Vec<T, S>
+where
+    T: Send,
+    S: Storage + Send,
+{
+}
+
+// SAFETY: A shared vec only provides shared access to its elements and storage
+unsafe impl<T, S> Sync for Vec<T, S>
+where
+    T: Sync,
+    S: Storage + Sync,
+{
+}
+
+impl<T, S> fmt::Debug for Vec<T, S>
+where
+    T: fmt::Debug,
+    S: Storage,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_ref())
+    }
+}
+
+impl<T, S> Default for Vec<T, S>
+where
+    S: Storage + Default,
+{
+    fn default() -> Vec<T, S> {
+        Vec::new()
+    }
+}
+
+impl<T, S> AsRef<[T]> for Vec<T, S>
+where
+    S: Storage,
+{
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T, S> AsMut<[T]> for Vec<T, S>
+where
+    S: Storage,
+{
+    fn as_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T, S> Borrow<[T]> for Vec<T, S>
+where
+    S: Storage,
+{
+    fn borrow(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T, S> BorrowMut<[T]> for Vec<T, S>
+where
+    S: Storage,
+{
+    fn borrow_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T, S> Deref for Vec<T, S>
+where
+    S: Storage,
+{
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        let ptr = self.buf_ptr();
+        // SAFETY: Valid handles are guaranteed to return valid pointers
+        //         Length counts initialized items, safe to interpret as `T`
+        unsafe { slice::from_raw_parts(ptr.cast().as_ptr(), self.len) }
+    }
+}
+
+impl<T, S> DerefMut for Vec<T, S>
+where
+    S: Storage,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        let ptr = self.buf_ptr();
+        // SAFETY: Valid handles are guaranteed to return valid pointers
+        //         Length counts initialized items, safe to interpret as `T`
+        unsafe { slice::from_raw_parts_mut(ptr.cast().as_ptr(), self.len) }
+    }
+}
+
+impl<T, S> Drop for Vec<T, S>
+where
+    S: Storage,
+{
+    fn drop(&mut self) {
+        // Deallocates the backing even if an element's destructor panics
+        let guard = DeallocGuard(self);
+        let elems: *mut [T] = guard.0.as_mut();
+        // SAFETY: This is `drop`, so no one else will observe these values. Dropping a slice
+        //         continues dropping the remaining elements if one of them panics
+        unsafe { ptr::drop_in_place(elems) }
+    }
+}
+
+struct DeallocGuard<'a, T, S: Storage>(&'a mut Vec<T, S>);
+
+impl<T, S: Storage> Drop for DeallocGuard<'_, T, S> {
+    fn drop(&mut self) {
+        if let Some(handle) = self.0.handle {
+            // SAFETY: Handle is guaranteed valid by internal invariant, and only deallocated once
+            //         the vector is being dropped
+            unsafe { self.0.storage.deallocate_single(handle) }
+        }
+    }
+}
+
+impl<T, S> Index<usize> for Vec<T, S>
+where
+    S: Storage,
+{
+    type Output = T;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.as_ref()[index]
+    }
+}
+
+impl<T, S> IndexMut<usize> for Vec<T, S>
+where
+    S: Storage,
+{
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.as_mut()[index]
+    }
+}
+
+impl<T, S> Clone for Vec<T, S>
+where
+    T: Clone,
+    S: Storage + Clone,
+{
+    fn clone(&self) -> Self {
+        self.try_clone().expect("Couldn't allocate new array")
+    }
+}
+
+impl<T, S> From<&[T]> for Vec<T, S>
+where
+    T: Clone,
+    S: Storage + Default,
+{
+    fn from(val: &[T]) -> Self {
+        let mut v = Vec::with_capacity(val.len());
+        v.extend(val.iter().cloned());
+        v
+    }
+}
+
+impl<T, S, const N: usize> From<[T; N]> for Vec<T, S>
+where
+    S: Storage + Default,
+{
+    fn from(val: [T; N]) -> Self {
+        let mut v = Vec::with_capacity(N);
+        v.extend(val);
+        v
+    }
+}
+
+impl<T, S, const N: usize> TryFrom<Vec<T, S>> for [T; N]
+where
+    S: Storage,
+{
+    type Error = Vec<T, S>;
+
+    /// Move the elements of a vector into an array, deallocating its buffer. If the vector's
+    /// length isn't exactly `N`, it's returned unchanged.
+    fn try_from(mut vec: Vec<T, S>) -> core::result::Result<[T; N], Vec<T, S>> {
+        if vec.len != N {
+            return Err(vec);
+        }
+
+        // Forget the elements, so dropping the vector only deallocates its buffer
+        vec.len = 0;
+        // SAFETY: The buffer holds `N` initialized elements, which are moved out exactly once as
+        //         the vector no longer considers them part of it
+        Ok(unsafe { vec.as_mut_ptr().cast::<[T; N]>().read() })
+    }
+}
+
+#[cfg(feature = "box")]
+impl<T, S> From<Box<[T], S>> for Vec<T, S>
+where
+    S: Storage,
+{
+    fn from(val: Box<[T], S>) -> Self {
+        Vec::from_boxed_slice(val)
+    }
+}
+
+impl<T, S> From<(&[T], S)> for Vec<T, S>
+where
+    T: Clone,
+    S: Storage,
+{
+    fn from(val: (&[T], S)) -> Self {
+        let mut v = Vec::with_capacity_in(val.0.len(), val.1);
+        v.extend(val.0.iter().cloned());
+        v
+    }
+}
+
+impl<T, S, const N: usize> From<([T; N], S)> for Vec<T, S>
+where
+    S: Storage,
+{
+    fn from(val: ([T; N], S)) -> Self {
+        let mut v = Vec::with_capacity_in(N, val.1);
+        v.extend(val.0);
         v
     }
-}
+}
+
+impl<T, S> IntoIterator for Vec<T, S>
+where
+    S: Storage,
+{
+    type Item = T;
+    type IntoIter = IntoIter<T, S>;
+
+    fn into_iter(mut self) -> IntoIter<T, S> {
+        let end = self.len;
+        // The iterator takes ownership of the elements, the vector only keeps the buffer
+        self.len = 0;
+        IntoIter {
+            vec: self,
+            start: 0,
+            end,
+        }
+    }
+}
+
+impl<'a, T, S> IntoIterator for &'a Vec<T, S>
+where
+    S: Storage,
+{
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, S> IntoIterator for &'a mut Vec<T, S>
+where
+    S: Storage,
+{
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T, S> Extend<T> for Vec<T, S>
+where
+    S: Storage,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        // Reserve for the known part of the iterator up-front, pushes will grow for the rest
+        self.grow_for(iter.size_hint().0);
+        iter.for_each(|i| self.push(i));
+    }
+}
+
+impl<T, S> FromIterator<T> for Vec<T, S>
+where
+    S: Storage + Default,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = Vec::new();
+        vec.extend(iter);
+        vec
+    }
+}
+
+impl<T, S> ops::Add<&[T]> for Vec<T, S>
+where
+    T: Clone,
+    S: Storage,
+{
+    type Output = Vec<T, S>;
+
+    fn add(mut self, rhs: &[T]) -> Self::Output {
+        self.extend_from_slice(rhs);
+        self
+    }
+}
+
+impl<T, S> ops::AddAssign<&[T]> for Vec<T, S>
+where
+    T: Clone,
+    S: Storage,
+{
+    fn add_assign(&mut self, rhs: &[T]) {
+        self.extend_from_slice(rhs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::alloc::GlobalAlloc;
+    use crate::base::Storage;
+    use crate::error::StorageError;
+    use crate::heap::VirtHeap;
+    use crate::inline::SingleInline;
+
+    type Vec<T> = super::Vec<T, SingleInline<[usize; 16]>>;
+
+    #[test]
+    fn vec_new() {
+        let v = Vec::<u32>::new();
+        assert_eq!(v.len(), 0);
+        assert_eq!(v.as_ref(), &[]);
+    }
+
+    #[test]
+    fn vec_new_lazy() {
+        let heap = VirtHeap::<u64, 1>::new();
+        let _full = crate::boxed::Box::new_in(0u64, &heap);
+
+        // Creating an empty vector doesn't need any space
+        let mut v = super::Vec::<u32, _>::new_in(&heap);
+        assert_eq!(v.capacity(), 0);
+        assert_eq!(v.as_ref(), &[]);
+
+        // Until it's first grown
+        assert!(v.try_reserve_exact(1).is_err());
+    }
+
+    #[test]
+    fn vec_spare_capacity() {
+        use std::rc::Rc;
+
+        // Read a source into the buffer in chunks, committing each chunk with `set_len`
+        let source = [1u8, 2, 3, 4, 5, 6, 7];
+        let mut v = Vec::<u8>::new();
+        v.reserve_exact(source.len());
+        for chunk in source.chunks(3) {
+            let spare = v.spare_capacity_mut();
+            for (slot, &byte) in spare.iter_mut().zip(chunk) {
+                slot.write(byte);
+            }
+            // SAFETY: The next `chunk.len()` elements were just initialized
+            unsafe { v.set_len(v.len() + chunk.len()) };
+        }
+        assert_eq!(v.as_ref(), &source);
+        assert!(v.spare_capacity_mut().is_empty());
+
+        // Each element is written exactly once, so none are leaked or dropped twice
+        let rc = Rc::new(());
+        let mut v = Vec::<Rc<()>>::new();
+        v.reserve_exact(4);
+        assert_eq!(v.spare_capacity_mut().len(), v.capacity());
+        for slot in v.spare_capacity_mut() {
+            slot.write(Rc::clone(&rc));
+        }
+        let capacity = v.capacity();
+        // SAFETY: Every element up to the capacity was just initialized
+        unsafe { v.set_len(capacity) };
+        assert_eq!(Rc::strong_count(&rc), capacity + 1);
+        drop(v);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn vec_push() {
+        let mut v = Vec::<u32>::new();
+        v.push(1);
+        v.push(2);
+
+        assert_eq!(v.len(), 2);
+        assert_eq!(v.as_ref(), &[1, 2]);
+    }
+
+    #[test]
+    fn vec_pop() {
+        let mut v = Vec::<u32>::new();
+        v.push(1);
+        v.push(2);
+
+        assert_eq!(v.pop(), 2);
+        assert_eq!(v.pop(), 1);
+        assert_eq!(v.len(), 0);
+    }
+
+    #[test]
+    fn vec_insert_remove() {
+        let mut v = Vec::<String>::new();
+        v.insert(0, "b".to_string());
+        v.insert(0, "a".to_string());
+        v.insert(2, "d".to_string());
+        v.insert(2, "c".to_string());
+        assert_eq!(v.as_ref(), &["a", "b", "c", "d"]);
+
+        assert_eq!(v.remove(1), "b");
+        assert_eq!(v.remove(2), "d");
+        assert_eq!(v.remove(0), "a");
+        assert_eq!(v.as_ref(), &["c"]);
+    }
+
+    #[test]
+    #[should_panic = "insertion index (is 2) should be <= len (is 1)"]
+    fn vec_insert_out_of_bounds() {
+        let mut v = Vec::<u32>::from([1]);
+        v.insert(2, 3);
+    }
+
+    #[test]
+    #[should_panic = "removal index (is 1) should be < len (is 1)"]
+    fn vec_remove_out_of_bounds() {
+        let mut v = Vec::<u32>::with_capacity(4);
+        v.push(1);
+        v.remove(1);
+    }
+
+    #[test]
+    fn vec_swap_remove() {
+        use std::cell::Cell;
+
+        struct Counted<'a>(u32, &'a Cell<u32>);
+
+        impl Drop for Counted<'_> {
+            fn drop(&mut self) {
+                self.1.set(self.1.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+        let mut v = Vec::<Counted<'_>>::new();
+        v.extend((0..5).map(|i| Counted(i, &drops)));
+
+        assert_eq!(v.swap_remove(1).0, 1);
+        assert_eq!(v.swap_remove(3).0, 3);
+        assert_eq!(v.swap_remove(0).0, 0);
+        assert_eq!(drops.get(), 3);
+        assert_eq!(v.iter().map(|c| c.0).collect::<std::vec::Vec<_>>(), [2, 4]);
+
+        drop(v);
+        assert_eq!(drops.get(), 5);
+    }
+
+    #[test]
+    #[should_panic = "swap_remove index (is 0) should be < len (is 0)"]
+    fn vec_swap_remove_empty() {
+        let mut v = Vec::<u32>::new();
+        v.swap_remove(0);
+    }
+
+    #[test]
+    fn vec_truncate_clear() {
+        use std::rc::Rc;
+
+        let rc = Rc::new(());
+        let mut v = Vec::<Rc<()>>::new();
+        v.extend(std::iter::repeat_with(|| Rc::clone(&rc)).take(6));
+        let capacity = v.capacity();
+
+        v.truncate(8);
+        assert_eq!(v.len(), 6);
+        v.truncate(4);
+        assert_eq!(v.len(), 4);
+        assert_eq!(Rc::strong_count(&rc), 5);
+
+        v.clear();
+        assert!(v.is_empty());
+        assert_eq!(v.capacity(), capacity);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn vec_into_iter() {
+        let v = Vec::<u32>::from([1, 2, 3, 4, 5]);
+        let mut iter = v.into_iter();
+        assert_eq!(iter.len(), 5);
+        assert_eq!(iter.next(), Some(1));
+        assert_eq!(iter.next_back(), Some(5));
+        assert_eq!(iter.as_slice(), &[2, 3, 4]);
+        assert_eq!(iter.collect::<std::vec::Vec<_>>(), [2, 3, 4]);
+
+        let v = Vec::<()>::from([(), ()]);
+        assert_eq!(v.into_iter().count(), 2);
+    }
+
+    #[test]
+    fn vec_into_iter_drop() {
+        use std::rc::Rc;
+
+        let rc = Rc::new(());
+        let v = super::Vec::<_, GlobalAlloc>::from([
+            Rc::clone(&rc),
+            Rc::clone(&rc),
+            Rc::clone(&rc),
+            Rc::clone(&rc),
+        ]);
+        let mut iter = v.into_iter();
+        let first = iter.next().unwrap();
+        iter.next_back();
+        assert_eq!(Rc::strong_count(&rc), 4);
+
+        // Unyielded elements are dropped with the iterator, yielded ones aren't touched
+        drop(iter);
+        assert_eq!(Rc::strong_count(&rc), 2);
+        drop(first);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn vec_from_iter() {
+        let v = (1..=4).collect::<super::Vec<u32, GlobalAlloc>>();
+        assert_eq!(v.as_ref(), &[1, 2, 3, 4]);
+
+        let v = (1..=4).collect::<Vec<u32>>();
+        assert_eq!(v.as_ref(), &[1, 2, 3, 4]);
+
+        let v = super::Vec::try_from_iter_in(0..4u64, SingleInline::<[u64; 4]>::new()).unwrap();
+        assert_eq!(v.as_ref(), &[0, 1, 2, 3]);
+
+        let res = super::Vec::try_from_iter_in(0..5u64, SingleInline::<[u64; 4]>::new());
+        assert!(matches!(res, Err(StorageError::InsufficientSpace { .. })));
+    }
+
+    #[test]
+    fn vec_clone() {
+        let mut v = Vec::<u32>::new();
+        v.push(1);
+        v.push(2);
+
+        let v2 = v.clone();
+
+        assert_eq!(v2.as_ref(), &[1, 2]);
+    }
+
+    #[test]
+    fn vec_zst() {
+        let mut v = Vec::<()>::new();
+        v.push(());
+        v.pop();
+    }
+
+    #[test]
+    fn vec_remove_first() {
+        let mut v = Vec::<u32>::from([1, 2, 3, 2]);
+
+        assert_eq!(v.position(|i| *i == 2), Some(1));
+        assert_eq!(v.remove_first(&2), Some(2));
+        assert_eq!(v.as_ref(), &[1, 3, 2]);
+        assert_eq!(v.remove_first(&2), Some(2));
+        assert_eq!(v.as_ref(), &[1, 3]);
+        assert_eq!(v.remove_first(&2), None);
+        assert_eq!(v.position(|i| *i == 2), None);
+    }
+
+    #[test]
+    fn vec_retain() {
+        use std::rc::Rc;
+
+        let kept = Rc::new(());
+        let removed = Rc::new(());
+        let mut v = Vec::<(u32, Rc<()>)>::new();
+        for i in 0..6 {
+            let rc = if i % 2 == 0 { &kept } else { &removed };
+            v.push((i, Rc::clone(rc)));
+        }
+
+        v.retain(|(i, _)| i % 2 == 0);
+        assert_eq!(
+            v.iter().map(|(i, _)| *i).collect::<std::vec::Vec<_>>(),
+            [0, 2, 4]
+        );
+        assert_eq!(Rc::strong_count(&kept), 4);
+        assert_eq!(Rc::strong_count(&removed), 1);
+
+        drop(v);
+        assert_eq!(Rc::strong_count(&kept), 1);
+    }
+
+    #[test]
+    fn vec_retain_indexed() {
+        let mut v = Vec::<u32>::from([5, 6, 7, 8, 9]);
+        let mut seen = std::vec::Vec::new();
+        v.retain_indexed(|idx, &val| {
+            seen.push((idx, val));
+            idx % 2 == 0
+        });
+
+        assert_eq!(&*v, &[5, 7, 9]);
+        assert_eq!(seen, [(0, 5), (1, 6), (2, 7), (3, 8), (4, 9)]);
+
+        v.retain_indexed(|idx, &val| idx != 1 && val != 9);
+        assert_eq!(&*v, &[5]);
+    }
+
+    #[test]
+    fn vec_retain_indexed_panic() {
+        use std::rc::Rc;
+
+        let item = Rc::new(());
+        let mut v = super::Vec::<_, GlobalAlloc>::from([
+            Rc::clone(&item),
+            Rc::clone(&item),
+            Rc::clone(&item),
+            Rc::clone(&item),
+        ]);
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            v.retain_indexed(|idx, _| {
+                assert_ne!(idx, 2);
+                idx == 1
+            });
+        }));
+        assert!(res.is_err());
+        // The first element was removed, the rest are kept
+        assert_eq!(v.len(), 3);
+        assert_eq!(Rc::strong_count(&item), 4);
+
+        drop(v);
+        assert_eq!(Rc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn vec_extract_if() {
+        let mut v = Vec::from([1, 2, 3, 4, 5, 6, 7]);
+
+        let mut evens = v.extract_if(|i| *i % 2 == 0);
+        assert_eq!(evens.next(), Some(2));
+        assert_eq!(evens.next(), Some(4));
+        assert_eq!(evens.next(), Some(6));
+        assert_eq!(evens.next(), None);
+        drop(evens);
+        assert_eq!(&*v, &[1, 3, 5, 7]);
+
+        // Dropping early keeps the unvisited elements
+        let mut big = v.extract_if(|i| {
+            *i *= 10;
+            *i > 20
+        });
+        assert_eq!(big.next(), Some(30));
+        drop(big);
+        assert_eq!(&*v, &[10, 5, 7]);
+    }
+
+    #[test]
+    fn vec_extract_if_panic() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+        use std::rc::Rc;
+
+        let counter = Rc::new(());
+        let mut v = super::Vec::<_, GlobalAlloc>::new();
+        v.extend((0..6).map(|i| (i, Rc::clone(&counter))));
+
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            v.extract_if(|(i, _)| {
+                assert!(*i != 3);
+                *i == 1
+            })
+            .for_each(drop)
+        }));
+        assert!(res.is_err());
+
+        let remaining = v.iter().map(|(i, _)| *i).collect::<std::vec::Vec<_>>();
+        assert_eq!(remaining, [0, 2, 3, 4, 5]);
+        drop(v);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
 
-impl<T, S, const N: usize> From<([T; N], S)> for Vec<T, S>
-where
-    S: Storage,
-{
-    fn from(val: ([T; N], S)) -> Self {
-        let mut v = Vec::with_capacity_in(N, val.1);
-        v.extend(val.0);
-        v
+    #[test]
+    fn vec_from_elem() {
+        let v = Vec::from_elem(3u32, 4);
+        assert_eq!(&*v, &[3, 3, 3, 3]);
+        assert_eq!(v.capacity(), 4);
+
+        let v = Vec::<u32>::from_elem_in(1, 0, SingleInline::new());
+        assert!(v.is_empty());
     }
-}
 
-impl<T, S> Extend<T> for Vec<T, S>
-where
-    S: Storage,
-{
-    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
-        iter.into_iter().for_each(|i| self.push(i));
+    #[test]
+    fn vec_from_elem_panic() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+        use std::rc::Rc;
+
+        struct PanicClone(Rc<()>);
+
+        impl Clone for PanicClone {
+            fn clone(&self) -> Self {
+                assert!(Rc::strong_count(&self.0) < 3, "Too many clones");
+                PanicClone(Rc::clone(&self.0))
+            }
+        }
+
+        let counter = Rc::new(());
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            super::Vec::<_, GlobalAlloc>::from_elem(PanicClone(Rc::clone(&counter)), 4)
+        }));
+        assert!(res.is_err());
+        assert_eq!(Rc::strong_count(&counter), 1);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::inline::SingleInline;
+    #[test]
+    fn vec_try_into_array() {
+        use std::rc::Rc;
 
-    type Vec<T> = super::Vec<T, SingleInline<[usize; 16]>>;
+        let v = Vec::<u32>::from([1, 2, 3]);
+        let v = <[u32; 2]>::try_from(v).unwrap_err();
+        assert_eq!(&*v, &[1, 2, 3]);
+        let arr: [u32; 3] = v.try_into().unwrap();
+        assert_eq!(arr, [1, 2, 3]);
+
+        let item = Rc::new(());
+        let v = super::Vec::<_, GlobalAlloc>::from([Rc::clone(&item), Rc::clone(&item)]);
+        let Ok(arr) = <[Rc<()>; 2]>::try_from(v) else {
+            panic!("Expected conversion to succeed");
+        };
+        assert_eq!(Rc::strong_count(&item), 3);
+        drop(arr);
+        assert_eq!(Rc::strong_count(&item), 1);
+    }
 
     #[test]
-    fn vec_new() {
-        let v = Vec::<u32>::new();
-        assert_eq!(v.len(), 0);
-        assert_eq!(v.as_ref(), &[]);
+    fn vec_partition() {
+        let v = Vec::<u32>::from([1, 2, 3, 4, 5, 6, 7]);
+        let (even, odd) = v.partition(|i| i % 2 == 0);
+
+        assert_eq!(even.as_ref(), &[2, 4, 6]);
+        assert_eq!(odd.as_ref(), &[1, 3, 5, 7]);
     }
 
     #[test]
-    fn vec_push() {
-        let mut v = Vec::<u32>::new();
-        v.push(1);
-        v.push(2);
+    fn vec_partition_drop() {
+        use std::rc::Rc;
 
-        assert_eq!(v.len(), 2);
-        assert_eq!(v.as_ref(), &[1, 2]);
+        let item = Rc::new(());
+        let v = super::Vec::<_, GlobalAlloc>::from([
+            Rc::clone(&item),
+            Rc::clone(&item),
+            Rc::clone(&item),
+        ]);
+
+        let (left, right) = v.partition(|_| false);
+        assert_eq!(left.len(), 0);
+        assert_eq!(right.len(), 3);
+        assert_eq!(Rc::strong_count(&item), 4);
+
+        drop((left, right));
+        assert_eq!(Rc::strong_count(&item), 1);
+
+        let v = super::Vec::<_, GlobalAlloc>::from([
+            Rc::clone(&item),
+            Rc::clone(&item),
+            Rc::clone(&item),
+        ]);
+        let mut count = 0;
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            v.partition(|_| {
+                count += 1;
+                assert!(count < 2);
+                true
+            })
+        }));
+        assert!(res.is_err());
+        assert_eq!(Rc::strong_count(&item), 1);
     }
 
     #[test]
-    fn vec_pop() {
+    fn vec_group_runs_into() {
+        let heap = VirtHeap::<u64, 32>::new();
+        let mut v = super::Vec::new_in(&heap);
+        v.extend([1, 1, 2, 3, 3, 3, 1]);
+
+        let runs = v.group_runs_into(GlobalAlloc::default());
+        assert_eq!(runs.len(), 4);
+        assert!(runs
+            .iter()
+            .map(|run| &**run)
+            .eq([&[1, 1][..], &[2], &[3, 3, 3], &[1]]));
+
+        let v = super::Vec::<u32, GlobalAlloc>::from([1, 2, 4, 5, 6, 9]);
+        let runs = v.group_runs_by_into(GlobalAlloc::default(), |a, b| a + 1 == *b);
+        assert!(runs
+            .iter()
+            .map(|run| &**run)
+            .eq([&[1, 2][..], &[4, 5, 6], &[9]]));
+
+        let v = super::Vec::<u32, GlobalAlloc>::new();
+        assert!(v.group_runs_into(GlobalAlloc::default()).is_empty());
+    }
+
+    #[test]
+    fn vec_group_runs_into_panic() {
+        use std::rc::Rc;
+
+        let item = Rc::new(());
+        let v = super::Vec::<_, GlobalAlloc>::from([
+            Rc::clone(&item),
+            Rc::clone(&item),
+            Rc::clone(&item),
+        ]);
+        let mut count = 0;
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            v.group_runs_by_into(GlobalAlloc::default(), |_, _| {
+                count += 1;
+                assert!(count < 2);
+                true
+            })
+        }));
+        assert!(res.is_err());
+        assert_eq!(Rc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn vec_extend_from_within() {
+        let mut v = Vec::<u32>::from([1, 2, 3]);
+
+        v.extend_from_within(1..);
+        assert_eq!(v.as_ref(), &[1, 2, 3, 2, 3]);
+        v.extend_from_within(..=1);
+        assert_eq!(v.as_ref(), &[1, 2, 3, 2, 3, 1, 2]);
+        v.extend_from_within(2..2);
+        assert_eq!(v.len(), 7);
+    }
+
+    #[test]
+    #[should_panic]
+    fn vec_extend_from_within_oob() {
+        let mut v = Vec::<u32>::from([1, 2, 3]);
+        v.extend_from_within(2..4);
+    }
+
+    #[test]
+    fn vec_drop_panic() {
+        use crate::heap::VirtHeap;
+        use core::cell::Cell;
+        use std::panic;
+
+        struct PanicDrop<'a>(&'a Cell<usize>, bool);
+
+        impl Drop for PanicDrop<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+                if self.1 {
+                    panic!("PanicDrop dropped");
+                }
+            }
+        }
+
+        let heap = VirtHeap::<usize, 8>::new();
+        let drops = Cell::new(0);
+
+        let mut v = super::Vec::new_in(&heap);
+        v.push(PanicDrop(&drops, false));
+        v.push(PanicDrop(&drops, true));
+        v.push(PanicDrop(&drops, false));
+
+        let res = panic::catch_unwind(panic::AssertUnwindSafe(move || drop(v)));
+        assert!(res.is_err());
+        // Every element was dropped exactly once
+        assert_eq!(drops.get(), 3);
+        // The backing was freed, so the whole heap is available again
+        let _full = crate::boxed::Box::new_in([0usize; 8], &heap);
+    }
+
+    #[test]
+    fn vec_try_extend() {
+        let mut v = super::Vec::<u32, SingleInline<[u32; 4]>>::new();
+
+        v.try_extend([1, 2, 3]).unwrap();
+        assert_eq!(v.as_ref(), &[1, 2, 3]);
+
+        let (_, item, mut rest) = v.try_extend(4..10).unwrap_err();
+        assert_eq!(v.as_ref(), &[1, 2, 3, 4]);
+        assert_eq!(item, 5);
+        assert_eq!(rest.next(), Some(6));
+    }
+
+    #[test]
+    fn vec_into_boxed_slice() {
         let mut v = Vec::<u32>::new();
         v.push(1);
         v.push(2);
+        v.push(3);
+        assert_eq!(v.capacity(), 4);
 
-        assert_eq!(v.pop(), 2);
-        assert_eq!(v.pop(), 1);
-        assert_eq!(v.len(), 0);
+        let b = v.into_boxed_slice();
+        assert_eq!(&*b, &[1, 2, 3]);
+
+        let mut v = super::Vec::<u32, GlobalAlloc>::with_capacity(16);
+        v.extend([1, 2, 3]);
+        let b = v.into_boxed_slice();
+        assert_eq!(&*b, &[1, 2, 3]);
     }
 
     #[test]
-    fn vec_clone() {
+    fn vec_try_into_boxed_slice() {
+        use crate::alloc::Alloc;
+        use core::alloc::{AllocError, Allocator, Layout};
+        use core::ptr::NonNull;
+        use std::alloc::Global;
+
+        // An allocator which never shrinks in-place
+        struct NoShrink;
+
+        // SAFETY: Forwards to `Global`, except for always failing to shrink
+        unsafe impl Allocator for NoShrink {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                Global.allocate(layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                // SAFETY: Same safety requirements
+                unsafe { Global.deallocate(ptr, layout) }
+            }
+
+            unsafe fn shrink(
+                &self,
+                _: NonNull<u8>,
+                _: Layout,
+                _: Layout,
+            ) -> Result<NonNull<[u8]>, AllocError> {
+                Err(AllocError)
+            }
+        }
+
+        let mut v = super::Vec::<u32, _>::with_capacity_in(4, Alloc::new(NoShrink));
+        v.extend([1, 2, 3]);
+        let (err, v) = v.try_into_boxed_slice().unwrap_err();
+        assert!(matches!(err, StorageError::Unimplemented));
+        assert_eq!(&*v, &[1, 2, 3]);
+        assert_eq!(v.capacity(), 4);
+
+        let mut v = super::Vec::<u32, _>::with_capacity_in(3, Alloc::new(NoShrink));
+        v.extend([1, 2, 3]);
+        let b = v.try_into_boxed_slice().unwrap();
+        assert_eq!(&*b, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn vec_boxed_slice_round_trip() {
+        use crate::boxed::Box;
+        use std::rc::Rc;
+
+        let rc = Rc::new(());
+        let mut v = super::Vec::<_, GlobalAlloc>::with_capacity(3);
+        v.extend([rc.clone(), rc.clone(), rc.clone()]);
+        let ptr = v.as_ptr();
+
+        // Neither conversion moves, clones, or drops any elements
+        let b = v.into_boxed_slice();
+        assert_eq!(b.as_ptr(), ptr);
+        let v = super::Vec::from(b);
+        assert_eq!(v.as_ptr(), ptr);
+        assert_eq!((v.len(), v.capacity()), (3, 3));
+        assert_eq!(Rc::strong_count(&rc), 4);
+
+        // Leaking and reclaiming the slice doesn't either
+        let leaked: &mut [Rc<()>] = Box::leak(v.into_boxed_slice());
+        assert_eq!(leaked.as_ptr(), ptr);
+        let b = unsafe { Box::<[Rc<()>], GlobalAlloc>::from_raw(leaked) };
+        let v = super::Vec::from_boxed_slice(b);
+        assert_eq!(v.as_ptr(), ptr);
+        assert_eq!(Rc::strong_count(&rc), 4);
+
+        drop(v);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn vec_map_storage() {
+        let mut v = super::Vec::<u32, SingleInline<[u32; 4]>>::new();
+        v.extend([1, 2, 3, 4]);
+
+        let v = v
+            .map_storage::<SingleInline<[u32; 2]>>(SingleInline::new())
+            .unwrap_err()
+            .0;
+        assert_eq!(v.as_ref(), &[1, 2, 3, 4]);
+
+        let Ok(mut v) = v.map_storage(GlobalAlloc::default()) else {
+            panic!("Couldn't move Vec into GlobalAlloc");
+        };
+        v.push(5);
+        assert_eq!(v.as_ref(), &[1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn vec_try_with_capacity() {
+        let v = Vec::<u32>::try_with_capacity_in(8, SingleInline::new()).unwrap();
+        assert_eq!(v.capacity(), 8);
+
+        let Err((_, storage)) = Vec::<u32>::try_with_capacity_in(64, SingleInline::new()) else {
+            panic!("Expected allocation to fail");
+        };
+        let v = Vec::<u32>::with_capacity_in(4, storage);
+        assert_eq!(v.capacity(), 4);
+    }
+
+    #[test]
+    fn vec_reserve_exact() {
         let mut v = Vec::<u32>::new();
+        v.reserve_exact(3);
+        assert_eq!(v.capacity(), 3);
+        v.extend([1, 2, 3]);
+
+        v.reserve_exact(2);
+        assert_eq!(v.capacity(), 5);
+        v.reserve_exact(1);
+        assert_eq!(v.capacity(), 5);
+
+        assert!(v.try_reserve_exact(usize::MAX).is_err());
+        assert!(v.try_reserve_exact(64).is_err());
+        assert_eq!(&*v, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn vec_reserve() {
+        let mut v = super::Vec::<u32, GlobalAlloc>::new();
+        v.reserve(10);
+        let capacity = v.capacity();
+        assert!(capacity >= 10);
+        // Hide the length from `extend`, so it has to grow one push at a time
+        v.extend((0..10).filter(|_| true));
+        assert_eq!(v.capacity(), capacity);
+
+        v.reserve(5);
+        assert!(v.capacity() >= 15);
+        let capacity = v.capacity();
+        v.extend((0..5).filter(|_| true));
+        assert_eq!(v.capacity(), capacity);
+
+        // Bounded storages fill up completely, then fail without changing the vector
+        let mut v = Vec::<u32>::from([1, 2, 3]);
+        assert!(matches!(
+            v.try_reserve(64),
+            Err(StorageError::InsufficientSpace { .. })
+        ));
+        assert!(v.try_reserve(29).is_ok());
+        assert_eq!(v.capacity(), 32);
+        assert_eq!(&*v, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn vec_shrink_to() {
+        let mut v = Vec::<u32>::with_capacity(10);
+        v.extend([1, 2, 3]);
+
+        v.shrink_to(5);
+        assert_eq!(v.capacity(), 5);
+        v.shrink_to(8);
+        assert_eq!(v.capacity(), 5);
+        v.shrink_to(0);
+        assert_eq!(v.capacity(), 3);
+        assert_eq!(&*v, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn vec_shrink_to_fit_reuse() {
+        let heap = VirtHeap::<u64, 8>::new();
+
+        let mut v = super::Vec::<u64, _>::with_capacity_in(8, &heap);
+        v.extend(0..8);
+        for _ in 0..6 {
+            v.pop();
+        }
+        assert_eq!(v.capacity(), 8);
+        assert!(crate::boxed::Box::try_new_in([0u64; 6], &heap).is_err());
+
+        // Blocks past the new capacity are freed, and can be used by another allocation
+        v.shrink_to_fit();
+        assert_eq!(v.capacity(), 2);
+        let other = crate::boxed::Box::new_in([1u64; 6], &heap);
+        assert_eq!(&*v, &[0, 1]);
+        assert_eq!(*other, [1; 6]);
+    }
+
+    #[test]
+    fn vec_zero_capacity() {
+        let heap = VirtHeap::<u64, 4>::new();
+
+        let mut empty = (0..16)
+            .map(|_| super::Vec::<u32, _>::with_capacity_in(0, &heap))
+            .collect::<std::vec::Vec<_>>();
+        assert_eq!(empty[0].capacity(), 0);
+        assert_eq!(&*empty[0], &[]);
+
+        // None of the empty vectors hold space in the heap
+        let full = crate::boxed::Box::new_in([0u64; 4], &heap);
+        drop(full);
+
+        empty[0].push(1);
+        assert_eq!(&*empty[0], &[1]);
+    }
+
+    #[test]
+    fn vec_raw_parts() {
+        let mut v = Vec::<u32>::with_capacity(8);
         v.push(1);
         v.push(2);
 
-        let v2 = v.clone();
+        let (handle, len, storage) = v.into_raw_parts();
+        assert_eq!(len, 2);
+        let slice = unsafe { storage.get(handle).as_mut() };
+        assert_eq!(slice.len(), 8);
+        slice[2].write(3);
 
-        assert_eq!(v2.as_ref(), &[1, 2]);
+        let v = unsafe { Vec::from_raw_parts(handle, 3, storage) };
+        assert_eq!(&*v, &[1, 2, 3]);
+
+        let (handle, len, storage) = super::Vec::<u32, GlobalAlloc>::new().into_raw_parts();
+        let v = unsafe { super::Vec::<u32, _>::from_raw_parts(handle, len, storage) };
+        assert!(v.is_empty());
     }
 
     #[test]
-    fn vec_zst() {
-        let mut v = Vec::<()>::new();
-        v.push(());
-        v.pop();
+    fn vec_clone_in() {
+        let v = Vec::from([1, 2, 3]);
+
+        let v2 = v.clone_in(GlobalAlloc::global());
+        assert_eq!(&*v2, &[1, 2, 3]);
+        assert_eq!(v2.capacity(), 3);
+
+        let v3 = v2.clone_in(crate::alloc::Alloc::new(std::alloc::System));
+        assert_eq!(&*v3, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn vec_add() {
+        let mut v = Vec::from([1, 2]);
+        v += &[3, 4];
+        v.extend_from_slice(&[5]);
+        assert_eq!(&*v, &[1, 2, 3, 4, 5]);
+
+        let v = v + &[6, 7];
+        assert_eq!(&*v, &[1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn vec_extend_reserve() {
+        let mut v = super::Vec::<u32, GlobalAlloc>::new();
+
+        v.extend(0..100);
+        assert_eq!(v.capacity(), 100);
+        // Unknown lengths still grow by doubling
+        v.extend((0..10).filter(|_| true));
+        assert_eq!(v.capacity(), 200);
+        assert_eq!(v.len(), 110);
+    }
+
+    #[test]
+    fn vec_ref_iter() {
+        let mut v = Vec::<u32>::from([1, 2, 3]);
+
+        for i in &mut v {
+            *i *= 2;
+        }
+
+        let mut sum = 0;
+        for i in &v {
+            sum += *i;
+        }
+        assert_eq!(sum, 12);
+    }
+
+    #[test]
+    fn vec_dedup() {
+        let mut v = Vec::<u32>::from([1, 1, 2, 3, 3, 3, 1]);
+        v.dedup();
+
+        assert_eq!(v.as_ref(), &[1, 2, 3, 1]);
+        assert_eq!(v.capacity(), 7);
+    }
+
+    #[test]
+    fn vec_is_sorted() {
+        let mut v = Vec::<u32>::from([1, 2, 2, 5]);
+        assert!(v.is_sorted());
+        assert!(!v.is_sorted_by(|a, b| a > b));
+
+        v.push(3);
+        assert!(!v.is_sorted());
+        assert!(Vec::<u32>::new().is_sorted());
+        assert!(Vec::<f32>::from([1.0, 2.0]).is_sorted());
+        assert!(!Vec::<f32>::from([1.0, f32::NAN]).is_sorted());
+    }
+
+    #[test]
+    fn vec_try_clone_heap() {
+        let heap = VirtHeap::<u32, 8>::new();
+
+        let v1 = super::Vec::<u32, _>::from_elem_in(1, 3, &heap);
+        let mut v2 = v1.clone();
+        v2[0] = 2;
+        assert_eq!(&*v1, &[1, 1, 1]);
+        assert_eq!(&*v2, &[2, 1, 1]);
+
+        // Only two blocks are left in the shared heap
+        assert!(matches!(v1.try_clone(), Err(StorageError::NoSlots)));
+        drop(v2);
+        assert_eq!(&*v1.try_clone().unwrap(), &[1, 1, 1]);
+    }
+
+    #[test]
+    fn vec_grow_bounded() {
+        // Doubling from 8 would ask for 16, more than the storage can hold
+        let mut v = super::Vec::<u32, SingleInline<[u32; 10]>>::new();
+        v.extend(0..10);
+        assert_eq!(v.capacity(), 10);
+        assert!(v.try_reserve_exact(1).is_err());
+
+        // Storages without a known maximum fall back to growing exactly
+        let budget = crate::alloc::BudgetedAlloc::new(std::alloc::Global, 40);
+        let mut v = super::Vec::<u32, _>::new_in(&budget);
+        v.extend(0..10);
+        assert_eq!(v.capacity(), 10);
+        assert_eq!(&*v, &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn vec_insert_sorted() {
+        let mut v = Vec::<(u32, char)>::new();
+
+        assert_eq!(v.insert_sorted((2, 'a')), 0);
+        assert_eq!(v.insert_sorted((1, 'a')), 0);
+        assert_eq!(v.insert_sorted((3, 'a')), 2);
+        assert_eq!(v.insert_sorted((2, 'b')), 2);
+        assert_eq!(&*v, &[(1, 'a'), (2, 'a'), (2, 'b'), (3, 'a')]);
+
+        // Equal elements go after existing ones
+        let mut v = super::Vec::<Ordered, GlobalAlloc>::new();
+        for (key, id) in [(1, 0), (0, 1), (1, 2), (1, 3)] {
+            v.insert_sorted(Ordered(key, id));
+        }
+        assert!(v.iter().map(|o| o.1).eq([1, 0, 2, 3]));
+
+        #[derive(Debug)]
+        struct Ordered(u32, u32);
+
+        impl PartialEq for Ordered {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+
+        impl Eq for Ordered {}
+
+        impl PartialOrd for Ordered {
+            fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for Ordered {
+            fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+                self.0.cmp(&other.0)
+            }
+        }
+    }
+
+    #[test]
+    fn vec_split() {
+        let mut v = Vec::<u32>::from([1, 2, 3, 4]);
+
+        assert_eq!(v.split_first(), Some((&1, &[2, 3, 4][..])));
+        assert_eq!(v.split_last(), Some((&4, &[1, 2, 3][..])));
+        assert_eq!(v.split_at(1), (&[1][..], &[2, 3, 4][..]));
+
+        let (first, rest) = v.split_first_mut().unwrap();
+        *first = rest[0];
+        let (last, rest) = v.split_last_mut().unwrap();
+        *last = rest[0];
+        let (left, right) = v.split_at_mut(2);
+        left.swap_with_slice(right);
+        assert_eq!(&*v, &[3, 2, 2, 2]);
+
+        assert_eq!(Vec::<u32>::new().split_first(), None);
+    }
+
+    #[test]
+    fn vec_iter_fold() {
+        let v = Vec::<i32>::from([3, -1, 4, 2]);
+
+        assert_eq!(v.iter().copied().sum::<i32>(), 8);
+        assert_eq!(v.iter().product::<i32>(), -24);
+        assert_eq!(v.iter().min(), Some(&-1));
+        assert_eq!(v.iter().max(), Some(&4));
+        assert_eq!((&v).into_iter().rev().nth(1), Some(&4));
+        assert_eq!(Vec::<i32>::new().iter().sum::<i32>(), 0);
+    }
+
+    #[test]
+    fn vec_chunk_by() {
+        // Provided through the slice `Deref`, borrowing runs without allocating
+        let v = Vec::<u32>::from([1, 1, 2, 3, 3, 3, 1]);
+        let mut runs = v.chunk_by(|a, b| a == b);
+
+        assert_eq!(runs.next(), Some(&[1, 1][..]));
+        assert_eq!(runs.next(), Some(&[2][..]));
+        assert_eq!(runs.next(), Some(&[3, 3, 3][..]));
+        assert_eq!(runs.next_back(), Some(&[1][..]));
+        assert_eq!(runs.next(), None);
+    }
+
+    #[test]
+    fn vec_dedup_by_key() {
+        let mut v = Vec::<u32>::from([10, 11, 20, 21, 22, 30]);
+        v.dedup_by_key(|i| *i / 10);
+
+        assert_eq!(v.as_ref(), &[10, 20, 30]);
+    }
+
+    #[test]
+    fn vec_sort_dedup_shrink() {
+        let mut v = Vec::<u32>::from([4, 1, 3, 1, 4, 2, 3]);
+        v.sort_dedup_shrink();
+
+        assert_eq!(v.as_ref(), &[1, 2, 3, 4]);
+        assert_eq!(v.len(), 4);
+        assert_eq!(v.capacity(), 4);
+    }
+
+    #[test]
+    fn vec_concat() {
+        let mut v = super::Vec::<Vec<u32>, GlobalAlloc>::new();
+        v.push(Vec::from([1, 2]));
+        v.push(Vec::new());
+        v.push(Vec::from([3]));
+
+        let flat: Vec<u32> = v.concat();
+        assert_eq!(flat.as_ref(), &[1, 2, 3]);
+        assert_eq!(flat.capacity(), 3);
+
+        let joined: Vec<u32> = v.join(&0);
+        assert_eq!(joined.as_ref(), &[1, 2, 0, 0, 3]);
+        assert_eq!(joined.capacity(), 5);
+    }
+
+    #[test]
+    fn vec_concat_slices() {
+        let v = Vec::<&[u8]>::from([b"ab".as_slice(), b"cd", b"e"]);
+
+        let flat = v.concat_in(GlobalAlloc::default());
+        assert_eq!(flat.as_ref(), b"abcde");
+
+        let joined = v.join_in(&b',', GlobalAlloc::default());
+        assert_eq!(joined.as_ref(), b"ab,cd,e");
+    }
+
+    #[test]
+    fn vec_sort_dedup_shrink_alloc() {
+        let mut v = super::Vec::<u32, GlobalAlloc>::from([5, 5, 5, 5, 5, 5, 5, 5]);
+        v.sort_dedup_shrink();
+
+        assert_eq!(v.as_ref(), &[5]);
+        assert_eq!(v.capacity(), 1);
+
+        v.push(6);
+        assert_eq!(v.as_ref(), &[5, 6]);
+    }
+
+    #[test]
+    fn vec_drop_fuzz() {
+        use core::cell::{Cell, RefCell};
+        use std::collections::HashSet;
+
+        /// Records every element created and dropped, so leaks and double drops are caught
+        #[derive(Default)]
+        struct Ledger {
+            created: Cell<usize>,
+            dropped: RefCell<HashSet<usize>>,
+        }
+
+        struct Tracked<'a> {
+            id: usize,
+            val: usize,
+            ledger: &'a Ledger,
+        }
+
+        impl<'a> Tracked<'a> {
+            fn new(val: usize, ledger: &'a Ledger) -> Tracked<'a> {
+                let id = ledger.created.get();
+                ledger.created.set(id + 1);
+                Tracked { id, val, ledger }
+            }
+        }
+
+        impl Clone for Tracked<'_> {
+            fn clone(&self) -> Self {
+                Tracked::new(self.val, self.ledger)
+            }
+        }
+
+        impl Drop for Tracked<'_> {
+            fn drop(&mut self) {
+                assert!(
+                    self.ledger.dropped.borrow_mut().insert(self.id),
+                    "Element {} dropped twice",
+                    self.id
+                );
+            }
+        }
+
+        // Xorshift, so every run sees the same sequence of operations
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            usize::try_from(state % bound as u64).unwrap()
+        };
+
+        for _ in 0..32 {
+            let ledger = Ledger::default();
+            let mut v = super::Vec::<Tracked<'_>, GlobalAlloc>::new();
+            let mut model = std::vec::Vec::<usize>::new();
+
+            for _ in 0..128 {
+                match next(8) {
+                    0 => {
+                        let val = next(4);
+                        v.push(Tracked::new(val, &ledger));
+                        model.push(val);
+                    }
+                    1 if !v.is_empty() => assert_eq!(v.pop().val, model.pop().unwrap()),
+                    2 if !v.is_empty() => {
+                        let pos = next(v.len());
+                        assert_eq!(v.remove(pos).val, model.remove(pos));
+                    }
+                    3 => {
+                        let vals = (0..next(6)).map(|_| next(4)).collect::<std::vec::Vec<_>>();
+                        v.extend(vals.iter().map(|&val| Tracked::new(val, &ledger)));
+                        model.extend(vals);
+                    }
+                    4 => {
+                        v.dedup_by(|a, b| a.val == b.val);
+                        model.dedup();
+                    }
+                    5 => {
+                        // Only take some of the matches, leaving the rest unvisited
+                        let take = next(4);
+                        let taken = v.extract_if(|t| t.val % 2 == 0).take(take).count();
+                        let mut removed = 0;
+                        model.retain(|&val| {
+                            let extract = removed < taken && val % 2 == 0;
+                            removed += usize::from(extract);
+                            !extract
+                        });
+                    }
+                    6 if !v.is_empty() => {
+                        let start = next(v.len());
+                        let end = start + next(v.len() - start + 1);
+                        v.extend_from_within(start..end);
+                        model.extend_from_within(start..end);
+                    }
+                    7 => {
+                        drop(v.clone());
+                        v.shrink_to_fit();
+                    }
+                    _ => (),
+                }
+                assert!(v.iter().map(|t| t.val).eq(model.iter().copied()));
+            }
+
+            drop(v);
+            assert_eq!(ledger.created.get(), ledger.dropped.borrow().len());
+        }
     }
 }
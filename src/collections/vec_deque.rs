@@ -0,0 +1,390 @@
+//! A storage-based implementation of [`std::collections::VecDeque`]
+
+use core::mem::MaybeUninit;
+use core::ptr::NonNull;
+use core::{fmt, ptr, slice};
+
+use crate::base::Storage;
+use crate::error::{Result, StorageError};
+
+/// Storage based implementation of [`VecDeque`](std::collections::VecDeque), a double-ended queue
+/// in a growable ring buffer.
+pub struct VecDeque<T, S>
+where
+    S: Storage,
+{
+    // `None` until a buffer is allocated, so empty deques don't need to touch the storage
+    handle: Option<S::Handle<[MaybeUninit<T>]>>,
+    head: usize,
+    len: usize,
+    storage: S,
+}
+
+impl<T, S> VecDeque<T, S>
+where
+    S: Storage + Default,
+{
+    /// Create a new, empty [`VecDeque`], creating a default instance of the desired storage.
+    pub fn new() -> VecDeque<T, S> {
+        VecDeque::new_in(S::default())
+    }
+}
+
+impl<T, S> VecDeque<T, S>
+where
+    S: Storage,
+{
+    /// Create a new, empty [`VecDeque`], using the provided storage instance. This doesn't
+    /// allocate until the first element is pushed.
+    pub fn new_in(storage: S) -> VecDeque<T, S> {
+        VecDeque {
+            handle: None,
+            head: 0,
+            len: 0,
+            storage,
+        }
+    }
+
+    /// Check if the deque contains no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the current length of the deque
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check the deque's current capacity, the maximum length it can grow to without reallocating
+    pub fn capacity(&self) -> usize {
+        self.buf_ptr().len()
+    }
+
+    /// Add a new element onto the back of the deque
+    ///
+    /// # Panics
+    ///
+    /// If the deque needs to grow and the backing storage fails to do so
+    pub fn push_back(&mut self, val: T) {
+        self.grow_for_one();
+
+        let idx = self.physical(self.len);
+        // SAFETY: `idx` is in-bounds, and past the initialized elements
+        unsafe { self.buf_ptr().as_mut()[idx] = MaybeUninit::new(val) };
+        self.len += 1;
+    }
+
+    /// Add a new element onto the front of the deque
+    ///
+    /// # Panics
+    ///
+    /// If the deque needs to grow and the backing storage fails to do so
+    pub fn push_front(&mut self, val: T) {
+        self.grow_for_one();
+
+        self.head = self.physical(self.capacity() - 1);
+        let head = self.head;
+        // SAFETY: `head` is in-bounds, and before the initialized elements
+        unsafe { self.buf_ptr().as_mut()[head] = MaybeUninit::new(val) };
+        self.len += 1;
+    }
+
+    /// Remove the element at the back of the deque and return it, or `None` if it's empty
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        self.len -= 1;
+        let idx = self.physical(self.len);
+        // SAFETY: The element was initialized, and is now outside the length so won't be read
+        //         again
+        Some(unsafe { self.buf_ptr().as_ref()[idx].assume_init_read() })
+    }
+
+    /// Remove the element at the front of the deque and return it, or `None` if it's empty
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let head = self.head;
+        self.head = self.physical(1);
+        self.len -= 1;
+        // SAFETY: The element was initialized, and is now outside the length so won't be read
+        //         again
+        Some(unsafe { self.buf_ptr().as_ref()[head].assume_init_read() })
+    }
+
+    /// Get a reference to the element at a given index from the front, or `None` if it's out of
+    /// bounds
+    pub fn get(&self, idx: usize) -> Option<&T> {
+        if idx >= self.len {
+            return None;
+        }
+        let idx = self.physical(idx);
+        // SAFETY: Elements within the length are initialized
+        Some(unsafe { self.buf_ptr().as_ref()[idx].assume_init_ref() })
+    }
+
+    /// Get a mutable reference to the element at a given index from the front, or `None` if it's
+    /// out of bounds
+    pub fn get_mut(&mut self, idx: usize) -> Option<&mut T> {
+        if idx >= self.len {
+            return None;
+        }
+        let idx = self.physical(idx);
+        // SAFETY: Elements within the length are initialized
+        Some(unsafe { self.buf_ptr().as_mut()[idx].assume_init_mut() })
+    }
+
+    /// Get the contents of the deque as two slices, which together contain all elements in
+    /// order. The second slice is only non-empty if the elements wrap around the end of the
+    /// buffer.
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let (front, back) = self.slice_ranges();
+        let base = self.buf_ptr().as_ptr().cast::<T>();
+        // SAFETY: Both ranges are in-bounds, don't overlap, and only contain initialized elements
+        unsafe {
+            (
+                slice::from_raw_parts(base.add(front.0), front.1),
+                slice::from_raw_parts(base.add(back.0), back.1),
+            )
+        }
+    }
+
+    /// Get the contents of the deque as two mutable slices, which together contain all elements in
+    /// order. See [`VecDeque::as_slices`].
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let (front, back) = self.slice_ranges();
+        let base = self.buf_ptr().as_ptr().cast::<T>();
+        // SAFETY: Both ranges are in-bounds, don't overlap, and only contain initialized elements
+        unsafe {
+            (
+                slice::from_raw_parts_mut(base.add(front.0), front.1),
+                slice::from_raw_parts_mut(base.add(back.0), back.1),
+            )
+        }
+    }
+
+    /// The start and length of the front and back runs of initialized elements
+    fn slice_ranges(&self) -> ((usize, usize), (usize, usize)) {
+        let front_len = usize::min(self.len, self.capacity() - self.head);
+        ((self.head, front_len), (0, self.len - front_len))
+    }
+
+    /// Map an index from the front of the deque to an index in the buffer
+    fn physical(&self, idx: usize) -> usize {
+        let cap = self.capacity();
+        let idx = self.head + idx;
+        if idx >= cap {
+            idx - cap
+        } else {
+            idx
+        }
+    }
+
+    fn try_grow_for_one(&mut self) -> Result<()> {
+        let old_capacity = self.capacity();
+        if self.len < old_capacity {
+            return Ok(());
+        }
+
+        let new_capacity = usize::max(
+            2,
+            old_capacity
+                .checked_mul(2)
+                .ok_or(StorageError::exceeds_max())?,
+        );
+        let handle = match self.handle {
+            // SAFETY: Handle is guaranteed valid by internal invariant
+            //         New capacity cannot be less than old due to how it's calculated
+            Some(handle) => unsafe { self.storage.try_grow(handle, new_capacity) }?,
+            None => self.storage.allocate_single(new_capacity)?,
+        };
+        self.handle = Some(handle);
+
+        // The buffer was full, so if the elements wrapped, the wrapped part is moved to directly
+        // after the old end. It's shorter than the old capacity, so always fits.
+        let wrapped = self.head + self.len - usize::min(old_capacity, self.head + self.len);
+        if wrapped > 0 {
+            let base = self.buf_ptr().as_ptr().cast::<T>();
+            // SAFETY: Both ranges are in-bounds of the new capacity, and don't overlap
+            unsafe { ptr::copy_nonoverlapping(base, base.add(old_capacity), wrapped) };
+        }
+
+        Ok(())
+    }
+
+    fn grow_for_one(&mut self) {
+        self.try_grow_for_one()
+            .expect("Couldn't grow VecDeque buffer");
+    }
+
+    /// Pointer to the backing buffer, which is dangling and empty if it hasn't been allocated
+    fn buf_ptr(&self) -> NonNull<[MaybeUninit<T>]> {
+        match self.handle {
+            // SAFETY: Handle is guaranteed valid by internal invariant
+            Some(handle) => unsafe { self.storage.get(handle) },
+            None => NonNull::slice_from_raw_parts(NonNull::dangling(), 0),
+        }
+    }
+}
+
+// SAFETY: A deque uniquely owns its elements, so can be sent if they and its storage can be
+unsafe impl<T, S> Send for VecDeque<T, S>
+where
+    T: Send,
+    S: Storage + Send,
+{
+}
+
+// SAFETY: A shared deque only provides shared access to its elements and storage
+unsafe impl<T, S> Sync for VecDeque<T, S>
+where
+    T: Sync,
+    S: Storage + Sync,
+{
+}
+
+impl<T, S> fmt::Debug for VecDeque<T, S>
+where
+    T: fmt::Debug,
+    S: Storage,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (front, back) = self.as_slices();
+        f.debug_list().entries(front).entries(back).finish()
+    }
+}
+
+impl<T, S> Default for VecDeque<T, S>
+where
+    S: Storage + Default,
+{
+    fn default() -> VecDeque<T, S> {
+        VecDeque::new()
+    }
+}
+
+impl<T, S> Drop for VecDeque<T, S>
+where
+    S: Storage,
+{
+    fn drop(&mut self) {
+        // Deallocates the backing even if an element's destructor panics
+        let guard = DeallocGuard(self);
+        let (front, back) = guard.0.as_mut_slices();
+        let (front, back): (*mut [T], *mut [T]) = (front, back);
+        // Drops the back even if dropping the front panics
+        let _back = DropGuard(back);
+        // SAFETY: This is `drop`, so no one else will observe these values
+        unsafe { ptr::drop_in_place(front) }
+    }
+}
+
+struct DropGuard<T>(*mut [T]);
+
+impl<T> Drop for DropGuard<T> {
+    fn drop(&mut self) {
+        // SAFETY: Only created in `VecDeque::drop`, over initialized elements
+        unsafe { ptr::drop_in_place(self.0) }
+    }
+}
+
+struct DeallocGuard<'a, T, S: Storage>(&'a mut VecDeque<T, S>);
+
+impl<T, S: Storage> Drop for DeallocGuard<'_, T, S> {
+    fn drop(&mut self) {
+        if let Some(handle) = self.0.handle {
+            // SAFETY: Handle is guaranteed valid by internal invariant, and only deallocated once
+            //         the deque is being dropped
+            unsafe { self.0.storage.deallocate_single(handle) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::alloc::GlobalAlloc;
+    use crate::inline::SingleInline;
+
+    type VecDeque<T> = super::VecDeque<T, SingleInline<[usize; 16]>>;
+
+    #[test]
+    fn deque_push_pop() {
+        let mut d = VecDeque::<u32>::new();
+        assert_eq!(d.pop_front(), None);
+
+        d.push_back(2);
+        d.push_back(3);
+        d.push_front(1);
+        d.push_front(0);
+        assert_eq!(d.len(), 4);
+        assert_eq!(d.get(0), Some(&0));
+        assert_eq!(d.get(3), Some(&3));
+        assert_eq!(d.get(4), None);
+
+        assert_eq!(d.pop_front(), Some(0));
+        assert_eq!(d.pop_back(), Some(3));
+        assert_eq!(d.pop_back(), Some(2));
+        assert_eq!(d.pop_back(), Some(1));
+        assert_eq!(d.pop_back(), None);
+        assert!(d.is_empty());
+    }
+
+    #[test]
+    fn deque_as_slices() {
+        let mut d = super::VecDeque::<u32, GlobalAlloc>::new();
+        assert_eq!(d.as_slices(), (&[][..], &[][..]));
+
+        d.push_back(1);
+        d.push_back(2);
+        assert_eq!(d.as_slices(), (&[1, 2][..], &[][..]));
+
+        d.push_front(0);
+        assert_eq!(d.as_slices(), (&[0][..], &[1, 2][..]));
+
+        let (front, back) = d.as_mut_slices();
+        front[0] = 10;
+        back[1] = 20;
+        assert_eq!(d.as_slices(), (&[10][..], &[1, 20][..]));
+    }
+
+    #[test]
+    fn deque_grow_wrapped() {
+        let mut d = super::VecDeque::<u32, GlobalAlloc>::new();
+        d.push_back(2);
+        d.push_back(3);
+        d.push_front(1);
+        d.push_front(0);
+        // Full and wrapped, so growing has to move the wrapped part
+        d.push_back(4);
+        d.push_front(5);
+
+        let (front, back) = d.as_slices();
+        let all = front
+            .iter()
+            .chain(back)
+            .copied()
+            .collect::<std::vec::Vec<_>>();
+        assert_eq!(all, [5, 0, 1, 2, 3, 4]);
+        assert_eq!(format!("{:?}", d), "[5, 0, 1, 2, 3, 4]");
+    }
+
+    #[test]
+    fn deque_drop() {
+        use std::rc::Rc;
+
+        let counter = Rc::new(());
+        let mut d = super::VecDeque::<_, GlobalAlloc>::new();
+        d.push_back(Rc::clone(&counter));
+        d.push_front(Rc::clone(&counter));
+        d.push_front(Rc::clone(&counter));
+        drop(d.pop_back());
+        assert_eq!(Rc::strong_count(&counter), 3);
+
+        drop(d);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+}
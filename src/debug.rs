@@ -2,33 +2,72 @@
 //! for most forms of incorrect usage.
 //!
 //! This will not catch *all* UB, but it should catch most obviously incorrect usages.
+//!
+//! With the `backtrace` feature enabled, a backtrace is captured for every allocation, and
+//! included in the panic message when misuse of that allocation is detected.
 
 #[cfg(feature = "unsize")]
 use core::marker::Unsize;
+use core::num::NonZeroUsize;
 use core::ptr::{NonNull, Pointee};
 use spin::Mutex;
+#[cfg(feature = "backtrace")]
+use std::backtrace::Backtrace;
 
 use crate::alloc::GlobalAlloc;
 use crate::base::{ExactSizeStorage, LeaksafeStorage, MultiItemStorage, Storage};
+use crate::collections::HashMap;
+#[cfg(feature = "backtrace")]
 use crate::collections::Vec;
 use crate::handles::Handle;
 
 struct DebugState<S: Storage> {
     single_allocated: Option<DebugHandle<S, ()>>,
-    id: usize,
-    allocated_handles: Vec<DebugHandle<S, ()>, GlobalAlloc>,
-    deallocated_handles: Vec<DebugHandle<S, ()>, GlobalAlloc>,
+    id: NonZeroUsize,
+    /// IDs of live allocations, so validation doesn't need to scan every handle
+    allocated_handles: HashMap<NonZeroUsize, (), GlobalAlloc>,
+    /// IDs of freed allocations
+    deallocated_handles: HashMap<NonZeroUsize, (), GlobalAlloc>,
+    /// Where each allocation was made, indexed by its ID minus one
+    #[cfg(feature = "backtrace")]
+    backtraces: Vec<Backtrace, GlobalAlloc>,
 }
 
 impl<S: Storage> DebugState<S> {
     fn new() -> DebugState<S> {
         DebugState {
             single_allocated: None,
-            id: 0,
-            allocated_handles: Vec::new(),
-            deallocated_handles: Vec::new(),
+            id: NonZeroUsize::MIN,
+            allocated_handles: HashMap::new(),
+            deallocated_handles: HashMap::new(),
+            #[cfg(feature = "backtrace")]
+            backtraces: Vec::new(),
+        }
+    }
+
+    fn next_id(&mut self) -> NonZeroUsize {
+        let id = self.id;
+        self.id = id
+            .checked_add(1)
+            .expect("Debug storage ran out of handle IDs");
+        #[cfg(feature = "backtrace")]
+        self.backtraces.push(Backtrace::force_capture());
+        id
+    }
+
+    /// Describe where the allocation for a handle was made, for use in panic messages
+    #[cfg(feature = "backtrace")]
+    fn allocated_at(&self, handle: DebugHandle<S, ()>) -> std::string::String {
+        match self.backtraces.get(handle.id.get() - 1) {
+            Some(backtrace) => std::format!("\nAllocation was made at:\n{backtrace}"),
+            None => std::string::String::new(),
         }
     }
+
+    #[cfg(not(feature = "backtrace"))]
+    fn allocated_at(&self, _: DebugHandle<S, ()>) -> &'static str {
+        ""
+    }
 }
 
 /// A storage which provides runtime panics for many forms of storage UB
@@ -48,35 +87,67 @@ where
 
         if let Some(alloc_handle) = lock.single_allocated {
             assert_eq!(
-                alloc_handle, handle,
-                "Attempted to access single allocation with incorrect handle"
+                alloc_handle,
+                handle,
+                "Attempted to access single allocation with incorrect handle{}",
+                lock.allocated_at(handle),
             );
         }
 
         assert!(
-            !lock.deallocated_handles.contains(&handle),
-            "Attempting to access allocation with deallocated handle",
+            !lock.deallocated_handles.contains_key(&handle.id),
+            "Attempting to access allocation with deallocated handle{}",
+            lock.allocated_at(handle),
         );
         assert!(
-            lock.allocated_handles.contains(&handle),
+            lock.allocated_handles.contains_key(&handle.id),
             "Attempting to access allocation with never-allocated handle"
         );
     }
 
-    fn validate_alloc(&self, single: bool, handle: S::Handle<()>) -> usize {
+    fn validate_alloc(&self, single: bool, handle: S::Handle<()>) -> NonZeroUsize {
         let mut lock = self.0.lock();
 
-        let id = lock.id;
-        lock.id += 1;
+        let id = lock.next_id();
 
         let handle = DebugHandle { id, handle };
 
         if single {
-            assert!(lock.single_allocated.is_none(), "Called allocate_single without calling deallocate_single - this may overwrite the old value");
+            if let Some(old) = lock.single_allocated {
+                panic!(
+                    "Called allocate_single without calling deallocate_single - this may overwrite the old value{}",
+                    lock.allocated_at(old),
+                );
+            }
             lock.single_allocated = Some(handle);
         }
 
-        lock.allocated_handles.push(handle);
+        lock.allocated_handles.insert(id, ());
+
+        id
+    }
+
+    /// Track a grow or shrink. The old handle is retired and the new one gets a fresh ID, even if
+    /// the allocation didn't move, so any use of the old handle afterwards is caught.
+    fn validate_realloc(&self, old: DebugHandle<S, ()>, new: S::Handle<()>) -> NonZeroUsize {
+        let mut lock = self.0.lock();
+
+        assert!(
+            !lock.deallocated_handles.contains_key(&old.id),
+            "Attempting to reallocate with deallocated handle{}",
+            lock.allocated_at(old),
+        );
+        assert!(
+            lock.allocated_handles.remove(&old.id).is_some(),
+            "Attempting to reallocate with never-allocated handle"
+        );
+        lock.deallocated_handles.insert(old.id, ());
+
+        let id = lock.next_id();
+        lock.allocated_handles.insert(id, ());
+        if lock.single_allocated == Some(old) {
+            lock.single_allocated = Some(DebugHandle { id, handle: new });
+        }
 
         id
     }
@@ -85,8 +156,9 @@ where
         let mut lock = self.0.lock();
 
         assert!(
-            !lock.deallocated_handles.contains(&handle),
-            "Called deallocate_single on the same handle twice"
+            !lock.deallocated_handles.contains_key(&handle.id),
+            "Called deallocate_single on the same handle twice{}",
+            lock.allocated_at(handle),
         );
 
         if single {
@@ -97,12 +169,8 @@ where
             lock.single_allocated = None;
         }
 
-        lock.allocated_handles
-            .iter()
-            .position(|h| *h == handle)
-            .map(|pos| lock.allocated_handles.remove(pos));
-
-        lock.deallocated_handles.push(handle);
+        lock.allocated_handles.remove(&handle.id);
+        lock.deallocated_handles.insert(handle.id, ());
     }
 }
 
@@ -143,6 +211,14 @@ where
         handle.coerce()
     }
 
+    fn will_fit_hint<T: ?Sized + Pointee>(&self, meta: T::Metadata) -> Option<bool> {
+        self.1.will_fit_hint::<T>(meta)
+    }
+
+    fn max_range_hint<T>(&self) -> usize {
+        self.1.max_range_hint::<T>()
+    }
+
     fn allocate_single<T: ?Sized + Pointee>(
         &mut self,
         meta: T::Metadata,
@@ -152,6 +228,15 @@ where
         Ok(DebugHandle { id, handle })
     }
 
+    fn allocate_single_zeroed<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> crate::error::Result<Self::Handle<T>> {
+        let handle = self.1.allocate_single_zeroed::<T>(meta)?;
+        let id = self.validate_alloc(true, S::cast(handle));
+        Ok(DebugHandle { id, handle })
+    }
+
     unsafe fn deallocate_single<T: ?Sized>(&mut self, handle: Self::Handle<T>) {
         self.validate_dealloc(true, Self::cast(handle));
         // SAFETY: Shares our safety requirements
@@ -163,10 +248,21 @@ where
         handle: Self::Handle<[T]>,
         capacity: usize,
     ) -> crate::error::Result<Self::Handle<[T]>> {
-        handle.try_map(|h| {
-            // SAFETY: Shares our safety requirements
-            unsafe { self.1.try_grow::<T>(h, capacity) }
-        })
+        // SAFETY: Shares our safety requirements
+        let new = unsafe { self.1.try_grow::<T>(handle.handle, capacity) }?;
+        let id = self.validate_realloc(Self::cast(handle), S::cast(new));
+        Ok(DebugHandle { id, handle: new })
+    }
+
+    unsafe fn try_grow_moved<T>(
+        &mut self,
+        handle: Self::Handle<[T]>,
+        capacity: usize,
+    ) -> crate::error::Result<(Self::Handle<[T]>, bool)> {
+        // SAFETY: Shares our safety requirements
+        let (new, moved) = unsafe { self.1.try_grow_moved::<T>(handle.handle, capacity) }?;
+        let id = self.validate_realloc(Self::cast(handle), S::cast(new));
+        Ok((DebugHandle { id, handle: new }, moved))
     }
 
     unsafe fn try_shrink<T>(
@@ -174,10 +270,10 @@ where
         handle: Self::Handle<[T]>,
         capacity: usize,
     ) -> crate::error::Result<Self::Handle<[T]>> {
-        handle.try_map(|h| {
-            // SAFETY: Shares our safety requirements
-            unsafe { self.1.try_shrink::<T>(h, capacity) }
-        })
+        // SAFETY: Shares our safety requirements
+        let new = unsafe { self.1.try_shrink::<T>(handle.handle, capacity) }?;
+        let id = self.validate_realloc(Self::cast(handle), S::cast(new));
+        Ok(DebugHandle { id, handle: new })
     }
 }
 
@@ -195,11 +291,25 @@ where
         Ok(DebugHandle { id, handle })
     }
 
+    fn allocate_zeroed<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> crate::error::Result<Self::Handle<T>> {
+        let handle = self.1.allocate_zeroed::<T>(meta)?;
+        let id = self.validate_alloc(false, S::cast(handle));
+        Ok(DebugHandle { id, handle })
+    }
+
     unsafe fn deallocate<T: ?Sized + Pointee>(&mut self, handle: Self::Handle<T>) {
         self.validate_dealloc(false, Self::cast(handle));
         // SAFETY: Shares our safety requirements
         unsafe { self.1.deallocate(handle.handle) }
     }
+
+    fn is_allocated<T: ?Sized + Pointee>(&self, handle: Self::Handle<T>) -> bool {
+        self.0.lock().allocated_handles.contains_key(&handle.id)
+            && self.1.is_allocated(handle.handle)
+    }
 }
 
 impl<S> ExactSizeStorage for Debug<S>
@@ -226,11 +336,10 @@ where
 {
     unsafe fn unleak_ptr<T: ?Sized>(&self, leaked: *mut T) -> Self::Handle<T> {
         let mut lock = self.0.lock();
-        let id = lock.id;
-        lock.id += 1;
+        let id = lock.next_id();
         let handle = self.1.unleak_ptr(leaked);
         let out = DebugHandle { id, handle };
-        self.0.lock().allocated_handles.push(self.cast(out));
+        self.0.lock().allocated_handles.insert(id, ());
         out
     }
 }*/
@@ -238,10 +347,14 @@ where
 mod private {
     use super::*;
     use core::fmt;
+    use core::hash::{Hash, Hasher};
 
     /// Handle for a debug storage
+    ///
+    /// IDs start at one, so this always has a niche and `Option<DebugHandle>` is the same size,
+    /// even if the wrapped handle has none.
     pub struct DebugHandle<S: Storage, T: ?Sized> {
-        pub(super) id: usize,
+        pub(super) id: NonZeroUsize,
         pub(super) handle: S::Handle<T>,
     }
 
@@ -259,16 +372,6 @@ mod private {
                 handle: f(self.handle),
             }
         }
-
-        pub(super) fn try_map<U: ?Sized, E, F: FnOnce(S::Handle<T>) -> Result<S::Handle<U>, E>>(
-            self,
-            f: F,
-        ) -> Result<DebugHandle<S, U>, E> {
-            Ok(DebugHandle {
-                id: self.id,
-                handle: f(self.handle)?,
-            })
-        }
     }
 
     impl<S, T> fmt::Debug for DebugHandle<S, T>
@@ -293,6 +396,18 @@ mod private {
         }
     }
 
+    impl<S: Storage, T: ?Sized> Eq for DebugHandle<S, T> {}
+
+    impl<S, T> Hash for DebugHandle<S, T>
+    where
+        S: Storage,
+        T: ?Sized,
+    {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.id.hash(state)
+        }
+    }
+
     impl<S: Storage, T: ?Sized> Clone for DebugHandle<S, T> {
         fn clone(&self) -> Self {
             *self
@@ -348,6 +463,7 @@ use private::DebugHandle;
 mod tests {
     use super::*;
     use crate::inline::SingleInline;
+    use core::mem::size_of;
 
     fn storage() -> Debug<SingleInline<[usize; 16]>> {
         Debug::new(SingleInline::<[usize; 16]>::default())
@@ -367,6 +483,46 @@ mod tests {
         unsafe { s.deallocate_single(h2) };
     }
 
+    #[test]
+    fn test_many_allocations() {
+        let mut s = Debug::new(GlobalAlloc::default());
+
+        let handles = (0..4096)
+            .map(|_| s.allocate::<u32>(()).unwrap())
+            .collect::<std::vec::Vec<_>>();
+        // Free every other allocation first, so validation can't rely on allocation order
+        for &h in handles
+            .iter()
+            .step_by(2)
+            .chain(handles.iter().skip(1).step_by(2))
+        {
+            unsafe { s.get(h) };
+            unsafe { s.deallocate(h) };
+        }
+    }
+
+    #[test]
+    fn test_grow_vec() {
+        let mut v = crate::collections::Vec::<u32, _>::new_in(Debug::new(GlobalAlloc::default()));
+        v.extend(0..64);
+        v.shrink_to_fit();
+        assert_eq!(v.len(), 64);
+        assert!(v.iter().copied().eq(0..64));
+    }
+
+    #[test]
+    #[should_panic = "Attempting to access allocation with deallocated handle"]
+    fn test_get_pre_grow() {
+        let mut s = Debug::new(GlobalAlloc::default());
+
+        let h1 = s.allocate::<[u32]>(2).unwrap();
+        let h2 = unsafe { s.try_grow(h1, 8) }.unwrap();
+        unsafe { s.get(h2) };
+
+        // Even if the grow happened in-place, the old handle is no longer valid
+        unsafe { s.get(h1) };
+    }
+
     #[test]
     #[should_panic = "Called allocate_single without calling deallocate_single"]
     fn test_double_alloc() {
@@ -434,4 +590,28 @@ mod tests {
 
         unsafe { s.get(h1) };
     }
+
+    #[test]
+    #[cfg(feature = "backtrace")]
+    #[should_panic = "Allocation was made at:"]
+    fn test_backtrace() {
+        let mut s = storage();
+
+        let h1 = s.allocate_single::<()>(()).unwrap();
+
+        unsafe { s.deallocate_single(h1) };
+        unsafe { s.deallocate_single(h1) };
+    }
+
+    #[test]
+    fn test_handle_niche() {
+        type Handle<T> = <Debug<SingleInline<[usize; 16]>> as Storage>::Handle<T>;
+
+        // The wrapped handle is zero-sized, so the niche comes from the ID
+        assert_eq!(size_of::<Option<Handle<u32>>>(), size_of::<Handle<u32>>());
+        assert_eq!(
+            size_of::<Option<Handle<[u32]>>>(),
+            size_of::<Handle<[u32]>>()
+        );
+    }
 }
@@ -6,7 +6,7 @@ use core::fmt;
 pub type Result<T> = core::result::Result<T, StorageError>;
 
 /// The error type returned by storages upon allocation failure
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[non_exhaustive]
 pub enum StorageError {
     /// The storage didn't have enough space for the requested allocation
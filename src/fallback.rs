@@ -14,6 +14,9 @@ use crate::error;
 use crate::handles::Handle;
 
 /// A storage which attempts to store in one storage, then falls back to a second
+///
+/// If the first storage reports an item won't fit through [`Storage::will_fit_hint`], such as
+/// storages implementing [`ExactSizeStorage`] do, the item goes directly to the second.
 #[derive(Copy, Clone)]
 pub struct FallbackStorage<S1, S2> {
     first: S1,
@@ -84,18 +87,50 @@ where
         handle.coerce()
     }
 
+    fn will_fit_hint<T: ?Sized + Pointee>(&self, meta: T::Metadata) -> Option<bool> {
+        match (
+            self.first.will_fit_hint::<T>(meta),
+            self.second.will_fit_hint::<T>(meta),
+        ) {
+            (Some(true), _) | (_, Some(true)) => Some(true),
+            (Some(false), Some(false)) => Some(false),
+            _ => None,
+        }
+    }
+
+    fn max_range_hint<T>(&self) -> usize {
+        usize::max(
+            self.first.max_range_hint::<T>(),
+            self.second.max_range_hint::<T>(),
+        )
+    }
+
     fn allocate_single<T: ?Sized + Pointee>(
         &mut self,
         meta: T::Metadata,
     ) -> error::Result<Self::Handle<T>> {
-        self.first
+        if self.first.will_fit_hint::<T>(meta) != Some(false) {
+            if let Ok(handle) = self.first.allocate_single(meta) {
+                return Ok(FallbackHandle::First(handle));
+            }
+        }
+        self.second
             .allocate_single(meta)
-            .map(FallbackHandle::First)
-            .or_else(|_| {
-                self.second
-                    .allocate_single(meta)
-                    .map(FallbackHandle::Second)
-            })
+            .map(FallbackHandle::Second)
+    }
+
+    fn allocate_single_zeroed<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> error::Result<Self::Handle<T>> {
+        if self.first.will_fit_hint::<T>(meta) != Some(false) {
+            if let Ok(handle) = self.first.allocate_single_zeroed(meta) {
+                return Ok(FallbackHandle::First(handle));
+            }
+        }
+        self.second
+            .allocate_single_zeroed(meta)
+            .map(FallbackHandle::Second)
     }
 
     unsafe fn deallocate_single<T: ?Sized>(&mut self, handle: Self::Handle<T>) {
@@ -112,17 +147,22 @@ where
         handle: Self::Handle<[T]>,
         capacity: usize,
     ) -> error::Result<Self::Handle<[T]>> {
+        // SAFETY: Same safety requirements
+        unsafe { self.try_grow_moved(handle, capacity) }.map(|(handle, _)| handle)
+    }
+
+    unsafe fn try_grow_moved<T>(
+        &mut self,
+        handle: Self::Handle<[T]>,
+        capacity: usize,
+    ) -> error::Result<(Self::Handle<[T]>, bool)> {
         match handle {
             FallbackHandle::First(handle) => {
                 // SAFETY: Same safety requirements
-                let res = unsafe {
-                    self.first
-                        .try_grow(handle, capacity)
-                        .map(FallbackHandle::First)
-                };
-
-                if let Ok(handle) = res {
-                    return Ok(handle);
+                let res = unsafe { self.first.try_grow_moved(handle, capacity) };
+
+                if let Ok((handle, moved)) = res {
+                    return Ok((FallbackHandle::First(handle), moved));
                 }
 
                 // SAFETY: We require the provided handle is valid
@@ -142,13 +182,13 @@ where
                 // SAFETY: We require the provided handle is valid, so it's safe to deallocate
                 unsafe { self.first.deallocate_single(handle) };
 
-                Ok(FallbackHandle::Second(new_handle))
+                Ok((FallbackHandle::Second(new_handle), true))
             }
             // SAFETY: Same safety requirements
             FallbackHandle::Second(handle) => unsafe {
                 self.second
-                    .try_shrink(handle, capacity)
-                    .map(FallbackHandle::Second)
+                    .try_grow_moved(handle, capacity)
+                    .map(|(handle, moved)| (FallbackHandle::Second(handle), moved))
             },
         }
     }
@@ -185,10 +225,26 @@ where
         &mut self,
         meta: T::Metadata,
     ) -> error::Result<Self::Handle<T>> {
-        self.first
-            .allocate(meta)
-            .map(FallbackHandle::First)
-            .or_else(|_| self.second.allocate(meta).map(FallbackHandle::Second))
+        if self.first.will_fit_hint::<T>(meta) != Some(false) {
+            if let Ok(handle) = self.first.allocate(meta) {
+                return Ok(FallbackHandle::First(handle));
+            }
+        }
+        self.second.allocate(meta).map(FallbackHandle::Second)
+    }
+
+    fn allocate_zeroed<T: ?Sized + Pointee>(
+        &mut self,
+        meta: T::Metadata,
+    ) -> error::Result<Self::Handle<T>> {
+        if self.first.will_fit_hint::<T>(meta) != Some(false) {
+            if let Ok(handle) = self.first.allocate_zeroed(meta) {
+                return Ok(FallbackHandle::First(handle));
+            }
+        }
+        self.second
+            .allocate_zeroed(meta)
+            .map(FallbackHandle::Second)
     }
 
     unsafe fn deallocate<T: ?Sized + Pointee>(&mut self, handle: Self::Handle<T>) {
@@ -199,6 +255,13 @@ where
             FallbackHandle::Second(handle) => unsafe { self.second.deallocate(handle) },
         }
     }
+
+    fn is_allocated<T: ?Sized + Pointee>(&self, handle: Self::Handle<T>) -> bool {
+        match handle {
+            FallbackHandle::First(handle) => self.first.is_allocated(handle),
+            FallbackHandle::Second(handle) => self.second.is_allocated(handle),
+        }
+    }
 }
 
 impl<S1, S2> ExactSizeStorage for FallbackStorage<S1, S2>
@@ -234,7 +297,8 @@ where
 mod private {
     use super::*;
     use core::cmp::Ordering;
-    use core::mem::ManuallyDrop;
+    use core::hash::{Hash, Hasher};
+    use core::mem::{self, ManuallyDrop};
 
     union HandleCast<S: Storage, T: ?Sized, U: ?Sized> {
         left: ManuallyDrop<<S::Handle<T> as Handle>::This<U>>,
@@ -302,6 +366,10 @@ mod private {
     }
 
     /// Handle for a fallback storage. Contains either a handle for the first or second storage used
+    ///
+    /// No custom layout is used - if the first handle is zero-sized, as with
+    /// [`SingleInline`](crate::inline::SingleInline), the discriminant reuses a niche of the second
+    /// handle and this is the same size as it. Otherwise, an extra tag is required.
     #[non_exhaustive]
     pub enum FallbackHandle<S1: Storage, S2: Storage, T: ?Sized> {
         /// Allocation uses the first storage
@@ -311,6 +379,16 @@ mod private {
     }
 
     impl<S1: Storage, S2: Storage, T: ?Sized> FallbackHandle<S1, S2, T> {
+        /// Whether this allocation was placed in the first storage
+        pub fn is_first(self) -> bool {
+            matches!(self, FallbackHandle::First(_))
+        }
+
+        /// Whether this allocation fell back to the second storage
+        pub fn is_second(self) -> bool {
+            matches!(self, FallbackHandle::Second(_))
+        }
+
         fn map<U: ?Sized>(
             self,
             left: impl FnOnce(S1::Handle<T>) -> <S1::Handle<T> as Handle>::This<U>,
@@ -360,6 +438,27 @@ mod private {
         }
     }
 
+    impl<S1: Storage, S2: Storage, T: ?Sized> Eq for FallbackHandle<S1, S2, T>
+    where
+        S1::Handle<T>: Eq,
+        S2::Handle<T>: Eq,
+    {
+    }
+
+    impl<S1: Storage, S2: Storage, T: ?Sized> Hash for FallbackHandle<S1, S2, T>
+    where
+        S1::Handle<T>: Hash,
+        S2::Handle<T>: Hash,
+    {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            mem::discriminant(self).hash(state);
+            match self {
+                FallbackHandle::First(handle) => handle.hash(state),
+                FallbackHandle::Second(handle) => handle.hash(state),
+            }
+        }
+    }
+
     impl<S1, S2, T> Clone for FallbackHandle<S1, S2, T>
     where
         S1: Storage,
@@ -444,7 +543,11 @@ use private::FallbackHandle;
 mod tests {
     use super::*;
     use crate::alloc::GlobalAlloc;
-    use crate::inline::SingleInline;
+    use crate::boxed::Box;
+    use crate::heap::VirtHeap;
+    use crate::inline::{MultiInline, SingleInline};
+    use core::mem::size_of;
+    use core::ptr::NonNull;
 
     type Store = FallbackStorage<SingleInline<[u16; 4]>, GlobalAlloc>;
 
@@ -478,4 +581,160 @@ mod tests {
 
         unsafe { f.deallocate_single(h3) };
     }
+
+    #[test]
+    fn test_try_grow_moved() {
+        let mut f = Store::default();
+
+        let h1 = f.allocate_single::<[u16]>(2).unwrap();
+        let (h2, moved) = unsafe { f.try_grow_moved(h1, 4) }.unwrap();
+        assert!(matches!(h2, FallbackHandle::First(_)));
+        assert!(!moved);
+        let (h3, moved) = unsafe { f.try_grow_moved(h2, 8) }.unwrap();
+        assert!(matches!(h3, FallbackHandle::Second(_)));
+        assert!(moved);
+        // Growing within the second storage grows, rather than shrinking
+        let (h4, _) = unsafe { f.try_grow_moved(h3, 64) }.unwrap();
+        assert_eq!(h4.metadata(), 64);
+
+        unsafe { f.deallocate_single(h4) };
+    }
+
+    #[test]
+    fn test_is_first() {
+        let mut f = Store::default();
+
+        let h1 = f.create_single::<u16>(1).unwrap();
+        assert!(h1.is_first());
+        assert!(!h1.is_second());
+        unsafe { f.drop_single(h1) };
+
+        let h2 = f.create_single::<[u16; 8]>([1; 8]).unwrap();
+        assert!(!h2.is_first());
+        assert!(h2.is_second());
+        unsafe { f.drop_single(h2) };
+    }
+
+    #[test]
+    fn test_box_parts() {
+        let b1 = Box::<_, Store>::new(1u16);
+        let (storage, handle) = b1.parts();
+        assert!(handle.is_first());
+        assert_eq!(unsafe { storage.get(handle) }, NonNull::from(&*b1));
+
+        let b2 = Box::<_, Store>::new([1u16; 8]);
+        assert!(b2.parts().1.is_second());
+        assert_eq!(*b2, [1; 8]);
+    }
+
+    #[test]
+    fn test_will_fit_fast_path() {
+        use crate::handles::MetaHandle;
+
+        /// A storage which can't allocate anything, and panics if it's asked to
+        struct Refusing;
+
+        unsafe impl Storage for Refusing {
+            type Handle<T: ?Sized> = MetaHandle<T>;
+
+            unsafe fn get<T: ?Sized>(&self, _: Self::Handle<T>) -> NonNull<T> {
+                unreachable!()
+            }
+
+            fn from_raw_parts<T: ?Sized + Pointee>(
+                handle: Self::Handle<()>,
+                meta: T::Metadata,
+            ) -> Self::Handle<T> {
+                MetaHandle::from_raw_parts(handle, meta)
+            }
+
+            fn cast<T: ?Sized + Pointee, U>(handle: Self::Handle<T>) -> Self::Handle<U> {
+                handle.cast()
+            }
+
+            fn cast_unsized<T: ?Sized + Pointee, U: ?Sized + Pointee<Metadata = T::Metadata>>(
+                handle: Self::Handle<T>,
+            ) -> Self::Handle<U> {
+                handle.cast_unsized()
+            }
+
+            #[cfg(feature = "unsize")]
+            fn coerce<T: ?Sized + Unsize<U>, U: ?Sized>(
+                handle: Self::Handle<T>,
+            ) -> Self::Handle<U> {
+                handle.coerce()
+            }
+
+            fn will_fit_hint<T: ?Sized + Pointee>(&self, meta: T::Metadata) -> Option<bool> {
+                Some(self.will_fit::<T>(meta))
+            }
+
+            fn allocate_single<T: ?Sized + Pointee>(
+                &mut self,
+                _: T::Metadata,
+            ) -> error::Result<Self::Handle<T>> {
+                panic!("Refusing storage was asked to allocate")
+            }
+
+            unsafe fn deallocate_single<T: ?Sized>(&mut self, _: Self::Handle<T>) {
+                unreachable!()
+            }
+        }
+
+        impl ExactSizeStorage for Refusing {
+            fn will_fit<T: ?Sized + Pointee>(&self, _: T::Metadata) -> bool {
+                false
+            }
+
+            fn max_range<T>(&self) -> usize {
+                0
+            }
+        }
+
+        let mut f = FallbackStorage::new(Refusing, GlobalAlloc::global());
+        let h = f.allocate_single::<[u16]>(4).unwrap();
+        assert!(h.is_second());
+        unsafe { f.deallocate_single(h) };
+    }
+
+    #[test]
+    fn test_will_fit_heap() {
+        let heap = VirtHeap::<u8, 64>::new();
+        let mut f = FallbackStorage::new(&heap, GlobalAlloc::global());
+
+        // Items spanning multiple blocks of the heap still fit in it
+        let h = f.allocate_single::<[u8; 4]>(()).unwrap();
+        assert!(h.is_first());
+        unsafe { f.deallocate_single(h) };
+
+        let h = f.allocate_single::<[u8; 65]>(()).unwrap();
+        assert!(h.is_second());
+        unsafe { f.deallocate_single(h) };
+    }
+
+    #[test]
+    fn test_handle_size() {
+        type Handle<T> = <Store as Storage>::Handle<T>;
+        type MultiHandle<T> =
+            <FallbackStorage<MultiInline<[u16; 4], 4>, GlobalAlloc> as Storage>::Handle<T>;
+
+        // A zero-sized first handle lets the discriminant live in the niche of `NonNull`
+        assert_eq!(size_of::<Handle<u16>>(), size_of::<NonNull<u16>>());
+        assert_eq!(size_of::<Handle<[u16]>>(), size_of::<NonNull<[u16]>>());
+        // That niche is used up, so an `Option` needs its own tag
+        assert_eq!(
+            size_of::<Option<Handle<u16>>>(),
+            size_of::<NonNull<u16>>() + size_of::<usize>()
+        );
+
+        // Two non-zero-sized handles need a tag, which leaves room for `Option`
+        assert_eq!(
+            size_of::<MultiHandle<u16>>(),
+            size_of::<NonNull<u16>>() + size_of::<usize>()
+        );
+        assert_eq!(
+            size_of::<Option<MultiHandle<u16>>>(),
+            size_of::<MultiHandle<u16>>()
+        );
+    }
 }
@@ -4,6 +4,7 @@
 //! though not all handles may implement all items.
 
 use core::fmt;
+use core::hash::{Hash, Hasher};
 #[cfg(feature = "unsize")]
 use core::marker::Unsize;
 use core::num::NonZeroUsize;
@@ -161,7 +162,7 @@ impl<T: ?Sized + Pointee> Handle for NonNull<T> {
         U: ?Sized + Pointee<Metadata = T::Metadata>,
     {
         let meta = ptr::metadata(self.as_ptr());
-        NonNull::from_raw_parts(self.cast(), meta)
+        NonNull::from_raw_parts(self.cast::<()>(), meta)
     }
 
     #[cfg(feature = "unsize")]
@@ -272,6 +273,14 @@ impl<T: ?Sized> PartialEq for MetaHandle<T> {
     }
 }
 
+impl<T: ?Sized> Eq for MetaHandle<T> {}
+
+impl<T: ?Sized> Hash for MetaHandle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
 impl<T> fmt::Debug for MetaHandle<T>
 where
     T: ?Sized + Pointee,
@@ -421,6 +430,15 @@ impl<T: ?Sized> PartialEq for OffsetMetaHandle<T> {
     }
 }
 
+impl<T: ?Sized> Eq for OffsetMetaHandle<T> {}
+
+impl<T: ?Sized> Hash for OffsetMetaHandle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+        self.1.hash(state);
+    }
+}
+
 impl<T> fmt::Debug for OffsetMetaHandle<T>
 where
     T: ?Sized + Pointee,
@@ -450,4 +468,38 @@ mod tests {
         assert_eq!(h1, MetaHandle::from_raw_parts(h2, 1));
         assert_eq!(h3, MetaHandle::from_raw_parts(h2, 1));
     }
+
+    #[test]
+    fn test_hash() {
+        use std::collections::HashSet;
+
+        let mut set = HashSet::new();
+        assert!(set.insert(OffsetMetaHandle::<[u8]>::from_offset_meta(0, 4)));
+        assert!(set.insert(OffsetMetaHandle::from_offset_meta(0, 8)));
+        assert!(set.insert(OffsetMetaHandle::from_offset_meta(1, 4)));
+        assert!(!set.insert(OffsetMetaHandle::from_offset_meta(0, 4)));
+
+        let mut set = HashSet::new();
+        assert!(set.insert(MetaHandle::<str>::from_metadata(1)));
+        assert!(!set.insert(MetaHandle::<[u8]>::from_metadata(1).cast_unsized::<str>()));
+        assert!(!set.insert(MetaHandle::from_metadata(1)));
+    }
+
+    #[test]
+    fn test_offset_meta_niche() {
+        use core::mem::size_of;
+
+        assert_eq!(
+            size_of::<Option<OffsetMetaHandle<u32>>>(),
+            size_of::<OffsetMetaHandle<u32>>()
+        );
+        assert_eq!(
+            size_of::<Option<OffsetMetaHandle<[u32]>>>(),
+            size_of::<OffsetMetaHandle<[u32]>>()
+        );
+        assert_eq!(
+            size_of::<Option<OffsetMetaHandle<dyn fmt::Debug>>>(),
+            size_of::<OffsetMetaHandle<dyn fmt::Debug>>()
+        );
+    }
 }
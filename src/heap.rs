@@ -49,44 +49,112 @@ use crate::error::{Result, StorageError};
 use crate::handles::{Handle, OffsetMetaHandle};
 use crate::utils;
 
+/// The lock guarding a heap's block usage. Under `std` this blocks the thread while contended,
+/// rather than spinning.
+#[cfg(feature = "std")]
+type Lock<T> = std::sync::Mutex<T>;
+#[cfg(feature = "std")]
+type LockGuard<'a, T> = std::sync::MutexGuard<'a, T>;
+#[cfg(not(feature = "std"))]
+type Lock<T> = spin::Mutex<T>;
+#[cfg(not(feature = "std"))]
+type LockGuard<'a, T> = spin::MutexGuard<'a, T>;
+
 /// Given a size, determine how many blocks are required to fit it
 fn blocks<S>(size: usize) -> usize {
-    size / mem::size_of::<S>()
+    size.div_ceil(mem::size_of::<S>())
 }
 
 /// Given a type and a length, determine how many blocks are needed to fit length instances
 fn blocks_for<S, T>(capacity: usize) -> usize {
-    (mem::size_of::<T>() * capacity) / mem::size_of::<S>()
+    blocks::<S>(mem::size_of::<T>() * capacity)
 }
 
-fn lock_range<const N: usize>(lock: &mut spin::MutexGuard<'_, [bool; N]>, range: Range<usize>) {
-    lock[range].iter_mut().for_each(|i| {
-        debug_assert!(!*i);
-        *i = true;
-    });
+/// Block usage of a heap, guarded by its lock
+///
+/// # Panic safety
+///
+/// Critical sections holding the lock never call into user code, and can only panic if an
+/// invariant is already broken (such as by an invalid handle), so a panic elsewhere can't leave
+/// the blocks inconsistent. The lock is released if a holder unwinds, and any poisoning is
+/// ignored.
+#[derive(Debug)]
+struct Blocks<const N: usize> {
+    used: [bool; N],
+    /// The most recently freed run of blocks, tried first by the next allocation
+    last_freed: Option<Range<usize>>,
 }
 
-fn unlock_range<const N: usize>(lock: &mut spin::MutexGuard<'_, [bool; N]>, range: Range<usize>) {
-    lock[range].iter_mut().for_each(|i| {
-        debug_assert!(*i);
-        *i = false;
-    });
+impl<const N: usize> Blocks<N> {
+    fn lock_range(&mut self, range: Range<usize>) {
+        self.used[range].iter_mut().for_each(|i| {
+            debug_assert!(!*i);
+            *i = true;
+        });
+    }
+
+    fn unlock_range(&mut self, range: Range<usize>) {
+        self.used[range].iter_mut().for_each(|i| {
+            debug_assert!(*i);
+            *i = false;
+        });
+    }
+
+    /// Unlock a range that's been deallocated, remembering it for reuse
+    fn free_range(&mut self, range: Range<usize>) {
+        if !range.is_empty() {
+            self.unlock_range(range.clone());
+            self.last_freed = Some(range);
+        }
+    }
+
+    /// Try to fit an allocation at the start of the most recently freed run, taking it if it's
+    /// still free, big enough, and correctly aligned
+    fn take_freed(&mut self, blocks: usize, is_aligned: impl Fn(usize) -> bool) -> Option<usize> {
+        let freed = self.last_freed.clone()?;
+        let range = freed.start..(freed.start + blocks);
+
+        let fits = blocks != 0
+            && range.end <= freed.end
+            && is_aligned(range.start)
+            && self.used[range.clone()].iter().all(|&used| !used);
+        if !fits {
+            return None;
+        }
+
+        self.last_freed = Some(range.end..freed.end).filter(|rest| !rest.is_empty());
+        self.lock_range(range.clone());
+        Some(range.start)
+    }
 }
 
-/// Attempt to find open space for an allocation of a given size.
-/// If size is zero, this returns a zero-sized range
+/// Attempt to find open space for an allocation of a given layout, starting at an address meeting
+/// the layout's alignment. If size is zero, this returns a zero-sized range
 fn find_open<S, const N: usize>(
-    lock: &spin::MutexGuard<'_, [bool; N]>,
-    size: usize,
+    lock: &[bool; N],
+    base: *const S,
+    layout: Layout,
 ) -> Result<Range<usize>> {
-    let blocks = blocks::<S>(size);
+    let blocks = blocks::<S>(layout.size());
+    let is_aligned =
+        |start: usize| (base.addr() + start * mem::size_of::<S>()).is_multiple_of(layout.align());
+    // Reported if no start in the heap is aligned, however many blocks are free. The available
+    // alignment is that of a single block, as that's all the backing guarantees.
+    let invalid_align = StorageError::InvalidAlign {
+        expected: layout.align(),
+        available: mem::align_of::<S>(),
+    };
 
     if blocks == 0 {
-        return Ok(0..0);
+        // `get` indexes the backing at the offset, so even zero-sized items must start in-bounds
+        return (0..N)
+            .find(|&start| is_aligned(start))
+            .map(|start| start..start)
+            .ok_or(invalid_align);
     }
     if blocks > N {
         return Err(StorageError::InsufficientSpace {
-            expected: size,
+            expected: layout.size(),
             available: Some(mem::size_of::<S>() * N),
         });
     }
@@ -101,14 +169,21 @@ fn find_open<S, const N: usize>(
             }
             Some(*n)
         })
-        // Find the end point of a chain with the right size, if one exist
-        .position(|count| count >= blocks)
+        .enumerate()
+        // Find the end point of a chain with the right size and an aligned start, if one exist
+        .find(|&(end, count)| count >= blocks && is_aligned(end + 1 - blocks))
         // Find the range of the desired chain
-        .map(|end| {
+        .map(|(end, _)| {
             let start = end - (blocks - 1);
             start..(end + 1)
         })
-        .ok_or(StorageError::NoSlots)
+        .ok_or_else(|| {
+            if (0..=N - blocks).any(is_aligned) {
+                StorageError::NoSlots
+            } else {
+                invalid_align
+            }
+        })
 }
 
 /// A storage based on a variable (static or on the stack), supporting heap-like behavior but
@@ -116,11 +191,15 @@ fn find_open<S, const N: usize>(
 /// for either a larger binary or more stack usage.
 ///
 /// Note that any items stored take at minimum one instance of `S` due to current limitations on
-/// implementation.
+/// implementation. Items with a greater alignment than `S` are placed at offsets meeting their
+/// alignment, which may leave gaps in the heap.
+///
+/// Allocations first try to reuse the most recently freed blocks before searching the heap from
+/// the start, which keeps churning allocations in the same, likely cached, memory.
 #[derive(Debug)]
 pub struct VirtHeap<S, const N: usize> {
     // TODO: This is unnecessarily inefficient in terms of memory
-    used: spin::Mutex<[bool; N]>,
+    used: Lock<Blocks<N>>,
     storage: UnsafeCell<[MaybeUninit<S>; N]>,
 }
 
@@ -130,12 +209,26 @@ where
 {
     /// Create a new heap
     pub const fn new() -> VirtHeap<S, N> {
+        // SAFETY: The array contains only `MaybeUninit` values, so this is okay
+        VirtHeap::from_backing(unsafe {
+            MaybeUninit::<[MaybeUninit<S>; N]>::uninit().assume_init()
+        })
+    }
+
+    /// Create a new heap which adopts the provided memory as its backing, with every block
+    /// starting out free. Any existing contents of the memory are ignored, and may be overwritten
+    /// by allocations.
+    ///
+    /// The backing is moved into the heap, so it can't be accessed other than through the heap's
+    /// allocations while the heap lives. To place a heap at a fixed location, such as a specific
+    /// linker section, construct it in a `static` there instead.
+    pub const fn from_backing(backing: [MaybeUninit<S>; N]) -> VirtHeap<S, N> {
         VirtHeap {
-            used: spin::Mutex::new([false; N]),
-            // SAFETY: The array contains only `MaybeUninit` values, so this is okay
-            storage: UnsafeCell::new(unsafe {
-                MaybeUninit::<[MaybeUninit<S>; N]>::uninit().assume_init()
+            used: Lock::new(Blocks {
+                used: [false; N],
+                last_freed: None,
             }),
+            storage: UnsafeCell::new(backing),
         }
     }
 }
@@ -144,11 +237,49 @@ impl<S, const N: usize> VirtHeap<S, N>
 where
     S: StorageSafe,
 {
-    fn find_lock(&self, size: usize) -> Result<usize> {
-        let mut used = self.used.lock();
-        let open = find_open::<S, N>(&used, size)?;
+    fn base(&self) -> *const S {
+        self.storage.get().cast::<S>()
+    }
+
+    fn lock(&self) -> LockGuard<'_, Blocks<N>> {
+        #[cfg(feature = "std")]
+        {
+            self.used
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            self.used.lock()
+        }
+    }
+
+    /// Check whether all blocks covering an item of a given size at an offset are in use.
+    /// Zero-sized items are always considered allocated.
+    fn range_used(&self, offset: usize, size: usize) -> bool {
+        let blocks = blocks::<S>(size);
+        blocks == 0
+            || self
+                .lock()
+                .used
+                .get(offset..offset + blocks)
+                .is_some_and(|range| range.iter().all(|&used| used))
+    }
+
+    fn find_lock(&self, layout: Layout) -> Result<usize> {
+        let mut used = self.lock();
+
+        let base = self.base();
+        let is_aligned = |start: usize| {
+            (base.addr() + start * mem::size_of::<S>()).is_multiple_of(layout.align())
+        };
+        if let Some(start) = used.take_freed(blocks::<S>(layout.size()), is_aligned) {
+            return Ok(start);
+        }
+
+        let open = find_open::<S, N>(&used.used, base, layout)?;
         let start = open.start;
-        lock_range(&mut used, open);
+        used.lock_range(open);
         Ok(start)
     }
 
@@ -158,17 +289,17 @@ where
         old_layout: Layout,
         new_layout: Layout,
     ) -> bool {
-        let mut used = self.used.lock();
+        let mut used = self.lock();
 
         let old_blocks = blocks::<S>(old_layout.size());
         let new_blocks = blocks::<S>(new_layout.size());
 
         let after_old = (handle.offset() + old_blocks)..(handle.offset() + new_blocks);
 
-        let has_space = used[after_old.clone()].iter().all(|&i| !i);
+        let has_space = after_old.end <= N && used.used[after_old.clone()].iter().all(|&i| !i);
 
         if has_space {
-            lock_range(&mut used, after_old);
+            used.lock_range(after_old);
         }
 
         has_space
@@ -179,25 +310,23 @@ where
         handle: <&Self as Storage>::Handle<[T]>,
         new_layout: Layout,
     ) -> Option<usize> {
-        let mut used = self.used.lock();
+        let mut used = self.lock();
         let old_range = handle.offset()..(handle.offset() + blocks_for::<S, T>(handle.metadata()));
 
-        if handle.metadata() != 0 {
-            unlock_range(&mut used, old_range.clone());
-        }
+        // The old range is briefly marked free so the new one may overlap it. Searching can't
+        // panic, so the range is always either relocked or replaced before the lock is released.
+        used.unlock_range(old_range.clone());
 
-        let new_range = match find_open::<S, N>(&used, new_layout.size()) {
+        let new_range = match find_open::<S, N>(&used.used, self.base(), new_layout) {
             Ok(open) => open,
             Err(_) => {
-                if handle.metadata() != 0 {
-                    lock_range(&mut used, old_range);
-                }
+                used.lock_range(old_range);
                 return None;
             }
         };
 
         let new_start = new_range.start;
-        lock_range(&mut used, new_range);
+        used.lock_range(new_range);
 
         // SAFETY: We only access slices of the mutex we have a lock on
         unsafe { &mut *self.storage.get() }.copy_within(old_range, new_start);
@@ -223,10 +352,14 @@ where
     type Handle<T: ?Sized> = OffsetMetaHandle<T>;
 
     unsafe fn get<T: ?Sized>(&self, handle: Self::Handle<T>) -> NonNull<T> {
+        utils::strict_assert!(
+            self.is_allocated(handle),
+            "VirtHeap handle doesn't point to an allocation"
+        );
         // SAFETY: We only access slices of the mutex this handle has a lock on
         let slice_ptr = unsafe { ptr::addr_of_mut!((*self.storage.get())[handle.offset()]) };
         // SAFETY: We retrieved this from an offset on a guaranteed valid pointer
-        let ptr = unsafe { NonNull::new_unchecked(slice_ptr).cast() };
+        let ptr = unsafe { NonNull::new_unchecked(slice_ptr).cast::<()>() };
         NonNull::from_raw_parts(ptr, handle.metadata())
     }
 
@@ -254,6 +387,14 @@ where
         handle.coerce()
     }
 
+    fn will_fit_hint<T: ?Sized + Pointee>(&self, meta: T::Metadata) -> Option<bool> {
+        Some(self.will_fit::<T>(meta))
+    }
+
+    fn max_range_hint<T>(&self) -> usize {
+        self.max_range::<T>()
+    }
+
     fn allocate_single<T: ?Sized + Pointee>(
         &mut self,
         meta: T::Metadata,
@@ -271,6 +412,15 @@ where
         handle: Self::Handle<[T]>,
         capacity: usize,
     ) -> Result<Self::Handle<[T]>> {
+        // SAFETY: Shares our safety requirements
+        unsafe { self.try_grow_moved(handle, capacity) }.map(|(handle, _)| handle)
+    }
+
+    unsafe fn try_grow_moved<T>(
+        &mut self,
+        handle: Self::Handle<[T]>,
+        capacity: usize,
+    ) -> Result<(Self::Handle<[T]>, bool)> {
         debug_assert!(capacity >= handle.metadata());
         // We need to check if we can grow in-place. If not, then we need to see if we have any
         // open space for the new range, ignoring ourselves as we're allowed to overwrite that.
@@ -278,12 +428,16 @@ where
         let new_layout = Layout::array::<T>(capacity).map_err(|_| StorageError::exceeds_max())?;
 
         if self.grow_in_place(handle, old_layout, new_layout) {
-            Ok(OffsetMetaHandle::from_offset_meta(
-                handle.offset(),
-                capacity,
+            Ok((
+                OffsetMetaHandle::from_offset_meta(handle.offset(), capacity),
+                false,
             ))
         } else if let Some(new_start) = self.grow_move(handle, new_layout) {
-            Ok(OffsetMetaHandle::from_offset_meta(new_start, capacity))
+            let moved = new_start != handle.offset();
+            Ok((
+                OffsetMetaHandle::from_offset_meta(new_start, capacity),
+                moved,
+            ))
         } else {
             Err(StorageError::InsufficientSpace {
                 expected: new_layout.size(),
@@ -298,9 +452,9 @@ where
         capacity: usize,
     ) -> Result<Self::Handle<[T]>> {
         debug_assert!(capacity <= handle.metadata());
-        unlock_range(
-            &mut self.used.lock(),
-            (handle.offset() + capacity)..(handle.offset() + handle.metadata()),
+        self.lock().free_range(
+            (handle.offset() + blocks_for::<S, T>(capacity))
+                ..(handle.offset() + blocks_for::<S, T>(handle.metadata())),
         );
         Ok(OffsetMetaHandle::from_offset_meta(
             handle.offset(),
@@ -315,22 +469,35 @@ where
     S: StorageSafe,
 {
     fn allocate<T: ?Sized + Pointee>(&mut self, meta: T::Metadata) -> Result<Self::Handle<T>> {
-        let layout = utils::layout_of::<T>(meta);
-        utils::validate_layout_for::<[S; N]>(layout)?;
-        let start = self.find_lock(layout.size())?;
+        let layout = utils::layout_of::<T>(meta)?;
+        // Alignment is handled per-allocation, so only the size needs to be checked up-front
+        if layout.size() > mem::size_of::<[S; N]>() {
+            return Err(StorageError::InsufficientSpace {
+                expected: layout.size(),
+                available: Some(mem::size_of::<[S; N]>()),
+            });
+        }
+        let start = self.find_lock(layout)?;
         Ok(OffsetMetaHandle::from_offset_meta(start, meta))
     }
 
     unsafe fn deallocate<T: ?Sized + Pointee>(&mut self, handle: Self::Handle<T>) {
         // SAFETY: By deallocation's safety requirements, the handle is valid at this point
-        let ptr = unsafe { self.get(handle) };
-        // SAFETY: get will return a valid pointer to `T`
-        let layout = unsafe { Layout::for_value_raw(ptr.as_ptr()) };
-        let mut used = self.used.lock();
-        unlock_range(
-            &mut used,
-            handle.offset()..(handle.offset() + blocks::<S>(layout.size())),
+        let layout = unsafe { self.layout_of_handle(handle) };
+        let mut used = self.lock();
+        let range = handle.offset()..(handle.offset() + blocks::<S>(layout.size()));
+        utils::strict_assert!(
+            used.used
+                .get(range.clone())
+                .is_some_and(|range| range.iter().all(|&used| used)),
+            "VirtHeap handle was already deallocated"
         );
+        used.free_range(range);
+    }
+
+    fn is_allocated<T: ?Sized + Pointee>(&self, handle: Self::Handle<T>) -> bool {
+        utils::layout_of::<T>(handle.metadata())
+            .is_ok_and(|layout| self.range_used(handle.offset(), layout.size()))
     }
 }
 
@@ -339,8 +506,8 @@ where
     S: StorageSafe,
 {
     fn will_fit<T: ?Sized + Pointee>(&self, meta: T::Metadata) -> bool {
-        let layout = utils::layout_of::<T>(meta);
-        mem::size_of::<S>() >= layout.size()
+        // Allocations may span any number of blocks, so the whole heap is available
+        utils::layout_of::<T>(meta).is_ok_and(|layout| mem::size_of::<[S; N]>() >= layout.size())
     }
 
     fn max_range<T>(&self) -> usize {
@@ -364,18 +531,17 @@ where
     unsafe fn unleak_ptr<T: ?Sized>(&self, leaked: *mut T) -> Self::Handle<T> {
         let meta = ptr::metadata(leaked);
 
-        // We don't need a lock here because we never dereference the pointer
-        // SAFETY: Our safety requirements guarantee the provided pointer was generated
-        //         in-bounds of our backing
-        let offset: usize = unsafe {
-            leaked
-                .cast::<S>()
-                .offset_from(self.storage.get() as *const S)
-                .try_into()
-                .unwrap()
-        };
+        // We don't need a lock here because we never dereference the pointer. The offset is
+        // computed from addresses, so a pointer from another heap can be caught instead of being
+        // turned into a bogus handle.
+        let byte_offset = leaked.addr().wrapping_sub(self.base().addr());
+        debug_assert!(
+            byte_offset < mem::size_of::<S>() * N
+                && byte_offset.is_multiple_of(mem::size_of::<S>()),
+            "Leaked pointer doesn't point into this VirtHeap's backing"
+        );
 
-        OffsetMetaHandle::from_offset_meta(offset, meta)
+        OffsetMetaHandle::from_offset_meta(byte_offset / mem::size_of::<S>(), meta)
     }
 }
 
@@ -386,6 +552,8 @@ unsafe impl<S: Sync + StorageSafe, const N: usize> Sync for VirtHeap<S, N> {}
 
 #[cfg(test)]
 mod tests {
+    use core::fmt;
+
     use crate::boxed::Box;
     use crate::collections::Vec;
 
@@ -457,47 +625,239 @@ mod tests {
         Box::<[u8; 8]>::try_new_in([1, 2, 3, 4, 5, 6, 7, 8], &HEAP).unwrap_err();
     }
 
+    #[derive(Debug)]
+    #[repr(align(1))]
+    struct Align1;
+    #[derive(Debug)]
+    #[repr(align(2))]
+    struct Align2;
+    #[derive(Debug)]
+    #[repr(align(4))]
+    struct Align4;
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[repr(align(8))]
+    struct Align8(u8);
+
     #[test]
     fn test_align() {
-        static FOO1: VirtHeap<u8, 4> = VirtHeap::new();
-        static FOO2: VirtHeap<u16, 4> = VirtHeap::new();
+        static FOO1: VirtHeap<u8, 16> = VirtHeap::new();
+        static FOO2: VirtHeap<u16, 8> = VirtHeap::new();
         static FOO4: VirtHeap<u32, 4> = VirtHeap::new();
-        static FOO8: VirtHeap<u64, 4> = VirtHeap::new();
-
-        type Box<T, S> = crate::boxed::Box<T, &'static VirtHeap<S, 4>>;
-
-        #[derive(Debug)]
-        #[repr(align(1))]
-        struct Align1;
-        #[derive(Debug)]
-        #[repr(align(2))]
-        struct Align2;
-        #[derive(Debug)]
-        #[repr(align(4))]
-        struct Align4;
-        #[derive(Debug)]
-        #[repr(align(8))]
-        struct Align8;
-
-        Box::<_, u8>::try_new_in(Align1, &FOO1).unwrap();
-        Box::<_, u8>::try_new_in(Align2, &FOO1).unwrap_err();
-        Box::<_, u8>::try_new_in(Align4, &FOO1).unwrap_err();
-        Box::<_, u8>::try_new_in(Align8, &FOO1).unwrap_err();
-
-        Box::<_, u16>::try_new_in(Align1, &FOO2).unwrap();
-        Box::<_, u16>::try_new_in(Align2, &FOO2).unwrap();
-        Box::<_, u16>::try_new_in(Align4, &FOO2).unwrap_err();
-        Box::<_, u16>::try_new_in(Align8, &FOO2).unwrap_err();
-
-        Box::<_, u32>::try_new_in(Align1, &FOO4).unwrap();
-        Box::<_, u32>::try_new_in(Align2, &FOO4).unwrap();
-        Box::<_, u32>::try_new_in(Align4, &FOO4).unwrap();
-        Box::<_, u32>::try_new_in(Align8, &FOO4).unwrap_err();
-
-        Box::<_, u64>::try_new_in(Align1, &FOO8).unwrap();
-        Box::<_, u64>::try_new_in(Align2, &FOO8).unwrap();
-        Box::<_, u64>::try_new_in(Align4, &FOO8).unwrap();
-        Box::<_, u64>::try_new_in(Align8, &FOO8).unwrap();
+        static FOO8: VirtHeap<u64, 2> = VirtHeap::new();
+
+        type Box<T, S, const N: usize> = crate::boxed::Box<T, &'static VirtHeap<S, N>>;
+
+        fn check<T: fmt::Debug, S: StorageSafe, const N: usize>(
+            val: T,
+            heap: &'static VirtHeap<S, N>,
+        ) {
+            let b = Box::<_, S, N>::try_new_in(val, heap).unwrap();
+            assert!((&*b as *const T).is_aligned());
+        }
+
+        check(Align1, &FOO1);
+        check(Align2, &FOO1);
+        check(Align4, &FOO1);
+        check(Align8(0), &FOO1);
+
+        check(Align1, &FOO2);
+        check(Align2, &FOO2);
+        check(Align4, &FOO2);
+        check(Align8(0), &FOO2);
+
+        check(Align1, &FOO4);
+        check(Align2, &FOO4);
+        check(Align4, &FOO4);
+        check(Align8(0), &FOO4);
+
+        check(Align1, &FOO8);
+        check(Align2, &FOO8);
+        check(Align4, &FOO8);
+        check(Align8(0), &FOO8);
+    }
+
+    #[test]
+    fn test_align_error() {
+        let layout = Layout::from_size_align(2, 8).unwrap();
+        let free = [false; 16];
+
+        // No block of the heap starts at an 8-aligned address
+        let res = find_open::<u8, 4>(&[false; 4], ptr::without_provenance(1), layout);
+        assert!(matches!(
+            res,
+            Err(StorageError::InvalidAlign {
+                expected: 8,
+                available: 1
+            })
+        ));
+        let res = find_open::<u8, 16>(&free, ptr::without_provenance(1), Layout::new::<()>());
+        assert!(res.is_ok());
+        let res = find_open::<u8, 16>(&free, ptr::without_provenance(1), layout);
+        assert_eq!(res.unwrap(), 7..9);
+
+        // Aligned starts exist, they're just in use
+        let mut used = [true; 16];
+        used[..7].fill(false);
+        let res = find_open::<u8, 16>(&used, ptr::without_provenance(1), layout);
+        assert!(matches!(res, Err(StorageError::NoSlots)));
+    }
+
+    #[test]
+    fn test_grow_align() {
+        let heap: VirtHeap<u8, 128> = VirtHeap::new();
+
+        let mut v = Vec::new_in(&heap);
+        v.extend([Align8(1), Align8(2)]);
+        // Block the space directly after the vec, forcing the next grow to move it
+        let blocker = Box::new_in(Align8(0), &heap);
+        let old_ptr = v.as_ptr();
+        v.extend([Align8(3), Align8(4), Align8(5)]);
+
+        assert_ne!(v.as_ptr(), old_ptr);
+        assert!(v.as_ptr().is_aligned());
+        assert_eq!(
+            &*v,
+            &[Align8(1), Align8(2), Align8(3), Align8(4), Align8(5)]
+        );
+        assert_eq!(*blocker, Align8(0));
+    }
+
+    #[test]
+    fn test_grow_moved() {
+        let heap: VirtHeap<u64, 8> = VirtHeap::new();
+        let mut storage = &heap;
+
+        let handle = storage.allocate::<[u64]>(2).unwrap();
+        let (handle, moved) = unsafe { storage.try_grow_moved(handle, 3) }.unwrap();
+        assert!(!moved);
+
+        // Block the space directly after, so the next grow has to move
+        let blocker = storage.allocate::<u64>(()).unwrap();
+        let (handle, moved) = unsafe { storage.try_grow_moved(handle, 4) }.unwrap();
+        assert!(moved);
+        assert_eq!(handle.offset(), 4);
+
+        unsafe { storage.deallocate(blocker) };
+        unsafe { storage.deallocate(handle) };
+    }
+
+    #[test]
+    fn test_is_allocated() {
+        let heap: VirtHeap<u64, 4> = VirtHeap::new();
+        let mut storage = &heap;
+
+        let a = storage.allocate::<[u64]>(2).unwrap();
+        let b = storage.allocate::<u64>(()).unwrap();
+        assert!(storage.is_allocated(a));
+        assert!(storage.is_allocated(b));
+
+        unsafe { storage.deallocate(a) };
+        assert!(!storage.is_allocated(a));
+        // Handles reaching past the end of the heap are never allocated
+        assert!(!storage.is_allocated(OffsetMetaHandle::<[u64]>::from_offset_meta(2, 3)));
+        unsafe { storage.deallocate(b) };
+        assert!(!storage.is_allocated(b));
+    }
+
+    #[test]
+    fn test_small_items() {
+        let heap: VirtHeap<u64, 2> = VirtHeap::new();
+
+        // Items smaller than a block still take up a whole block
+        let b1 = Box::new_in(1u8, &heap);
+        let b2 = Box::new_in(2u32, &heap);
+        assert!(Box::try_new_in(3u8, &heap).is_err());
+        assert_eq!((*b1, *b2), (1, 2));
+    }
+
+    #[test]
+    fn test_reuse_freed() {
+        let heap: VirtHeap<u64, 8> = VirtHeap::new();
+
+        let b1 = Box::new_in(1u64, &heap);
+        let b2 = Box::new_in(2u64, &heap);
+        let b3 = Box::new_in([3u64, 4], &heap);
+        let ptr3 = b3.as_ptr();
+        let _b4 = Box::new_in(5u64, &heap);
+
+        drop(b1);
+        drop(b3);
+
+        // The most recently freed blocks are reused, even though earlier ones are free
+        let b5 = Box::new_in(6u64, &heap);
+        assert_eq!(&*b5 as *const u64, ptr3);
+        // The rest of the freed run is reused next
+        let b6 = Box::new_in(7u64, &heap);
+        assert_eq!(&*b6 as *const u64, ptr3.wrapping_add(1));
+        // Once it's used up, the heap is searched from the start
+        let b7 = Box::new_in(8u64, &heap);
+        assert_eq!(&*b7 as *const u64, (&*b2 as *const u64).wrapping_sub(1));
+    }
+
+    #[test]
+    fn test_same_allocation() {
+        type Heap<'a> = &'a VirtHeap<u64, 8>;
+        let heap: VirtHeap<u64, 8> = VirtHeap::new();
+        let mut storage = &heap;
+
+        let a = storage.allocate::<[u64]>(2).unwrap();
+        let b = storage.allocate::<u64>(()).unwrap();
+        let first = Heap::cast::<[u64], u64>(a);
+        let short = Heap::from_raw_parts::<[u64]>(Heap::cast::<[u64], ()>(a), 1);
+
+        // Differing metadata makes the handles unequal, but they're still the same allocation
+        assert_ne!(a, short);
+        assert!(Heap::same_allocation(a, short));
+        assert!(Heap::same_allocation(a, first));
+        assert!(!Heap::same_allocation(a, b));
+    }
+
+    #[test]
+    fn test_panic_safety() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        #[derive(Debug, PartialEq)]
+        struct PanicClone(u64);
+
+        impl Clone for PanicClone {
+            fn clone(&self) -> Self {
+                assert_ne!(self.0, 3, "Cloning 3");
+                PanicClone(self.0)
+            }
+        }
+
+        let heap: VirtHeap<u64, 8> = VirtHeap::new();
+
+        let mut v = Vec::new_in(&heap);
+        v.push(PanicClone(1));
+        let b = Box::new_in(0u64, &heap);
+
+        // Growing relocates the vector past the box, then cloning panics partway through
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            v.extend_from_slice(&[PanicClone(2), PanicClone(3), PanicClone(4)]);
+        }));
+        assert!(res.is_err());
+        assert_eq!(&*v, &[PanicClone(1), PanicClone(2)]);
+
+        // Only the blocks not in use are free
+        let b2 = Box::new_in([0u64; 2], &heap);
+        let b3 = Box::new_in(0u64, &heap);
+        assert!(Box::try_new_in(0u64, &heap).is_err());
+
+        // And everything is freed once dropped
+        drop((v, b, b2, b3));
+        let _all = Box::new_in([0u64; 8], &heap);
+    }
+
+    #[test]
+    fn test_from_backing() {
+        let heap = VirtHeap::<u64, 4>::from_backing([MaybeUninit::new(u64::MAX); 4]);
+
+        let b = Box::new_in([1u64, 2], &heap);
+        assert_eq!(*b, [1, 2]);
+        let b2 = Box::new_in([3u64, 4], &heap);
+        assert_eq!(*b2, [3, 4]);
+        assert!(Box::try_new_in(5u64, &heap).is_err());
     }
 
     #[test]
@@ -517,9 +877,81 @@ mod tests {
         assert_eq!(*v1, -1);
     }
 
+    #[test]
+    fn test_leak_shared_backing() {
+        let heap: VirtHeap<u32, 16> = VirtHeap::new();
+        let (a, b) = (&heap, &heap);
+
+        let _pad = Box::new_in(0u8, a);
+        let leaked = Box::leak(Box::new_in([1u16, 2, 3], a)) as *mut [u16];
+
+        // Any reference to the same heap is unleak-compatible
+        let handle = unsafe { b.unleak_ptr(leaked) };
+        assert_eq!(handle.offset(), 1);
+        let v = unsafe { Box::from_parts(b, handle) };
+        assert_eq!(*v, [1, 2, 3]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic = "doesn't point into this VirtHeap's backing"]
+    fn test_leak_wrong_heap() {
+        let heap1: VirtHeap<u32, 4> = VirtHeap::new();
+        let heap2: VirtHeap<u32, 4> = VirtHeap::new();
+
+        let leaked = Box::leak(Box::new_in(1u32, &heap1));
+        let _ = unsafe { (&heap2).unleak_ptr(leaked) };
+    }
+
     #[test]
     fn test_non_static() {
         let heap: VirtHeap<u32, 4> = VirtHeap::new();
         Box::new_in(1, &heap);
     }
+
+    #[test]
+    fn test_send() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<Box<u32, &VirtHeap<usize, 16>>>();
+        assert_send_sync::<Vec<u32, &VirtHeap<usize, 16>>>();
+        assert_send_sync::<crate::collections::LinkedList<u32, &VirtHeap<usize, 16>>>();
+
+        let heap = VirtHeap::<usize, 16>::new();
+
+        let b = Box::new_in(1, &heap);
+        let mut v = Vec::new_in(&heap);
+        v.extend([1, 2]);
+
+        std::thread::scope(|s| {
+            s.spawn(move || {
+                assert_eq!(*b, 1);
+                v.push(3);
+                assert_eq!(&*v, &[1, 2, 3]);
+            });
+        });
+
+        // Everything was freed on the other thread
+        Box::new_in([0usize; 16], &heap);
+    }
+
+    #[test]
+    fn test_contended() {
+        let heap = VirtHeap::<u64, 64>::new();
+
+        std::thread::scope(|s| {
+            for i in 0..8 {
+                let heap = &heap;
+                s.spawn(move || {
+                    for j in 0..100 {
+                        let b = Box::new_in([i, j], heap);
+                        assert_eq!(*b, [i, j]);
+                    }
+                });
+            }
+        });
+
+        // Every allocation was freed
+        Box::new_in([0u64; 64], &heap);
+    }
 }
@@ -12,8 +12,13 @@ use crate::handles::{Handle, OffsetMetaHandle};
 use crate::{error, utils};
 
 /// Inline multi-element storage implementation
+///
+/// By default, allocations take the lowest free slot. See [`MultiInline::round_robin`] to
+/// assign slots in rotation instead.
 pub struct MultiInline<S, const N: usize> {
     used: [bool; N],
+    /// Slot to start searching for free space from, if allocating round-robin
+    cursor: Option<usize>,
     storage: [UnsafeCell<MaybeUninit<S>>; N],
 }
 
@@ -22,9 +27,18 @@ impl<S, const N: usize> MultiInline<S, N> {
     pub fn new() -> MultiInline<S, N> {
         MultiInline {
             used: [false; N],
+            cursor: None,
             storage: <[(); N]>::map([(); N], |_| UnsafeCell::new(MaybeUninit::uninit())),
         }
     }
+
+    /// Make this storage assign slots round-robin, searching for a free slot after the most
+    /// recently allocated one instead of from the lowest index. This spreads reuse across all
+    /// slots, rather than repeatedly reusing the first ones.
+    pub fn round_robin(mut self) -> MultiInline<S, N> {
+        self.cursor = Some(0);
+        self
+    }
 }
 
 // SAFETY: Internal locks and check ensure memory safety
@@ -35,6 +49,10 @@ where
     type Handle<T: ?Sized + Pointee> = OffsetMetaHandle<T>;
 
     unsafe fn get<T: ?Sized>(&self, handle: Self::Handle<T>) -> NonNull<T> {
+        utils::strict_assert!(
+            self.is_allocated(handle),
+            "MultiInline handle doesn't point to an allocation"
+        );
         let ptr: NonNull<()> = NonNull::new(self.storage[handle.offset()].get())
             .unwrap()
             .cast();
@@ -65,6 +83,14 @@ where
         handle.coerce()
     }
 
+    fn will_fit_hint<T: ?Sized + Pointee>(&self, meta: T::Metadata) -> Option<bool> {
+        Some(self.will_fit::<T>(meta))
+    }
+
+    fn max_range_hint<T>(&self) -> usize {
+        self.max_range::<T>()
+    }
+
     fn allocate_single<T: ?Sized + Pointee>(
         &mut self,
         meta: T::Metadata,
@@ -122,21 +148,27 @@ where
     ) -> error::Result<Self::Handle<T>> {
         utils::validate_layout::<T, S>(meta)?;
 
-        // Find first unused storage
-        let pos = self
-            .used
-            .iter()
-            .position(|i| !*i)
-            .ok_or(StorageError::NoSlots)?;
+        let pos = utils::find_free_slot(&self.used, self.cursor).ok_or(StorageError::NoSlots)?;
 
         self.used[pos] = true;
+        if let Some(cursor) = &mut self.cursor {
+            *cursor = (pos + 1) % N;
+        }
 
         Ok(OffsetMetaHandle::from_offset_meta(pos, meta))
     }
 
     unsafe fn deallocate<T: ?Sized + Pointee>(&mut self, handle: Self::Handle<T>) {
+        utils::strict_assert!(
+            self.used[handle.offset()],
+            "MultiInline handle was already deallocated"
+        );
         self.used[handle.offset()] = false;
     }
+
+    fn is_allocated<T: ?Sized + Pointee>(&self, handle: Self::Handle<T>) -> bool {
+        self.used.get(handle.offset()) == Some(&true)
+    }
 }
 
 impl<S, const N: usize> ExactSizeStorage for MultiInline<S, N>
@@ -144,8 +176,7 @@ where
     S: StorageSafe,
 {
     fn will_fit<T: ?Sized + Pointee>(&self, meta: T::Metadata) -> bool {
-        let layout = utils::layout_of::<T>(meta);
-        mem::size_of::<S>() >= layout.size()
+        utils::layout_of::<T>(meta).is_ok_and(|layout| mem::size_of::<S>() >= layout.size())
     }
 
     fn max_range<T>(&self) -> usize {
@@ -163,7 +194,10 @@ impl<S, const N: usize> fmt::Debug for MultiInline<S, N> {
 impl<S, const N: usize> Clone for MultiInline<S, N> {
     fn clone(&self) -> Self {
         // 'cloning' doesn't preserve handles, it just gives you a new storage
-        MultiInline::new()
+        MultiInline {
+            cursor: self.cursor.map(|_| 0),
+            ..MultiInline::new()
+        }
     }
 }
 
@@ -189,4 +223,48 @@ mod tests {
         assert_eq!(list.get(1), Some(&2));
         assert_eq!(list.get(3), None);
     }
+
+    #[test]
+    fn test_round_robin() {
+        let mut first_free = MultiInline::<u32, 3>::new();
+        let a = first_free.allocate::<u32>(()).unwrap();
+        first_free.allocate::<u32>(()).unwrap();
+        unsafe { first_free.deallocate(a) };
+        assert_eq!(first_free.allocate::<u32>(()).unwrap().offset(), 0);
+
+        let mut round_robin = MultiInline::<u32, 3>::new().round_robin();
+        let a = round_robin.allocate::<u32>(()).unwrap();
+        round_robin.allocate::<u32>(()).unwrap();
+        unsafe { round_robin.deallocate(a) };
+        assert_eq!(round_robin.allocate::<u32>(()).unwrap().offset(), 2);
+        // Wraps around to the start once it reaches the end
+        assert_eq!(round_robin.allocate::<u32>(()).unwrap().offset(), 0);
+        assert!(round_robin.allocate::<u32>(()).is_err());
+    }
+
+    #[test]
+    fn test_is_allocated() {
+        let mut storage = MultiInline::<u32, 2>::new();
+        let a = storage.allocate::<u32>(()).unwrap();
+        let b = storage.allocate::<u32>(()).unwrap();
+        assert!(storage.is_allocated(a));
+
+        unsafe { storage.deallocate(a) };
+        assert!(!storage.is_allocated(a));
+        assert!(storage.is_allocated(b));
+        // Handles past the end are never allocated
+        assert!(!storage.is_allocated(OffsetMetaHandle::<u32>::from_offset_meta(2, ())));
+        unsafe { storage.deallocate(b) };
+    }
+
+    #[test]
+    #[cfg(all(feature = "strict", debug_assertions))]
+    #[should_panic = "already deallocated"]
+    fn test_strict_double_free() {
+        let mut storage = MultiInline::<usize, 2>::new();
+        let handle = storage.allocate::<usize>(()).unwrap();
+
+        unsafe { storage.deallocate(handle) };
+        unsafe { storage.deallocate(handle) };
+    }
 }
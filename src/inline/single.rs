@@ -61,6 +61,14 @@ where
         handle.coerce()
     }
 
+    fn will_fit_hint<T: ?Sized + Pointee>(&self, meta: T::Metadata) -> Option<bool> {
+        Some(self.will_fit::<T>(meta))
+    }
+
+    fn max_range_hint<T>(&self) -> usize {
+        self.max_range::<T>()
+    }
+
     fn allocate_single<T: ?Sized + Pointee>(
         &mut self,
         meta: T::Metadata,
@@ -104,8 +112,7 @@ where
     S: StorageSafe,
 {
     fn will_fit<T: ?Sized + Pointee>(&self, meta: T::Metadata) -> bool {
-        let layout = utils::layout_of::<T>(meta);
-        mem::size_of::<S>() >= layout.size()
+        utils::layout_of::<T>(meta).is_ok_and(|layout| mem::size_of::<S>() >= layout.size())
     }
 
     fn max_range<T>(&self) -> usize {
@@ -199,4 +206,11 @@ mod tests {
 
         assert_eq!(*b, ());
     }
+
+    #[test]
+    fn test_oversized_meta() {
+        let mut storage = SingleInline::<[usize; 4]>::new();
+        assert!(storage.allocate_single::<[u64]>(usize::MAX / 2).is_err());
+        assert!(!storage.will_fit::<[u64]>(usize::MAX / 2));
+    }
 }
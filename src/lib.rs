@@ -5,6 +5,8 @@
 #![feature(ptr_metadata)]
 // Needed to get a layout from just a type and metadata in `utils::layout_of`
 #![feature(layout_for_ptr)]
+// Needed to check slice metadata for overflow in `utils::layout_of`
+#![feature(min_specialization)]
 // Needed to implement custom unsizing and coercion
 #![cfg_attr(feature = "unsize", feature(unsize, coerce_unsized))]
 #![warn(
@@ -29,6 +31,8 @@
     clippy::cast_possible_truncation,
     clippy::cast_possible_wrap
 )]
+// Tests exercise unsafe storage APIs directly, and don't document each call
+#![cfg_attr(test, allow(clippy::undocumented_unsafe_blocks))]
 #![cfg_attr(not(feature = "std"), no_std)]
 #![cfg_attr(feature = "alloc", feature(allocator_api))]
 
@@ -47,6 +51,8 @@ pub mod handles;
 
 #[cfg(feature = "alloc")]
 pub mod alloc;
+#[cfg(feature = "cell")]
+pub mod cell;
 #[cfg(feature = "debug")]
 pub mod debug;
 #[cfg(feature = "fallback")]
@@ -60,9 +66,16 @@ pub mod statics;
 
 // Collection implementations
 
+#[cfg(feature = "box")]
+pub mod borrow;
 #[cfg(feature = "box")]
 pub mod boxed;
-#[cfg(any(feature = "vec", feature = "linked"))]
+#[cfg(any(
+    feature = "vec",
+    feature = "deque",
+    feature = "linked",
+    feature = "hash"
+))]
 pub mod collections;
 #[cfg(feature = "rc")]
 pub mod rc;
@@ -1,16 +1,22 @@
 //! A storage-based implementation of [`std::rc`]
 
-use crate::base::{ClonesafeStorage, Storage};
+use crate::base::{ClonesafeStorage, FromLeakedStorage, LeaksafeStorage, Storage};
+#[cfg(feature = "box")]
+use crate::boxed::Box;
+#[cfg(feature = "box")]
+use crate::handles::Handle;
+use core::alloc::Layout;
 use core::borrow::Borrow;
 use core::cell::Cell;
+use core::fmt;
 use core::marker::PhantomData;
 #[cfg(feature = "unsize")]
 use core::marker::Unsize;
-#[cfg(feature = "unsize")]
-use core::mem::ManuallyDrop;
+use core::mem::{ManuallyDrop, MaybeUninit};
 #[cfg(feature = "unsize")]
 use core::ops::CoerceUnsized;
 use core::ops::Deref;
+use core::{mem, ptr};
 
 #[repr(C)]
 #[derive(Debug)]
@@ -50,6 +56,21 @@ impl<T: ?Sized> RcBox<T> {
     }
 }
 
+impl<T: ?Sized> RcBox<T> {
+    /// Offset of `value` from the start of the box, given a pointer to the value
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be valid to call [`mem::align_of_val_raw`] on
+    unsafe fn value_offset(ptr: *const T) -> usize {
+        // SAFETY: Our safety requirements allow this
+        let align = unsafe { mem::align_of_val_raw(ptr) };
+        let value = Layout::from_size_align(0, align).unwrap();
+        // As RcBox is `repr(C)`, the value follows the counts padded to its alignment
+        Layout::new::<RcBox<()>>().extend(value).unwrap().1
+    }
+}
+
 impl<T> RcBox<T> {
     fn new(value: T) -> RcBox<T> {
         RcBox {
@@ -63,7 +84,7 @@ impl<T> RcBox<T> {
 #[cfg(feature = "unsize")]
 impl<T, U> CoerceUnsized<RcBox<U>> for RcBox<T>
 where
-    T: ?Sized + CoerceUnsized<U>,
+    T: CoerceUnsized<U>,
     U: ?Sized,
 {
 }
@@ -92,15 +113,78 @@ impl<T: ?Sized, S: Storage + ClonesafeStorage> Rc<T, S> {
         }
     }
 
+    /// Get a mutable reference to the inner value, if there are no other [`Rc`] or [`Weak`]
+    /// references to the same allocation
+    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+        let inner = this.inner();
+        if inner.strong() != 1 || inner.weak() != 1 {
+            return None;
+        }
+        // SAFETY: We're the only reference to the allocation, and hold it mutably
+        let inner = unsafe { this.storage.get(this.handle) };
+        // SAFETY: The pointer is valid, so projecting to a field of it is safe
+        Some(unsafe { &mut *ptr::addr_of_mut!((*inner.as_ptr()).value) })
+    }
+
+    /// Check whether two [`Rc`]s point to the same allocation, in the manner of
+    /// [`Storage::same_allocation`]
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        S::same_allocation(this.handle, other.handle)
+    }
+
     /// Get a [`Weak`] from this [`Rc`]
     pub fn downgrade(this: &Self) -> Weak<T, S> {
         this.inner().inc_weak();
         Weak {
             handle: this.handle,
             storage: this.storage.clone(),
+            phantom: PhantomData,
         }
     }
 
+    /// Consume this [`Rc`], returning a pointer to the contained value. The strong reference is
+    /// leaked, and can be regained with [`Rc::from_raw`].
+    pub fn into_raw(this: Self) -> *const T
+    where
+        S: LeaksafeStorage,
+    {
+        let mut this = ManuallyDrop::new(this);
+        // SAFETY: While Rc lives the inner handle is valid
+        let inner = unsafe { this.storage.get(this.handle) };
+        // SAFETY: The pointer is valid, so projecting to a field of it is safe
+        let out = unsafe { ptr::addr_of!((*inner.as_ptr()).value) };
+        // SAFETY: `this` is never used again, and the storage is leaksafe, so the pointer remains
+        //         valid after it's dropped
+        unsafe { ptr::drop_in_place(&mut this.storage) };
+        out
+    }
+
+    /// Reconstruct an [`Rc`] from a pointer returned by [`Rc::into_raw`], without changing the
+    /// reference count.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must have come from [`Rc::into_raw`] with the same `T`, and be
+    /// unleak-compatible for the default instance of the storage type. See
+    /// [`FromLeakedStorage::unleak_ptr`] for the exact definition of unleak-compatible. Each
+    /// call to this reclaims one strong reference, so it can't be called more times than
+    /// `into_raw` for the same allocation.
+    pub unsafe fn from_raw(ptr: *const T) -> Rc<T, S>
+    where
+        S: FromLeakedStorage + Default,
+    {
+        // SAFETY: The pointer came from `into_raw`, so points to a valid value
+        let offset = unsafe { RcBox::value_offset(ptr) };
+        // SAFETY: The value is inside an RcBox, `offset` bytes from its start
+        let inner = unsafe { ptr.byte_sub(offset) } as *mut RcBox<T>;
+
+        let storage = S::default();
+        // SAFETY: Our safety requirements allow this
+        let handle = unsafe { storage.unleak_ptr(inner) };
+        // SAFETY: The handle came from an Rc, and we reclaim the strong reference it leaked
+        unsafe { Rc::from_inner(handle, storage) }
+    }
+
     /// Perform an unsizing operation on `self`. A temporary solution to limitations with
     /// manual unsizing.
     #[cfg(feature = "unsize")]
@@ -136,6 +220,93 @@ impl<T, S: Storage + ClonesafeStorage> Rc<T, S> {
     }
 }
 
+impl<T, S: Storage + ClonesafeStorage> Rc<MaybeUninit<T>, S> {
+    /// Create a new [`Rc`] with uninitialized contents in some existing storage. The reference
+    /// counts are initialized, so the value can be written through [`Rc::get_mut`] before
+    /// calling [`Rc::assume_init`].
+    ///
+    /// # Panics
+    ///
+    /// If the storage fails to allocate enough space for the type and associated information
+    pub fn new_uninit_in(mut storage: S) -> Rc<MaybeUninit<T>, S> {
+        let handle = storage
+            .allocate_single::<RcBox<MaybeUninit<T>>>(())
+            .unwrap_or_else(|_| panic!("Couldn't allocate RcBox"));
+        // SAFETY: We just allocated this handle with the provided storage
+        let inner = unsafe { storage.get(handle) }.as_ptr();
+        // SAFETY: The pointer is valid for writes, and only the counts need initializing as the
+        //         value is allowed to be uninitialized
+        unsafe {
+            ptr::addr_of_mut!((*inner).strong).write(Cell::new(1));
+            ptr::addr_of_mut!((*inner).weak).write(Cell::new(1));
+        }
+        // SAFETY: We just allocated this handle with the provided storage
+        unsafe { Self::from_inner(handle, storage) }
+    }
+
+    /// Convert into an `Rc<T, S>`, assuming the contents have been initialized.
+    ///
+    /// # Safety
+    ///
+    /// The contained value must be a valid, initialized instance of `T`. See
+    /// [`MaybeUninit::assume_init`].
+    pub unsafe fn assume_init(self) -> Rc<T, S> {
+        let this = ManuallyDrop::new(self);
+        let handle = S::cast::<_, RcBox<T>>(this.handle);
+        // SAFETY: `this` is never used again, so the storage is moved out exactly once
+        let storage = unsafe { ptr::read(&this.storage) };
+        // SAFETY: `RcBox<MaybeUninit<T>>` has the same layout as `RcBox<T>`, and the caller
+        //         guarantees the value is initialized. The reference count is carried over.
+        unsafe { Rc::from_inner(handle, storage) }
+    }
+}
+
+impl<T, S: Storage + ClonesafeStorage + Default> Rc<MaybeUninit<T>, S> {
+    /// Create a new [`Rc`] with uninitialized contents
+    ///
+    /// # Panics
+    ///
+    /// If the storage fails to allocate enough space for the type and associated information
+    pub fn new_uninit() -> Rc<MaybeUninit<T>, S> {
+        Self::new_uninit_in(S::default())
+    }
+}
+
+impl<T, S: Storage + ClonesafeStorage> Rc<T, S> {
+    /// Return the inner value, if this [`Rc`] has exactly one strong reference. Otherwise, the
+    /// same [`Rc`] is returned back.
+    ///
+    /// Any outstanding [`Weak`] references will fail to upgrade after this succeeds, and the
+    /// allocation is freed once the last of them is dropped.
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        if this.inner().strong() != 1 {
+            return Err(this);
+        }
+
+        let mut this = ManuallyDrop::new(this);
+
+        // SAFETY: Strong count is 1, so we're the only observer of the value. It's never touched
+        //         again once the strong count hits 0
+        let value = unsafe { ptr::read(&this.inner().value) };
+
+        let inner = this.inner();
+        inner.dec_strong();
+        // Release the weak reference collectively held by the strong references
+        inner.dec_weak();
+
+        if inner.weak() == 0 {
+            let handle = this.handle;
+            // SAFETY: Both strong and weak count are 0, so we're the last RcBox observer
+            unsafe { this.storage.deallocate_single(handle) }
+        }
+
+        // SAFETY: `this` is never used again, so the storage won't be observed after this
+        unsafe { ptr::drop_in_place(&mut this.storage) };
+
+        Ok(value)
+    }
+}
+
 impl<T, S: Storage + ClonesafeStorage + Default> Rc<T, S> {
     /// Create a new [`Rc`] from the provided value
     pub fn new(value: T) -> Rc<T, S> {
@@ -143,12 +314,62 @@ impl<T, S: Storage + ClonesafeStorage + Default> Rc<T, S> {
     }
 }
 
+/// Move a boxed value into a new [`Rc`] in the same storage.
+///
+/// The box's allocation can't be reused, as it has no space for the reference counts, so this
+/// allocates a new one, copies the value into it, then frees the box's backing. The cost is one
+/// allocation and a copy of the value, the same as `std`.
+///
+/// # Panics
+///
+/// If the storage fails to allocate space for the value and associated information
+#[cfg(feature = "box")]
+impl<T: ?Sized, S: Storage + ClonesafeStorage> From<Box<T, S>> for Rc<T, S> {
+    fn from(value: Box<T, S>) -> Rc<T, S> {
+        let (mut storage, handle) = value.into_parts();
+        // `RcBox<T>` has the same metadata as `T`, but that can't be named for a generic `T`, so
+        // it's recovered from a pointer cast instead
+        let meta = ptr::metadata(
+            ptr::from_raw_parts::<T>(ptr::null::<()>(), handle.metadata()) as *const RcBox<T>,
+        );
+        let rc_handle = match storage.allocate_single::<RcBox<T>>(meta) {
+            Ok(rc_handle) => rc_handle,
+            Err(_) => {
+                // SAFETY: The parts came from a box, and haven't been used since
+                drop(unsafe { Box::from_parts(storage, handle) });
+                panic!("Couldn't allocate RcBox")
+            }
+        };
+
+        // SAFETY: Both handles are valid, as the box's handle is live and allocate just
+        //         succeeded
+        let (src, inner) = unsafe { (storage.get(handle), storage.get(rc_handle)) };
+        let inner = inner.as_ptr();
+        // SAFETY: The new allocation is valid for writes. The value is moved bitwise out of the
+        //         box, which is deallocated without dropping it, so it's only owned once.
+        unsafe {
+            ptr::addr_of_mut!((*inner).strong).write(Cell::new(1));
+            ptr::addr_of_mut!((*inner).weak).write(Cell::new(1));
+            let dst = ptr::addr_of_mut!((*inner).value);
+            ptr::copy_nonoverlapping(
+                src.as_ptr().cast::<u8>(),
+                dst.cast::<u8>(),
+                mem::size_of_val_raw(src.as_ptr()),
+            );
+            storage.deallocate_single(handle);
+        }
+
+        // SAFETY: We just allocated and initialized this handle with the provided storage
+        unsafe { Rc::from_inner(rc_handle, storage) }
+    }
+}
+
 impl<T: ?Sized, S: Storage + ClonesafeStorage> Drop for Rc<T, S> {
     fn drop(&mut self) {
         self.inner().dec_strong();
         if self.inner().strong() == 0 {
             // SAFETY: This is drop, and strong count is 0, so we're guaranteed last value observer
-            unsafe { core::ptr::drop_in_place(&mut self.storage.get(self.handle).as_mut().value) };
+            unsafe { ptr::drop_in_place(&mut self.storage.get(self.handle).as_mut().value) };
 
             self.inner().dec_weak();
 
@@ -189,6 +410,25 @@ impl<T: ?Sized, S: Storage + ClonesafeStorage> Borrow<T> for Rc<T, S> {
     }
 }
 
+impl<T: ?Sized + fmt::Debug, S: Storage + ClonesafeStorage> fmt::Debug for Rc<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display, S: Storage + ClonesafeStorage> fmt::Display for Rc<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized, S: Storage + ClonesafeStorage> fmt::Pointer for Rc<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ptr: *const T = &**self;
+        fmt::Pointer::fmt(&ptr, f)
+    }
+}
+
 #[cfg(feature = "unsize")]
 impl<T, U, S> CoerceUnsized<Rc<U, S>> for Rc<T, S>
 where
@@ -228,6 +468,7 @@ impl WeakInner<'_> {
 pub struct Weak<T: ?Sized, S: Storage + ClonesafeStorage> {
     handle: S::Handle<RcBox<T>>,
     storage: S,
+    phantom: PhantomData<*mut ()>,
 }
 
 impl<T: ?Sized, S: Storage + ClonesafeStorage> Weak<T, S> {
@@ -257,6 +498,13 @@ impl<T: ?Sized, S: Storage + ClonesafeStorage> Weak<T, S> {
     }
 }
 
+impl<T: ?Sized, S: Storage + ClonesafeStorage> fmt::Debug for Weak<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The value may already be dropped, so it's never printed
+        write!(f, "(Weak)")
+    }
+}
+
 impl<T: ?Sized, S: Storage + ClonesafeStorage> Drop for Weak<T, S> {
     fn drop(&mut self) {
         let inner = self.inner();
@@ -272,7 +520,9 @@ impl<T: ?Sized, S: Storage + ClonesafeStorage> Drop for Weak<T, S> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::alloc::GlobalAlloc;
     use crate::heap::VirtHeap;
+    use crate::utils;
 
     #[test]
     fn test_rc() {
@@ -293,6 +543,179 @@ mod tests {
         drop(rc2);
         drop(rc3);
 
-        assert!(matches!(weak1.upgrade(), None));
+        assert!(weak1.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_ptr_eq() {
+        let heap: VirtHeap<u64, 16> = VirtHeap::new();
+
+        let rc1 = Rc::new_in(1, &heap);
+        let rc2 = Rc::clone(&rc1);
+        let rc3 = Rc::new_in(1, &heap);
+
+        assert!(Rc::ptr_eq(&rc1, &rc2));
+        assert!(!Rc::ptr_eq(&rc1, &rc3));
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let heap: VirtHeap<u64, 16> = VirtHeap::new();
+
+        let mut rc1 = Rc::new_in(1, &heap);
+        *Rc::get_mut(&mut rc1).unwrap() = 2;
+
+        let rc2 = Rc::clone(&rc1);
+        assert!(Rc::get_mut(&mut rc1).is_none());
+        drop(rc2);
+
+        let weak = Rc::downgrade(&rc1);
+        assert!(Rc::get_mut(&mut rc1).is_none());
+        drop(weak);
+
+        assert_eq!(Rc::get_mut(&mut rc1), Some(&mut 2));
+    }
+
+    #[test]
+    fn test_new_uninit() {
+        let heap: VirtHeap<u64, 16> = VirtHeap::new();
+
+        let mut rc = Rc::<MaybeUninit<[u64; 4]>, _>::new_uninit_in(&heap);
+        Rc::get_mut(&mut rc).unwrap().write([1, 2, 3, 4]);
+        let rc = unsafe { rc.assume_init() };
+        let weak = Rc::downgrade(&rc);
+
+        assert_eq!(*rc, [1, 2, 3, 4]);
+        assert_eq!(Rc::try_unwrap(rc).ok(), Some([1, 2, 3, 4]));
+        assert!(weak.upgrade().is_none());
+
+        let mut rc = Rc::<MaybeUninit<u32>, GlobalAlloc>::new_uninit();
+        Rc::get_mut(&mut rc).unwrap().write(5);
+        assert_eq!(*unsafe { rc.assume_init() }, 5);
+    }
+
+    #[test]
+    fn test_from_box() {
+        let heap: VirtHeap<u64, 16> = VirtHeap::new();
+
+        let b = Box::new_in([1u64, 2, 3], &heap);
+        let rc = Rc::from(b);
+        assert_eq!(*rc, [1, 2, 3]);
+        // The box's backing was freed, so its space can be reused
+        let _freed = Box::new_in([0u64; 3], &heap);
+        let _rest = Box::new_in([0u64; 8], &heap);
+
+        let rc2 = Rc::clone(&rc);
+        drop(rc);
+        assert_eq!(*rc2, [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_from_box_fail() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let heap: VirtHeap<u64, 2> = VirtHeap::new();
+        let item = std::rc::Rc::new(());
+
+        // The box fits, but the counts don't fit alongside it
+        let b = Box::new_in(std::rc::Rc::clone(&item), &heap);
+        let res = panic::catch_unwind(AssertUnwindSafe(|| Rc::from(b)));
+        assert!(res.is_err());
+
+        // The value was dropped and the box's backing freed
+        assert_eq!(std::rc::Rc::strong_count(&item), 1);
+        let _all = Box::new_in([0u64; 2], &heap);
+    }
+
+    #[cfg(feature = "unsize")]
+    #[test]
+    fn test_from_box_unsized() {
+        let b = Box::<_, GlobalAlloc>::new([std::string::String::from("a"), "b".into()]);
+        let rc = Rc::from(b.coerce::<[std::string::String]>());
+
+        assert_eq!(&*rc, &["a", "b"]);
+        assert_eq!(rc.len(), 2);
+    }
+
+    #[test]
+    fn test_try_unwrap() {
+        let heap: VirtHeap<u64, 16> = VirtHeap::new();
+
+        let rc1 = Rc::new_in(1, &heap);
+        let rc2 = Rc::clone(&rc1);
+
+        let rc1 = Rc::try_unwrap(rc1).unwrap_err();
+        drop(rc2);
+        assert_eq!(Rc::try_unwrap(rc1).ok(), Some(1));
+
+        let rc3 = Rc::new_in(2, &heap);
+        let weak = Rc::downgrade(&rc3);
+        assert_eq!(Rc::try_unwrap(rc3).ok(), Some(2));
+        assert!(weak.upgrade().is_none());
+        drop(weak);
+
+        // All allocations have been freed, so the heap can be filled again
+        let _full = crate::boxed::Box::new_in([0u64; 16], &heap);
+    }
+
+    #[test]
+    fn test_fmt() {
+        let heap: VirtHeap<u64, 16> = VirtHeap::new();
+
+        let rc = Rc::new_in("a", &heap);
+        let weak = Rc::downgrade(&rc);
+        assert_eq!(format!("{:?}", rc), "\"a\"");
+        assert_eq!(format!("{}", rc), "a");
+        assert_eq!(format!("{:?}", weak), "(Weak)");
+        assert_eq!(format!("{:p}", rc), format!("{:p}", &*rc));
+        drop(rc);
+        assert_eq!(format!("{:?}", weak), "(Weak)");
+    }
+
+    #[test]
+    fn test_raw() {
+        #[repr(align(32))]
+        #[derive(Debug, PartialEq)]
+        struct Aligned(u8);
+
+        let rc1 = Rc::<_, GlobalAlloc>::new(Aligned(1));
+        let weak = Rc::downgrade(&rc1);
+
+        let ptr = Rc::into_raw(rc1);
+        assert!(ptr.is_aligned());
+        assert_eq!(unsafe { &*ptr }, &Aligned(1));
+        assert!(weak.upgrade().is_some());
+
+        let rc1 = unsafe { Rc::<_, GlobalAlloc>::from_raw(ptr) };
+        assert_eq!(*rc1, Aligned(1));
+        drop(rc1);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[cfg(feature = "unsize")]
+    #[test]
+    fn test_raw_unsized() {
+        let rc1 = Rc::<_, GlobalAlloc>::new([1u16, 2, 3]).coerce::<[u16]>();
+
+        let ptr = Rc::into_raw(rc1);
+        assert_eq!(unsafe { &*ptr }, &[1, 2, 3]);
+
+        let rc1 = unsafe { Rc::<[u16], GlobalAlloc>::from_raw(ptr) };
+        assert_eq!(&*rc1, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_oversized_meta() {
+        let layout = utils::layout_of::<RcBox<[u64]>>(3).unwrap();
+        assert_eq!(layout.size(), 40);
+
+        let max = (isize::MAX as usize - 16) / 8;
+        assert!(utils::layout_of::<RcBox<[u64]>>(max).is_ok());
+        assert!(utils::layout_of::<RcBox<[u64]>>(max + 1).is_err());
+
+        let mut storage = GlobalAlloc::default();
+        assert!(storage
+            .allocate_single::<RcBox<[u64]>>(usize::MAX / 8)
+            .is_err());
     }
 }
@@ -41,6 +41,18 @@ impl<S> StorageCell<S> {
             .unwrap_or_else(|| panic!("StorageCell already claimed by existing storage"))
     }
 
+    /// Forcibly release this `StorageCell`, whether or not it is currently claimed. This allows
+    /// recovering a cell whose storage was leaked, such as via [`mem::forget`](core::mem::forget),
+    /// which would otherwise stay claimed forever.
+    ///
+    /// # Safety
+    ///
+    /// No storage created from this cell may be live, or be used again after this call. If one
+    /// is, a later claim would alias its backing memory.
+    pub unsafe fn force_release(&'static self) {
+        self.1.store(false, Ordering::SeqCst);
+    }
+
     pub(crate) fn release(&self) {
         assert!(self.inner_try_release(), "Couldn't release StorageCell");
     }
@@ -48,7 +60,7 @@ impl<S> StorageCell<S> {
     fn inner_try_claim(&self) -> bool {
         self.1
             .compare_exchange(false, true, Ordering::SeqCst, Ordering::Acquire)
-            .map_or(false, |val| !val)
+            .is_ok_and(|val| !val)
     }
 
     fn inner_try_release(&self) -> bool {
@@ -1,8 +1,9 @@
 use core::alloc::Layout;
 #[cfg(feature = "unsize")]
 use core::marker::Unsize;
-use core::mem;
+use core::ops::Range;
 use core::ptr::{NonNull, Pointee};
+use core::{mem, ptr};
 
 use super::StorageCell;
 use crate::base::{ExactSizeStorage, MultiItemStorage, Storage, StorageSafe};
@@ -12,8 +13,16 @@ use crate::statics::traits::StaticStorage;
 use crate::utils;
 
 /// Static multi-element storage implementation
+///
+/// Items take up a single slot if they fit in one, otherwise a run of consecutive slots. Growing a
+/// slice may also spread it over a run, relocating it if the slots directly after it are in use.
+///
+/// By default, allocations take the lowest free slot. See [`MultiStatic::round_robin`] to
+/// assign slots in rotation instead.
 pub struct MultiStatic<S: 'static, const N: usize> {
     used: [bool; N],
+    /// Slot to start searching for free space from, if allocating round-robin
+    cursor: Option<usize>,
     storage: &'static StorageCell<[S; N]>,
 }
 
@@ -21,11 +30,65 @@ impl<S: 'static, const N: usize> StaticStorage<[S; N]> for MultiStatic<S, N> {
     fn take_cell(storage: &'static StorageCell<[S; N]>) -> MultiStatic<S, N> {
         MultiStatic {
             used: [false; N],
+            cursor: None,
             storage,
         }
     }
 }
 
+impl<S: 'static, const N: usize> MultiStatic<S, N> {
+    /// Make this storage assign slots round-robin, searching for a free slot after the most
+    /// recently allocated one instead of from the lowest index. This spreads reuse across all
+    /// slots, rather than repeatedly reusing the first ones.
+    pub fn round_robin(mut self) -> MultiStatic<S, N> {
+        self.cursor = Some(0);
+        self
+    }
+}
+
+impl<S, const N: usize> MultiStatic<S, N>
+where
+    S: StorageSafe,
+{
+    /// Number of slots needed to hold a value with the given layout
+    fn slots_for(layout: Layout) -> usize {
+        match mem::size_of::<S>() {
+            0 => 1,
+            size => usize::max(1, layout.size().div_ceil(size)),
+        }
+    }
+
+    fn range_free(&self, range: Range<usize>) -> bool {
+        self.used
+            .get(range)
+            .is_some_and(|slots| slots.iter().all(|used| !*used))
+    }
+
+    /// Find the first run of `count` free slots
+    fn find_run(&self, count: usize) -> Option<usize> {
+        let mut run = 0;
+        for (idx, used) in self.used.iter().enumerate() {
+            if *used {
+                run = 0;
+            } else {
+                run += 1;
+                if run == count {
+                    return Some(idx + 1 - count);
+                }
+            }
+        }
+        None
+    }
+
+    fn slot_ptr(&self, offset: usize) -> NonNull<S> {
+        // SAFETY: The inner Cell must be claimed as that's the only way to construct a MultiStatic
+        let store_ptr = unsafe { self.storage.as_ptr() };
+        // SAFETY: The storage pointer is guaranteed valid to dereference
+        let idx = unsafe { ptr::addr_of_mut!((*store_ptr.as_ptr())[offset]) };
+        NonNull::new(idx).unwrap()
+    }
+}
+
 // SAFETY: Internal locks and checks ensure memory safety
 unsafe impl<S, const N: usize> Storage for MultiStatic<S, N>
 where
@@ -34,11 +97,11 @@ where
     type Handle<T: ?Sized> = OffsetMetaHandle<T>;
 
     unsafe fn get<T: ?Sized>(&self, handle: Self::Handle<T>) -> NonNull<T> {
-        // SAFETY: The inner Cell must be claimed as that's the only way to construct a SingleStatic
-        let store_ptr = unsafe { self.storage.as_ptr() };
-        // SAFETY: The storage pointer is guaranteed valid to dereference
-        let idx = unsafe { core::ptr::addr_of_mut!((*store_ptr.as_ptr())[handle.offset()]) };
-        let ptr: NonNull<()> = NonNull::new(idx).unwrap().cast();
+        utils::strict_assert!(
+            self.is_allocated(handle),
+            "MultiStatic handle doesn't point to an allocation"
+        );
+        let ptr: NonNull<()> = self.slot_ptr(handle.offset()).cast();
         NonNull::from_raw_parts(ptr, handle.metadata())
     }
 
@@ -66,6 +129,14 @@ where
         handle.coerce()
     }
 
+    fn will_fit_hint<T: ?Sized + Pointee>(&self, meta: T::Metadata) -> Option<bool> {
+        Some(self.will_fit::<T>(meta))
+    }
+
+    fn max_range_hint<T>(&self) -> usize {
+        self.max_range::<T>()
+    }
+
     fn allocate_single<T: ?Sized + Pointee>(
         &mut self,
         meta: T::Metadata,
@@ -86,17 +157,35 @@ where
         debug_assert!(capacity >= handle.metadata());
         let new_layout = Layout::array::<T>(capacity).map_err(|_| StorageError::exceeds_max())?;
 
-        if self.will_fit::<[T]>(capacity) {
-            Ok(OffsetMetaHandle::from_offset_meta(
-                handle.offset(),
-                capacity,
-            ))
-        } else {
-            Err(StorageError::InsufficientSpace {
+        let start = handle.offset();
+        let old_slots =
+            Self::slots_for(Layout::array::<T>(handle.metadata()).expect("Valid handle"));
+        let new_slots = Self::slots_for(new_layout);
+
+        // Grow in place if the slots directly after this allocation are free
+        if self.range_free(start + old_slots..start + new_slots) {
+            self.used[start + old_slots..start + new_slots].fill(true);
+            return Ok(OffsetMetaHandle::from_offset_meta(start, capacity));
+        }
+
+        // Otherwise relocate, possibly overlapping the slots we currently hold
+        self.used[start..start + old_slots].fill(false);
+        let Some(new_start) = self.find_run(new_slots) else {
+            self.used[start..start + old_slots].fill(true);
+            return Err(StorageError::InsufficientSpace {
                 expected: new_layout.size(),
                 available: Some(self.max_range::<T>()),
-            })
-        }
+            });
+        };
+        self.used[new_start..new_start + new_slots].fill(true);
+
+        let old_ptr = self.slot_ptr(start).cast::<T>();
+        let new_ptr = self.slot_ptr(new_start).cast::<T>();
+        // SAFETY: Both pointers are into our backing, and valid for the old length of the slice.
+        //         The ranges may overlap, which `copy` allows.
+        unsafe { ptr::copy(old_ptr.as_ptr(), new_ptr.as_ptr(), handle.metadata()) };
+
+        Ok(OffsetMetaHandle::from_offset_meta(new_start, capacity))
     }
 
     unsafe fn try_shrink<T>(
@@ -105,6 +194,12 @@ where
         capacity: usize,
     ) -> Result<Self::Handle<[T]>> {
         debug_assert!(capacity <= handle.metadata());
+        let start = handle.offset();
+        let old_slots =
+            Self::slots_for(Layout::array::<T>(handle.metadata()).expect("Valid handle"));
+        let new_slots = Self::slots_for(Layout::array::<T>(capacity).expect("Valid handle"));
+        self.used[start + new_slots..start + old_slots].fill(false);
+
         Ok(OffsetMetaHandle::from_offset_meta(
             handle.offset(),
             capacity,
@@ -118,21 +213,38 @@ where
     S: StorageSafe,
 {
     fn allocate<T: ?Sized + Pointee>(&mut self, meta: T::Metadata) -> Result<Self::Handle<T>> {
-        utils::validate_layout::<T, S>(meta)?;
+        let layout = utils::layout_of::<T>(meta)?;
+        // Slots are contiguous, so an item needs to fit in all of them and be aligned like one
+        utils::validate_layout_for::<[S; N]>(layout)?;
 
-        let pos = self
-            .used
-            .iter()
-            .position(|i| !*i)
-            .ok_or(StorageError::NoSlots)?;
+        let slots = Self::slots_for(layout);
+        let pos = if slots == 1 {
+            utils::find_free_slot(&self.used, self.cursor)
+        } else {
+            self.find_run(slots)
+        }
+        .ok_or(StorageError::NoSlots)?;
 
-        self.used[pos] = true;
+        self.used[pos..pos + slots].fill(true);
+        if let Some(cursor) = &mut self.cursor {
+            *cursor = (pos + slots) % N;
+        }
 
         Ok(OffsetMetaHandle::from_offset_meta(pos, meta))
     }
 
     unsafe fn deallocate<T: ?Sized + Pointee>(&mut self, handle: Self::Handle<T>) {
-        self.used[handle.offset()] = false;
+        utils::strict_assert!(
+            self.used[handle.offset()],
+            "MultiStatic handle was already deallocated"
+        );
+        let slots =
+            Self::slots_for(utils::layout_of::<T>(handle.metadata()).expect("Valid handle"));
+        self.used[handle.offset()..handle.offset() + slots].fill(false);
+    }
+
+    fn is_allocated<T: ?Sized + Pointee>(&self, handle: Self::Handle<T>) -> bool {
+        self.used.get(handle.offset()) == Some(&true)
     }
 }
 
@@ -141,13 +253,12 @@ where
     S: StorageSafe,
 {
     fn will_fit<T: ?Sized + Pointee>(&self, meta: T::Metadata) -> bool {
-        let layout = utils::layout_of::<T>(meta);
-        mem::size_of::<S>() >= layout.size()
+        utils::validate_layout::<T, [S; N]>(meta).is_ok()
     }
 
     fn max_range<T>(&self) -> usize {
         let layout = Layout::new::<T>();
-        mem::size_of::<S>() / layout.size()
+        mem::size_of::<[S; N]>() / layout.size()
     }
 }
 
@@ -161,7 +272,7 @@ impl<S, const N: usize> Drop for MultiStatic<S, N> {
 mod tests {
     use super::*;
     use crate::backing::{Align8, Backing};
-    use crate::collections::LinkedList;
+    use crate::collections::{LinkedList, Vec};
 
     #[test]
     fn test_linked_list() {
@@ -175,4 +286,86 @@ mod tests {
         assert_eq!(list.get(1), Some(&2));
         assert_eq!(list.get(3), None);
     }
+
+    #[test]
+    fn test_round_robin() {
+        static FOO: StorageCell<[u32; 3]> = StorageCell::new([0; 3]);
+
+        let mut storage = FOO.claim::<MultiStatic<_, 3>>().round_robin();
+        let a = storage.allocate::<u32>(()).unwrap();
+        storage.allocate::<u32>(()).unwrap();
+        unsafe { storage.deallocate(a) };
+        assert_eq!(storage.allocate::<u32>(()).unwrap().offset(), 2);
+        assert_eq!(storage.allocate::<u32>(()).unwrap().offset(), 0);
+    }
+
+    #[test]
+    fn test_is_allocated() {
+        static FOO: StorageCell<[u32; 2]> = StorageCell::new([0; 2]);
+
+        let mut storage = FOO.claim::<MultiStatic<_, 2>>();
+        let a = storage.allocate::<u32>(()).unwrap();
+        assert!(storage.is_allocated(a));
+        unsafe { storage.deallocate(a) };
+        assert!(!storage.is_allocated(a));
+        assert!(!storage.is_allocated(OffsetMetaHandle::<u32>::from_offset_meta(2, ())));
+    }
+
+    #[test]
+    fn test_allocate_run() {
+        static FOO: StorageCell<[u32; 4]> = StorageCell::new([0; 4]);
+
+        let mut storage = FOO.claim::<MultiStatic<_, 4>>();
+        let a = storage.allocate::<u32>(()).unwrap();
+        // Items larger than a slot take a run of them
+        let b = storage.create::<[u32; 3]>([1, 2, 3]).unwrap();
+        assert_eq!(b.offset(), 1);
+        assert_eq!(unsafe { storage.get(b).as_ref() }, &[1, 2, 3]);
+        assert!(storage.allocate::<u32>(()).is_err());
+        assert!(!storage.will_fit::<[u32; 5]>(()));
+
+        unsafe { storage.deallocate(b) };
+        unsafe { storage.deallocate(a) };
+        storage.allocate::<[u32; 4]>(()).unwrap();
+    }
+
+    #[test]
+    fn test_grow_relocate() {
+        static FOO: StorageCell<[Backing<8, Align8>; 8]> = StorageCell::new([Backing::new(); 8]);
+
+        let mut storage = FOO.claim::<MultiStatic<_, 8>>();
+        let handle = storage.allocate_single::<[u32]>(2).unwrap();
+        let blocker = storage.allocate_single::<u64>(()).unwrap();
+        assert_eq!(blocker.offset(), 1);
+
+        unsafe { storage.get(handle).as_mut().copy_from_slice(&[1, 2]) };
+        let handle = unsafe { storage.try_grow(handle, 6) }.unwrap();
+        assert_eq!(handle.offset(), 2);
+        assert_eq!(unsafe { &storage.get(handle).as_ref()[..2] }, &[1, 2]);
+
+        let handle = unsafe { storage.try_grow(handle, 10) }.unwrap();
+        assert_eq!(handle.offset(), 2);
+        assert!(unsafe { storage.try_grow(handle, 16) }.is_err());
+
+        unsafe { storage.deallocate_single(blocker) };
+        let handle = unsafe { storage.try_grow(handle, 16) }.unwrap();
+        assert_eq!(handle.offset(), 0);
+        assert_eq!(unsafe { &storage.get(handle).as_ref()[..2] }, &[1, 2]);
+
+        let handle = unsafe { storage.try_shrink(handle, 2) }.unwrap();
+        let other = storage.allocate_single::<u64>(()).unwrap();
+        assert_eq!(other.offset(), 1);
+        unsafe { storage.deallocate_single(handle) };
+        unsafe { storage.deallocate_single(other) };
+    }
+
+    #[test]
+    fn test_vec_grow() {
+        static FOO: StorageCell<[Backing<8, Align8>; 4]> = StorageCell::new([Backing::new(); 4]);
+
+        let mut v = Vec::<u16, MultiStatic<Backing<8, Align8>, 4>>::new_in(FOO.claim());
+        v.extend(0..16);
+        assert_eq!(v.as_ref(), &(0..16).collect::<std::vec::Vec<_>>());
+        assert!(v.try_extend([16]).is_err());
+    }
 }
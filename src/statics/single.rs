@@ -57,6 +57,14 @@ where
         handle.coerce()
     }
 
+    fn will_fit_hint<T: ?Sized + Pointee>(&self, meta: T::Metadata) -> Option<bool> {
+        Some(self.will_fit::<T>(meta))
+    }
+
+    fn max_range_hint<T>(&self) -> usize {
+        self.max_range::<T>()
+    }
+
     fn allocate_single<T: ?Sized + Pointee>(
         &mut self,
         meta: T::Metadata,
@@ -100,8 +108,7 @@ where
     S: StorageSafe,
 {
     fn will_fit<T: ?Sized + Pointee>(&self, meta: T::Metadata) -> bool {
-        let layout = utils::layout_of::<T>(meta);
-        mem::size_of::<S>() >= layout.size()
+        utils::layout_of::<T>(meta).is_ok_and(|layout| mem::size_of::<S>() >= layout.size())
     }
 
     fn max_range<T>(&self) -> usize {
@@ -201,6 +208,19 @@ mod tests {
         assert_eq!(*b, ());
     }
 
+    #[test]
+    fn test_force_release() {
+        static FOO: StorageCell<[usize; 4]> = StorageCell::new([0; 4]);
+
+        let storage = FOO.claim::<SingleStatic<_>>();
+        core::mem::forget(storage);
+        assert!(FOO.try_claim::<SingleStatic<_>>().is_none());
+
+        unsafe { FOO.force_release() };
+        let b = Box::<_, SingleStatic<[usize; 4]>>::new_in(1, FOO.claim());
+        assert_eq!(*b, 1);
+    }
+
     #[test]
     #[ignore = "This test is for human-readable output, and does not actually panic"]
     fn test_atomic() {
@@ -1,12 +1,16 @@
 //! A storage-based implementation of [`std::string`]
 
 use core::borrow::Borrow;
-use core::ops::Deref;
+use core::hash::{Hash, Hasher};
+use core::ops::{Deref, RangeBounds};
 use core::{fmt, ops};
 
 use crate::base::Storage;
+#[cfg(feature = "box")]
+use crate::boxed::Box;
 use crate::collections::Vec;
-use crate::error::Result;
+use crate::error::{Result, StorageError};
+use crate::utils;
 
 /// Storage based implementation of [`String`](std::string::String)
 pub struct String<S>
@@ -21,10 +25,6 @@ where
     S: Storage + Default,
 {
     /// Create a new, empty `String` with a default instance of the desired storage
-    ///
-    /// # Panics
-    ///
-    /// If the backing allocation fails for any reason
     pub fn new() -> String<S> {
         String { inner: Vec::new() }
     }
@@ -42,10 +42,6 @@ where
     S: Storage,
 {
     /// Create a new, empty `String` with the provided storage instance
-    ///
-    /// # Panics
-    ///
-    /// If the backing allocation fails for any reason
     pub fn new_in(storage: S) -> String<S> {
         String {
             inner: Vec::new_in(storage),
@@ -58,6 +54,129 @@ where
             inner: Vec::try_new_in(storage)?,
         })
     }
+
+    /// Push characters from an iterator onto the end of the string until it's exhausted, or the
+    /// string fails to grow. A character is only pushed if there's space for all of its bytes.
+    ///
+    /// On failure, the error is returned alongside the character that didn't fit and the
+    /// unconsumed remainder of the iterator. See [`Vec::try_extend`].
+    pub fn try_extend<I>(
+        &mut self,
+        iter: I,
+    ) -> core::result::Result<(), (StorageError, char, I::IntoIter)>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let mut iter = iter.into_iter();
+        while let Some(ch) = iter.next() {
+            let mut buf = [0; 4];
+            let bytes = ch.encode_utf8(&mut buf).as_bytes();
+            if let Err(err) = self.inner.try_grow_for(bytes.len()) {
+                return Err((err, ch, iter));
+            }
+            self.inner.extend(bytes.iter().copied());
+        }
+        Ok(())
+    }
+
+    /// Append a string slice onto the end of this string
+    ///
+    /// # Panics
+    ///
+    /// If the string needs to grow and the backing storage fails to do so
+    pub fn push_str(&mut self, s: &str) {
+        self.inner.extend_from_slice(s.as_bytes());
+    }
+
+    /// Insert a string slice into this string at byte position `idx`
+    ///
+    /// # Panics
+    ///
+    /// If `idx` is greater than the string's length or does not lie on a char boundary, or if the
+    /// string needs to grow and the backing storage fails to do so
+    pub fn insert_str(&mut self, idx: usize, s: &str) {
+        assert!(
+            self.is_char_boundary(idx),
+            "String::insert_str called with an index not on a char boundary"
+        );
+        self.inner.extend_from_slice(s.as_bytes());
+        (*self.inner)[idx..].rotate_right(s.len());
+    }
+
+    /// Replace the given byte range of this string with a string slice. The replacement doesn't
+    /// need to be the same length as the range.
+    ///
+    /// # Panics
+    ///
+    /// If the range is out of bounds or either end does not lie on a char boundary, or if the
+    /// string needs to grow and the backing storage fails to do so
+    pub fn replace_range<R>(&mut self, range: R, replace_with: &str)
+    where
+        R: RangeBounds<usize>,
+    {
+        let range = utils::resolve_range(range, self.len());
+        assert!(
+            self.is_char_boundary(range.start) && self.is_char_boundary(range.end),
+            "String::replace_range called with a range not on char boundaries"
+        );
+
+        let bytes = replace_with.as_bytes();
+        let (overlap, rest) = bytes.split_at(usize::min(bytes.len(), range.len()));
+        (*self.inner)[range.start..range.start + overlap.len()].copy_from_slice(overlap);
+
+        if rest.is_empty() {
+            // Shift the tail back over the leftover part of the range, then cut it off
+            let excess = range.len() - overlap.len();
+            (*self.inner)[range.start + overlap.len()..].rotate_left(excess);
+            let new_len = self.len() - excess;
+            // SAFETY: The new length is shorter than the current one, and the bytes up to it
+            //         are the string's prefix, replacement, then tail, so are valid UTF-8
+            unsafe { self.inner.set_len(new_len) };
+        } else {
+            // Append the rest of the replacement, then rotate it into place before the tail
+            self.inner.extend_from_slice(rest);
+            (*self.inner)[range.end..].rotate_right(rest.len());
+        }
+    }
+
+    /// Shrink the capacity of the string as close to its length as the storage allows. See
+    /// [`Vec::shrink_to_fit`].
+    pub fn shrink_to_fit(&mut self) {
+        self.inner.shrink_to_fit();
+    }
+
+    /// Shrink the capacity of the string as close to the larger of its length and `min_capacity`
+    /// as the storage allows. See [`Vec::shrink_to`].
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.inner.shrink_to(min_capacity);
+    }
+
+    /// Remove the last character from this string and return it, or `None` if it is empty
+    pub fn pop(&mut self) -> Option<char> {
+        // Walks backwards over any continuation bytes to the start of the last character
+        let ch = self.chars().next_back()?;
+        let new_len = self.len() - ch.len_utf8();
+        // SAFETY: `new_len` is the start of the last character, so is a char boundary and
+        //         shorter than the current length
+        unsafe { self.inner.set_len(new_len) };
+        Some(ch)
+    }
+
+    /// Shorten this string to `new_len` bytes. If `new_len` is greater than the current length,
+    /// this has no effect.
+    ///
+    /// # Panics
+    ///
+    /// If `new_len` does not lie on a char boundary
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len <= self.len() {
+            assert!(
+                self.is_char_boundary(new_len),
+                "String::truncate called with a new length not on a char boundary"
+            );
+            self.inner.truncate(new_len);
+        }
+    }
 }
 
 impl<S> fmt::Debug for String<S>
@@ -78,15 +197,28 @@ where
     }
 }
 
-impl<S> PartialEq for String<S>
+impl<S1, S2> PartialEq<String<S2>> for String<S1>
 where
-    S: Storage,
+    S1: Storage,
+    S2: Storage,
 {
-    fn eq(&self, other: &Self) -> bool {
+    fn eq(&self, other: &String<S2>) -> bool {
         **self == **other
     }
 }
 
+impl<S> Eq for String<S> where S: Storage {}
+
+impl<S> Hash for String<S>
+where
+    S: Storage,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Must hash the same as `str`, for `Borrow<str>` to be correct
+        (**self).hash(state)
+    }
+}
+
 impl<S> PartialEq<str> for String<S>
 where
     S: Storage,
@@ -96,6 +228,55 @@ where
     }
 }
 
+impl<S> PartialEq<&str> for String<S>
+where
+    S: Storage,
+{
+    fn eq(&self, other: &&str) -> bool {
+        **self == **other
+    }
+}
+
+impl<S> PartialEq<String<S>> for str
+where
+    S: Storage,
+{
+    fn eq(&self, other: &String<S>) -> bool {
+        *self == **other
+    }
+}
+
+impl<S> PartialEq<String<S>> for &str
+where
+    S: Storage,
+{
+    fn eq(&self, other: &String<S>) -> bool {
+        **self == **other
+    }
+}
+
+#[cfg(feature = "box")]
+impl<S1, S2> PartialEq<Box<str, S2>> for String<S1>
+where
+    S1: Storage,
+    S2: Storage,
+{
+    fn eq(&self, other: &Box<str, S2>) -> bool {
+        **self == **other
+    }
+}
+
+#[cfg(feature = "box")]
+impl<S1, S2> PartialEq<String<S2>> for Box<str, S1>
+where
+    S1: Storage,
+    S2: Storage,
+{
+    fn eq(&self, other: &String<S2>) -> bool {
+        **self == **other
+    }
+}
+
 impl<S> Default for String<S>
 where
     S: Storage + Default,
@@ -134,11 +315,50 @@ where
     type Output = String<S>;
 
     fn add(mut self, rhs: &str) -> Self::Output {
-        self.inner.extend(rhs.as_bytes().iter().copied());
+        self.push_str(rhs);
         self
     }
 }
 
+impl<S> Extend<char> for String<S>
+where
+    S: Storage,
+{
+    fn extend<I: IntoIterator<Item = char>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        // Every char is at least one byte, so reserve that much up-front
+        self.inner.grow_for(iter.size_hint().0);
+        iter.for_each(|ch| {
+            let mut buf = [0; 4];
+            let bytes = ch.encode_utf8(&mut buf).as_bytes();
+            self.inner.extend(bytes.iter().copied());
+        });
+    }
+}
+
+impl<'a, S> Extend<&'a str> for String<S>
+where
+    S: Storage,
+{
+    fn extend<I: IntoIterator<Item = &'a str>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        // Pieces are usually non-empty, so reserve a byte for each of them up-front
+        self.inner.grow_for(iter.size_hint().0);
+        iter.for_each(|s| self.push_str(s));
+    }
+}
+
+impl<'a, S> FromIterator<&'a str> for String<S>
+where
+    S: Storage + Default,
+{
+    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> String<S> {
+        let mut out = String::new();
+        out.extend(iter);
+        out
+    }
+}
+
 impl<S> Deref for String<S>
 where
     S: Storage,
@@ -172,6 +392,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::alloc::GlobalAlloc;
     use crate::inline::SingleInline;
 
     #[test]
@@ -180,4 +401,158 @@ mod tests {
 
         assert_eq!(&s, "Hello World!");
     }
+
+    #[test]
+    fn test_pop() {
+        let mut s = String::<SingleInline<[u8; 20]>>::from("aé😀");
+
+        assert_eq!(s.pop(), Some('😀'));
+        assert_eq!(&s, "aé");
+        assert_eq!(s.pop(), Some('é'));
+        assert_eq!(s.pop(), Some('a'));
+        assert_eq!(s.pop(), None);
+        assert_eq!(&s, "");
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut s = String::<SingleInline<[u8; 20]>>::from("Hello é!");
+
+        s.truncate(20);
+        assert_eq!(&s, "Hello é!");
+        s.truncate(8);
+        assert_eq!(&s, "Hello é");
+        s.truncate(6);
+        assert_eq!(&s, "Hello ");
+    }
+
+    #[test]
+    #[should_panic = "not on a char boundary"]
+    fn test_truncate_boundary() {
+        let mut s = String::<SingleInline<[u8; 20]>>::from("é");
+        s.truncate(1);
+    }
+
+    #[test]
+    fn test_hash_borrow() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::collections::HashMap;
+
+        fn hash<T: ?Sized + Hash>(val: &T) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            val.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let s = String::<SingleInline<[u8; 20]>>::from("x");
+        assert_eq!(hash(&s), hash("x"));
+        assert_eq!(hash(&s), hash::<str>(s.borrow()));
+
+        // The inline storage has interior mutability, but that never affects the hash
+        #[allow(clippy::mutable_key_type)]
+        let mut map = HashMap::new();
+        map.insert(s, 1);
+        assert_eq!(map.get("x"), Some(&1));
+        assert_eq!(map.get("y"), None);
+    }
+
+    #[test]
+    fn test_try_extend() {
+        let mut s = String::<SingleInline<[u8; 4]>>::new();
+
+        s.try_extend("ab".chars()).unwrap();
+        assert_eq!(&s, "ab");
+
+        let (_, ch, mut rest) = s.try_extend("é😀".chars()).unwrap_err();
+        assert_eq!(&s, "abé");
+        assert_eq!(ch, '😀');
+        assert_eq!(rest.next(), None);
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut s = String::<GlobalAlloc>::new();
+
+        s.extend(['a', 'b', 'c']);
+        assert_eq!(s.inner.capacity(), 3);
+        s.extend("dé".chars());
+        assert_eq!(&s, "abcdé");
+    }
+
+    #[test]
+    fn test_extend_str() {
+        let mut s = String::<GlobalAlloc>::from("a");
+        s.extend(["bc", "", "def"]);
+        assert_eq!(&*s, "abcdef");
+
+        let s: String<GlobalAlloc> = "a b c".split(' ').collect();
+        assert_eq!(&*s, "abc");
+    }
+
+    #[test]
+    fn test_insert_str() {
+        let mut s = String::<GlobalAlloc>::from("hllo");
+        s.insert_str(1, "e");
+        s.insert_str(5, " wörld");
+        s.insert_str(0, "");
+        assert_eq!(&*s, "hello wörld");
+    }
+
+    #[test]
+    #[should_panic = "not on a char boundary"]
+    fn test_insert_str_boundary() {
+        let mut s = String::<GlobalAlloc>::from("ö");
+        s.insert_str(1, "a");
+    }
+
+    #[test]
+    fn test_replace_range() {
+        let mut s = String::<GlobalAlloc>::from("hello world");
+        s.replace_range(6.., "wörld");
+        assert_eq!(&*s, "hello wörld");
+        s.replace_range(..5, "hi");
+        assert_eq!(&*s, "hi wörld");
+        s.replace_range(3..3, "big ");
+        assert_eq!(&*s, "hi big wörld");
+        s.replace_range(2..=6, "");
+        assert_eq!(&*s, "hiwörld");
+    }
+
+    #[test]
+    #[should_panic = "not on char boundaries"]
+    fn test_replace_range_boundary() {
+        let mut s = String::<GlobalAlloc>::from("wörld");
+        s.replace_range(..2, "a");
+    }
+
+    #[test]
+    fn test_shrink_to() {
+        let mut s = String::<SingleInline<[u8; 16]>>::new();
+        s.push_str("hello");
+        s.inner.grow_for(8);
+
+        s.shrink_to(8);
+        assert_eq!(s.inner.capacity(), 8);
+        s.shrink_to_fit();
+        assert_eq!(s.inner.capacity(), 5);
+        assert_eq!(&*s, "hello");
+    }
+
+    #[test]
+    #[cfg(feature = "box")]
+    fn test_eq_box() {
+        use crate::boxed::Box;
+
+        let s = String::<GlobalAlloc>::from("hello");
+        let b = Box::<str, SingleInline<[u8; 8]>>::from("hello");
+
+        assert_eq!(s, b);
+        assert_eq!(b, s);
+        assert_eq!(s, "hello");
+        assert_eq!("hello", s);
+        assert_eq!(b, "hello");
+        assert_eq!("hello", b);
+        assert_eq!(s, String::<SingleInline<[u8; 8]>>::from("hello"));
+        assert_ne!(s, Box::<str, GlobalAlloc>::from("world"));
+    }
 }
@@ -1,21 +1,108 @@
 use core::alloc::Layout;
+#[cfg(feature = "vec")]
+use core::ops::{Bound, Range, RangeBounds};
 use core::ptr::Pointee;
 use core::{mem, ptr};
 
 use crate::error::Result;
 use crate::error::StorageError;
 
+/// Assert an invariant of a handle passed to a built-in storage. This only checks anything in debug
+/// builds with the `strict` feature enabled.
+#[cfg(any(feature = "heap", feature = "inline", feature = "static"))]
+macro_rules! strict_assert {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "strict")]
+        debug_assert!($($arg)*);
+    };
+}
+
+#[cfg(any(feature = "heap", feature = "inline", feature = "static"))]
+pub(crate) use strict_assert;
+
 /// Get the layout for a possibly unsized type, provided the type's metadata. This method is
-/// the sketchiest part of department - it relies on meta being valid
-pub(crate) fn layout_of<T: ?Sized + Pointee>(meta: T::Metadata) -> Layout {
-    let pointer = ptr::from_raw_parts(ptr::null::<()>(), meta);
-    // SAFETY: The provided metadata is passed by value, and thus must be a valid instance of the
-    //         metadata for `T`
-    unsafe { Layout::for_value_raw::<T>(pointer) }
+/// the sketchiest part of department - it relies on meta being valid. The length of slices and
+/// slice-tailed types is checked, returning an error if the resulting size would overflow
+/// `isize::MAX`.
+pub(crate) fn layout_of<T: ?Sized + Pointee>(meta: T::Metadata) -> Result<Layout> {
+    <T as LayoutOf>::layout_of(meta)
+}
+
+/// Helper to compute the layout of a type from its metadata, specialized for types whose metadata
+/// may describe a value too large to exist
+trait LayoutOf: Pointee {
+    fn layout_of(meta: Self::Metadata) -> Result<Layout>;
+}
+
+impl<T: ?Sized + Pointee> LayoutOf for T {
+    default fn layout_of(meta: T::Metadata) -> Result<Layout> {
+        meta.check_tail::<T>()?;
+        let pointer = ptr::from_raw_parts(ptr::null::<()>(), meta);
+        // SAFETY: The provided metadata is passed by value, and thus must be a valid instance of
+        //         the metadata for `T`. If it's the length of a slice tail, the size it results in
+        //         was checked above.
+        Ok(unsafe { Layout::for_value_raw::<T>(pointer) })
+    }
+}
+
+/// Helper to check the metadata of a type, specialized for the length of a slice tail
+trait CheckTail: Copy {
+    fn check_tail<T: ?Sized + Pointee<Metadata = Self>>(self) -> Result<()>;
+}
+
+impl<M: Copy> CheckTail for M {
+    default fn check_tail<T: ?Sized + Pointee<Metadata = M>>(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl CheckTail for usize {
+    fn check_tail<T: ?Sized + Pointee<Metadata = usize>>(self) -> Result<()> {
+        let layout_with = |len| {
+            let pointer = ptr::from_raw_parts::<T>(ptr::null::<()>(), len);
+            // SAFETY: Only called with zero or one elements, or with `align` elements once their
+            //         size is known not to overflow `isize::MAX`
+            unsafe { Layout::for_value_raw::<T>(pointer) }
+        };
+
+        let head = layout_with(0);
+        let align = head.align();
+        // A single element is never larger than a value holding exactly one
+        let upper = layout_with(1).size();
+        // A tail of `align` elements needs no padding, so gives the exact size of an element. If
+        // that could overflow, the upper bound is used instead.
+        let elem = match align
+            .checked_mul(upper)
+            .and_then(|tail| tail.checked_add(head.size()))
+        {
+            Some(size) if size <= isize::MAX as usize => {
+                (layout_with(align).size() - head.size()) / align
+            }
+            _ => upper,
+        };
+
+        elem.checked_mul(self)
+            .and_then(|tail| tail.checked_add(head.size()))
+            .and_then(|size| Layout::from_size_align(size, align).ok())
+            .map(|_| ())
+            .ok_or(StorageError::exceeds_max())
+    }
+}
+
+impl<T> LayoutOf for [T] {
+    fn layout_of(meta: usize) -> Result<Layout> {
+        Layout::array::<T>(meta).map_err(|_| StorageError::exceeds_max())
+    }
+}
+
+impl LayoutOf for str {
+    fn layout_of(meta: usize) -> Result<Layout> {
+        Layout::array::<u8>(meta).map_err(|_| StorageError::exceeds_max())
+    }
 }
 
 pub(crate) fn validate_layout<T: ?Sized + Pointee, S>(meta: T::Metadata) -> Result<()> {
-    validate_layout_for::<S>(layout_of::<T>(meta))
+    validate_layout_for::<S>(layout_of::<T>(meta)?)
 }
 
 pub(crate) fn validate_layout_for<S>(layout: Layout) -> Result<()> {
@@ -36,3 +123,43 @@ pub(crate) fn validate_layout_for<S>(layout: Layout) -> Result<()> {
         })
     }
 }
+
+/// Find a free slot in a slot-based storage, searching from `cursor` and wrapping around if it's
+/// set, or from the start if not
+#[cfg(any(feature = "inline", feature = "static"))]
+pub(crate) fn find_free_slot(used: &[bool], cursor: Option<usize>) -> Option<usize> {
+    let start = cursor.unwrap_or(0);
+    (start..used.len())
+        .chain(0..start)
+        .find(|&slot| !used[slot])
+}
+
+/// Resolve a range against a slice length, panicking if it's out of order or out of bounds
+#[cfg(feature = "vec")]
+pub(crate) fn resolve_range<R: RangeBounds<usize>>(range: R, len: usize) -> Range<usize> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start
+            .checked_add(1)
+            .expect("attempted to index slice from after maximum usize"),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end
+            .checked_add(1)
+            .expect("attempted to index slice up to maximum usize"),
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+
+    assert!(
+        start <= end,
+        "slice index starts at {start} but ends at {end}"
+    );
+    assert!(
+        end <= len,
+        "range end index {end} out of range for slice of length {len}"
+    );
+
+    start..end
+}